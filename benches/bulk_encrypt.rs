@@ -0,0 +1,89 @@
+//! Bulk-encrypt every value of a generated config through the YAML editor
+//! state — the same calls the UI makes after each crypto result — and compare
+//! with re-parsing the whole file per value, which is what edits used to do.
+//!
+//! ```sh
+//! cargo bench --bench bulk_encrypt
+//! ```
+//!
+//! Per value, the in-place path only splices the text and shifts the spans
+//! after it, while re-parsing costs a full parse per value.
+
+use std::time::{Duration, Instant};
+
+use lazyprop::state::Operation;
+use lazyprop::text_field::TextField;
+use lazyprop::yaml_editor::document::{self, Document};
+use lazyprop::yaml_editor::state::{SearchMode, YamlEditorState};
+
+/// A config of `lines` lines: groups of one key line plus four values.
+fn config(lines: usize) -> String {
+    let mut out = String::new();
+    for g in 0..lines / 5 {
+        out.push_str(&format!("service{g}:\n"));
+        out.push_str(&format!("  host: host-{g}.internal # owner: team-{g}\n"));
+        out.push_str("  user: admin\n");
+        out.push_str(&format!("  password: 'secret-{g}'\n"));
+        out.push_str(&format!("  port: \"{}\"\n", 8000 + g % 1000));
+    }
+    out
+}
+
+fn bulk_in_place(src: &str) -> (usize, Duration) {
+    let path = std::env::temp_dir().join(format!("lazyprop_bench_{}.yaml", std::process::id()));
+    std::fs::write(&path, src).unwrap();
+    let mut st = YamlEditorState::default();
+    st.open_path(path.to_str().unwrap()).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    st.search = Some(TextField::from_text("**"));
+    st.search_mode = SearchMode::Path;
+    let start = Instant::now();
+    st.start_bulk(Operation::Encrypt).unwrap();
+    let mut n = 0;
+    while let Some((_, value)) = st.next_bulk_value() {
+        st.finish_crypto(Ok(format!("CIPHER+{value}+CIPHER")));
+        n += 1;
+    }
+    let elapsed = start.elapsed();
+    assert!(st.doc().raw().matches("![CIPHER+").count() == n);
+    (n, elapsed)
+}
+
+fn bulk_reparse(src: &str) -> (usize, Duration) {
+    let mut doc = Document::parse(src);
+    let paths: Vec<_> = doc
+        .nodes()
+        .iter()
+        .filter(|n| n.is_editable_scalar())
+        .map(|n| n.path.clone())
+        .collect();
+    let start = Instant::now();
+    for path in &paths {
+        let id = doc.find_by_path(path).unwrap();
+        let value = doc.logical_value(id).unwrap();
+        let token = document::serialize_scalar_quoted(&document::wrap_cipher(&value));
+        let text = doc.replace_scalar_source(id, &token).unwrap();
+        doc = Document::parse(&text);
+    }
+    (paths.len(), start.elapsed())
+}
+
+fn report(label: &str, lines: usize, (n, elapsed): (usize, Duration)) {
+    println!(
+        "{label:>9} {lines:>6} lines  {n:>5} values  {:>9.1?} total  {:>8.1?}/value",
+        elapsed,
+        elapsed / n.max(1) as u32,
+    );
+}
+
+fn main() {
+    for lines in [500, 1_000, 5_000, 20_000] {
+        let src = config(lines);
+        report("in-place", lines, bulk_in_place(&src));
+        // The old path is quadratic; skip the larger sizes to keep runs short.
+        if lines <= 1_000 {
+            report("re-parse", lines, bulk_reparse(&src));
+        }
+    }
+}
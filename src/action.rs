@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use strum::Display;
 
+use crate::detect::Attempt;
 use crate::state::{CryptoTarget, Operation};
 
 #[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
@@ -64,6 +65,19 @@ pub enum Action {
     SendToPlayground,
     /// A background encrypt/decrypt finished.
     CryptoDone(CryptoTarget, Operation, Result<String, String>),
+    /// A background re-key of one YAML-screen value finished, with the new
+    /// `![...]` value.
+    RekeyDone(Result<String, String>),
+    /// Try the value (Main input or selected YAML value) with every
+    /// environment to find the one that encrypted it.
+    Detect,
+    /// A background detect finished.
+    DetectDone(Vec<Attempt>),
+    /// A background verify of one YAML-screen value finished: whether it
+    /// decrypted (the plaintext itself is dropped in the task).
+    VerifyDone(Result<(), String>),
+    /// A background decrypt of one YAML-screen value for peek finished.
+    PeekDone(Result<String, String>),
     /// Start filtering the environments list by name.
     Search,
     /// Open the form to add a new environment.
@@ -94,6 +108,7 @@ impl Action {
             Action::AddEnv => "Add environment",
             Action::EditEnv => "Edit environment",
             Action::DeleteEnv => "Delete environment",
+            Action::Detect => "Detect the environment of a value",
             Action::GoMain => "Go to Main screen",
             Action::GoPlayground => "Go to Playground screen",
             Action::GoAbout => "Go to About screen",
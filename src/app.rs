@@ -13,10 +13,11 @@ use crate::{
     components::{
         about::AboutScreen, home::Home, playground::PlaygroundScreen, yaml::YamlScreen, Component,
     },
-    config::Config,
+    config::{lazyprop_home, Config},
     panes::{footer::FooterPane, header::HeaderPane, Pane},
     state::{CryptoTarget, InputMode, Operation, State},
     tui::{Event, Tui},
+    yaml_editor::{backup::Store, bookmarks::Bookmarks},
 };
 
 pub struct App {
@@ -78,6 +79,19 @@ impl App {
         let (action_tx, action_rx) = mpsc::unbounded_channel();
         let config = Config::new()?;
         crate::theme::init(&config.theme);
+        let mut state = State::new(envs_path, jar_path)?;
+        state.yaml.secret_rules = config.secrets.clone();
+        state.yaml.bookmarks = Bookmarks::load(lazyprop_home().join("bookmarks.yaml"));
+        if let Some(e) = state.yaml.bookmarks.error().map(str::to_string) {
+            state.yaml.report(e, true);
+        }
+        state.yaml.project_links = Some(lazyprop_home().join("project-links.yaml"));
+        if config.backups.keep > 0 {
+            state.yaml.backups = Some(Store::new(
+                lazyprop_home().join("backups"),
+                config.backups.keep,
+            ));
+        }
         Ok(Self {
             tick_rate,
             frame_rate,
@@ -95,7 +109,7 @@ impl App {
             last_tick_key_events: Vec::new(),
             action_tx,
             action_rx,
-            state: State::new(envs_path, jar_path)?,
+            state,
         })
     }
 
@@ -105,6 +119,16 @@ impl App {
         if let Err(e) = self.state.yaml.open_path(path) {
             self.state.yaml.report(e, true);
         }
+        self.state.apply_linked_env();
+        self.state.mode = Mode::Yaml;
+    }
+
+    /// Load a Mule project on startup (from `--project`) and show its config
+    /// files on the YAML screen. Errors are shown there rather than aborting.
+    pub fn open_project(&mut self, dir: &str) {
+        if let Err(e) = self.state.yaml.open_project(dir) {
+            self.state.yaml.report(e, true);
+        }
         self.state.mode = Mode::Yaml;
     }
 
@@ -182,6 +206,12 @@ impl App {
         if self.state.pending_delete.is_some() {
             return self.handle_delete_key_event(key);
         }
+        if self.state.detect.is_some() {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                self.state.close_detect();
+            }
+            return Ok(());
+        }
         // The playground is a self-contained form screen with its own keys.
         if self.state.mode == Mode::Playground {
             return self.handle_playground_key_event(key);
@@ -376,12 +406,15 @@ impl App {
 
     fn handle_actions(&mut self, tui: &mut Tui) -> Result<()> {
         while let Ok(action) = self.action_rx.try_recv() {
-            if action != Action::Tick && action != Action::Render {
+            // Peeked plaintexts stay out of the log.
+            if !matches!(action, Action::Tick | Action::Render | Action::PeekDone(_)) {
                 debug!("{action:?}");
             }
+            self.state.sync_peek();
             match action {
                 Action::Tick => {
                     self.last_tick_key_events.drain(..);
+                    self.state.yaml.poll_disk();
                 }
                 Action::Quit => self.should_quit = true,
                 Action::Suspend => self.should_suspend = true,
@@ -410,6 +443,17 @@ impl App {
                         self.state.yaml_pump_bulk(self.action_tx.clone());
                     }
                 }
+                Action::RekeyDone(ref outcome) => self
+                    .state
+                    .yaml_finish_rekey(self.action_tx.clone(), outcome.clone()),
+                Action::Detect => self.state.begin_detect(self.action_tx.clone()),
+                Action::DetectDone(ref attempts) => self.state.finish_detect(attempts.clone()),
+                Action::VerifyDone(ref outcome) => self
+                    .state
+                    .yaml_finish_verify(self.action_tx.clone(), outcome.clone()),
+                Action::PeekDone(ref outcome) => self
+                    .state
+                    .yaml_finish_peek(self.action_tx.clone(), outcome.clone()),
                 _ => {}
             }
             let active = self.state.mode.component_index();
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use crate::config::{get_config_dir, get_data_dir};
 
@@ -14,16 +14,45 @@ pub struct Cli {
     pub frame_rate: f64,
 
     /// Path to the environments file (overrides the configured envs_path)
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", global = true)]
     pub envs: Option<String>,
 
     /// Path to the Secure Properties Tool jar (overrides the configured jar_path)
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", global = true)]
     pub jar: Option<String>,
 
     /// Open a YAML file in the YAML editor on startup
     #[arg(long, value_name = "FILE")]
     pub file: Option<String>,
+
+    /// Open a Mule project directory and list its config files on startup
+    #[arg(long, value_name = "DIR")]
+    pub project: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Re-key a config file: decrypt every ![...] value with one environment
+    /// and encrypt it with another, without starting the UI
+    Rekey {
+        /// The environment the values are encrypted with now
+        #[arg(long, value_name = "ENV")]
+        from: String,
+
+        /// The environment to encrypt them with
+        #[arg(long, value_name = "ENV")]
+        to: String,
+
+        /// Re-key in memory and print the summary without writing the file
+        #[arg(long)]
+        dry_run: bool,
+
+        /// The YAML, .properties or JSON file to re-key
+        file: String,
+    },
 }
 
 pub const VERSION_MESSAGE: &str = concat!(
@@ -61,4 +90,18 @@ mod tests {
     fn cli_definition_is_valid() {
         Cli::command().debug_assert();
     }
+
+    #[test]
+    fn rekey_takes_global_options_after_the_subcommand() {
+        let cli = Cli::try_parse_from([
+            "lazyprop", "rekey", "--from", "dev", "--to", "prod", "--envs", "e.yaml", "app.yaml",
+        ])
+        .unwrap();
+        assert_eq!(cli.envs.as_deref(), Some("e.yaml"));
+        assert!(matches!(
+            cli.command,
+            Some(Command::Rekey { ref from, ref file, dry_run: false, .. })
+                if from == "dev" && file == "app.yaml"
+        ));
+    }
 }
@@ -100,6 +100,7 @@ fn guide_content(guide: Guide) -> GuideContent {
                         "Tab"    => "Focus the value field",
                         "E"      => "Encrypt value",
                         "D"      => "Decrypt value",
+                        "I"      => "Detect the environment",
                         "Ctrl+Y" => "Copy result",
                         "Esc"    => "Leave the field",
                     ],
@@ -122,38 +123,90 @@ fn guide_content(guide: Guide) -> GuideContent {
             }],
         },
         Guide::Yaml => GuideContent {
-            summary: "Encrypt/decrypt values inside a .yaml/.yml file, in place.",
+            summary: "Encrypt/decrypt values inside a .yaml/.yml, .properties or .json file, in place.",
             blocks: &[
                 GuideBlock {
                     heading: "Open & navigate",
                     keys: keys![
                         "Ctrl+O" => "Open a file (browse / path)",
+                        "[ / ]"  => "Previous / next open file",
+                        "Ctrl+W" => "Close the file",
+                        "P"      => "Project config files (--project)",
+                        "X (in project)" => "Cross-check ${...} placeholders in the XML",
+                        "N (in project)" => "Create the file's environment from the XML",
                         "W / S"  => "Move in the tree",
                         "← / →"  => "Collapse / expand",
                         "Tab"    => "Switch pane",
+                        "G"      => "Go to a path (Tab completes)",
+                        "'"      => "Bookmarks (A adds the selected node)",
                         "/"      => "Search the tree",
+                        "Tab (in search)" => "Mode: text / path / value / regex",
+                        "Shift+Tab (in search)" => "Encrypted / plaintext / modified only",
+                        "Ctrl+F (in search)" => "Jump between matches, don't filter",
+                        "n / N"  => "Next / previous match",
                     ],
-                    note: None,
+                    note: Some("Path queries select nodes by pattern: db.*.password (one level), **.password (any depth), servers[1:3] (index range), /pass.*/ (key regex). Quote keys with dots: \"a.b\".c. While a path query is active, E / D apply to every match; marks (*) take precedence over both. Value and regex searches match a scalar's value as written, so an encrypted one matches its ciphertext."),
                 },
                 GuideBlock {
                     heading: "Edit & crypt",
                     keys: keys![
                         "Enter"           => "Edit scalar",
+                        "#"               => "Edit inline comment",
                         "e / d"           => "Encrypt / decrypt value",
-                        "E / D"           => "Bulk on the subtree",
+                        "E / D"           => "Bulk on the marks / query / subtree",
+                        "Space / M"       => "Mark node / clear marks",
+                        "y"               => "Copy menu: path, value, ciphertext, plaintext, subtree",
+                        "M (in copy)"     => "Copy the marked values",
+                        "!"               => "List plaintext that looks secret",
+                        "E (in ! list)"   => "Encrypt exactly those",
                         "R"               => "Reveal value",
+                        "o / O"           => "Peek: selection / all in view",
                         "A"               => "Add environment",
                         "Ctrl+Z / Ctrl+Y" => "Undo / redo",
+                        "u"               => "Undo history: jump to any step",
                     ],
-                    note: None,
+                    note: Some("Peek shows decrypted values without changing the document; o or O again, another environment or another file wipes them. Comments above a key and at the end of its line are shown under Selected and matched by / search. An empty comment removes it. In .properties files keys are flat, values are stored escaped rather than quoted, and # edits are unavailable; JSON files have no comments either."),
+                },
+                GuideBlock {
+                    heading: "Compare files",
+                    keys: keys![
+                        "C"                 => "Compare with another file",
+                        "C (in compare)"    => "Copy the missing key across",
+                        "F (in compare)"    => "Show only differences",
+                        "Enter (in compare)" => "Go to the key",
+                        "C (in project)"    => "Compare with the selected config",
+                    ],
+                    note: Some("Keys are matched by path, across YAML, .properties and JSON. Copied keys get the value CHANGE_ME: in the open file as an unsaved edit, in the other file written straight to disk."),
+                },
+                GuideBlock {
+                    heading: "Verify",
+                    keys: keys![
+                        "v"                => "Verify the subtree / query",
+                        "V"                => "Verify the whole file",
+                        "i"                => "Detect which environment encrypted it",
+                        "Enter (in report)" => "Go to the value",
+                    ],
+                    note: Some("Each ![...] value is decrypted with the selected environment and the result discarded. The tree shows ✓ ok or ✗ fails until the value or environment changes; failures are listed in a report. i tries the value against every environment and names the one that decrypts it."),
+                },
+                GuideBlock {
+                    heading: "Re-key",
+                    keys: keys![
+                        "K"                => "Re-key to another environment",
+                        "Enter (in report)" => "Go to the value",
+                    ],
+                    note: Some("Every ![...] value is decrypted with the selected environment and encrypted with the picked one in a single step; plaintext never enters the document. The run is one undo step. From a shell: lazyprop rekey --from A --to B FILE."),
                 },
                 GuideBlock {
                     heading: "Save & restore",
                     keys: keys![
+                        "Ctrl+D" => "Review changes",
+                        "R (in review)" => "Revert the selected change",
                         "Ctrl+S" => "Save (atomic)",
                         "Ctrl+R" => "Restore to opened",
+                        "Space (in merge)" => "Keep yours / the disk's",
+                        "B"      => "Backups: load a saved version",
                     ],
-                    note: Some("A ● marks each modified property until it is saved, restored, or edited back to its original value. Flow style, block scalars and anchors are not editable in place."),
+                    note: Some("A ● marks each modified property until it is saved, restored, or edited back to its original value. Each open file keeps its own changes; quitting offers to save them all. A file changed on disk is reloaded, or merged with your unsaved changes. Each save keeps a backup in ~/.lazyprop/backups with the environment used. The review shows ciphertext changes as plaintext → encrypted, never the bytes. Flow style, block scalars and anchors are not editable in place."),
                 },
             ],
         },
@@ -8,6 +8,7 @@ use tokio::sync::mpsc::UnboundedSender;
 use super::Component;
 use crate::{
     action::Action,
+    detect::{self, Outcome},
     hints::{contextual_hints, ConfirmationKind, HintContext},
     panes::{
         details::DetailsPane, envs::EnvsPane, input::InputPane, popup::render_popup,
@@ -142,6 +143,66 @@ pub fn draw_env_form(frame: &mut Frame, area: Rect, state: &State) {
     frame.render_widget(Paragraph::new(lines).block(block), popup);
 }
 
+/// Draw the detect popup: one line per environment and the verdict. Shared by
+/// the Main and YAML screens.
+pub fn draw_detect(frame: &mut Frame, area: Rect, state: &State) {
+    let Some(report) = &state.detect else { return };
+
+    let popup = centered_rect(60, 60, area);
+    let mut lines: Vec<Line> = vec![Line::raw("")];
+    match &report.attempts {
+        None => lines.push(Line::from(Span::styled(
+            format!("  Trying {} environment(s)…", state.envs.environments.len()),
+            theme::hint_italic(),
+        ))),
+        Some(attempts) => {
+            let width = attempts
+                .iter()
+                .map(|a| a.env.chars().count())
+                .max()
+                .unwrap_or(0);
+            for attempt in attempts {
+                let (text, style) = match &attempt.outcome {
+                    Outcome::Match => (
+                        "decrypts".to_string(),
+                        Style::default().fg(theme::success()),
+                    ),
+                    Outcome::WrongKey => (
+                        "wrong key or settings".to_string(),
+                        Style::default().fg(theme::error()),
+                    ),
+                    Outcome::Malformed => (
+                        "malformed for this algorithm".to_string(),
+                        Style::default().fg(theme::error()),
+                    ),
+                    Outcome::Unusable(why) => (format!("not tried: {why}"), theme::hint()),
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<width$}  ", attempt.env), theme::label()),
+                    Span::styled(text, style),
+                ]));
+            }
+            lines.push(Line::raw(""));
+            lines.push(Line::from(Span::styled(
+                format!("  {}", detect::verdict(attempts)),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+        }
+    }
+
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Detect — {} ", report.subject))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        popup,
+    );
+}
+
 fn form_text_line(
     label: &str,
     value: &crate::text_field::TextField,
@@ -259,6 +320,7 @@ impl Component for Home {
         if state.pending_delete.is_some() {
             self.draw_delete_confirm(frame, area, state);
         }
+        draw_detect(frame, area, state);
         Ok(())
     }
 }
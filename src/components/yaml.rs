@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use color_eyre::Result;
 use ratatui::{
     prelude::*,
@@ -7,12 +9,22 @@ use ratatui::{
 use super::Component;
 use crate::{
     hints::{contextual_hints, ConfirmationKind, HintContext},
+    mule::{
+        project::{Project, RESOURCES_DIR},
+        xref::{Finding, Reference, Report},
+    },
     panes::popup::render_popup,
     state::State,
     theme,
     yaml_editor::{
-        document::{self, NodeKind, ScalarStyle},
-        state::{Confirm, Guard, OpenMode, YamlFocus},
+        compare::{self, Side, Status, ValueKind},
+        document::{self, DocFormat, NodeKind, ScalarStyle},
+        state::{
+            BackupView, BookmarkView, CompareView, Confirm, CopyItem, CopyMenu, EditTarget,
+            GotoPrompt, Guard, HistoryView, MergeView, OpenMode, ProjectPanel, RekeyPicker,
+            RekeyReport, ReviewView, SearchFilter, SearchMode, SuspiciousPanel, Verdict,
+            VerifyReport, YamlEditorState, YamlFocus,
+        },
     },
 };
 
@@ -38,13 +50,20 @@ impl Component for YamlScreen {
     fn draw(&mut self, frame: &mut Frame, area: Rect, state: &State) -> Result<()> {
         let y = &state.yaml;
 
-        let [info, body] =
-            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+        // The tab row only shows while more than one file is open.
+        let tabs_height = u16::from(y.buffer_count() > 1);
+        let [info, tabs, body] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(tabs_height),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
         draw_info(frame, info, state);
+        draw_tabs(frame, tabs, y);
 
         if !y.is_open() {
             let hint = Paragraph::new(
-                "No file open. Press Ctrl-o to open a .yaml/.yml file (browse or type a path).",
+                "No file open. Press Ctrl-o to open a .yaml/.yml/.properties/.json file (browse or type a path).",
             )
             .style(theme::hint_italic())
             .block(Block::default().borders(Borders::ALL).title(" YAML "));
@@ -66,6 +85,7 @@ impl Component for YamlScreen {
         draw_property(frame, prop_area, state);
 
         draw_overlays(frame, area, state);
+        super::home::draw_detect(frame, area, state);
         // The add-environment form is modal and shared with the Main screen.
         if state.form.is_some() {
             super::home::draw_env_form(frame, area, state);
@@ -104,6 +124,25 @@ fn draw_info(frame: &mut Frame, area: Rect, state: &State) {
     frame.render_widget(Line::from(spans), area);
 }
 
+/// One tab per open file; `*` marks unsaved changes.
+fn draw_tabs(frame: &mut Frame, area: Rect, y: &YamlEditorState) {
+    let (tabs, active) = y.buffers();
+    let mut spans = Vec::new();
+    for (i, (name, dirty)) in tabs.into_iter().enumerate() {
+        let label = format!(" {name}{} ", if dirty { "*" } else { "" });
+        let style = if i == active {
+            Style::default()
+                .fg(theme::accent())
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            theme::hint()
+        };
+        spans.push(Span::styled(label, style));
+        spans.push(Span::raw(" "));
+    }
+    frame.render_widget(Line::from(spans), area);
+}
+
 fn draw_environments(frame: &mut Frame, area: Rect, state: &State) {
     let active = state.yaml.focus == YamlFocus::Environments;
     let (style, btype) = focus_border(active);
@@ -134,11 +173,29 @@ fn draw_tree(frame: &mut Frame, area: Rect, state: &State) {
     let y = &state.yaml;
     let (style, btype) = focus_border(y.focus == YamlFocus::Tree);
     let mut title = " YAML tree ".to_string();
-    if let Some(q) = y.search_query() {
+    if y.search_editing || y.search_query().is_some() || y.search_filter != SearchFilter::All {
+        let q = y.search_query().unwrap_or_default();
         let editing = if y.search_editing { "_" } else { "" };
-        title = format!(" YAML tree — /{q}{editing} ");
-    } else if y.search_editing {
-        title = " YAML tree — /_ ".to_string();
+        title = match y.search_mode {
+            SearchMode::Text => format!(" YAML tree — /{q}{editing} "),
+            mode => format!(" YAML tree — {}: {q}{editing} ", mode.label()),
+        };
+        if y.search_error().is_some() {
+            title.push_str("(invalid query) ");
+        }
+        if y.search_filter != SearchFilter::All {
+            title.push_str(&format!("[{}] ", y.search_filter.label()));
+        }
+        if y.search_jump {
+            title.push_str("(n/N jump) ");
+        }
+    }
+    if let Some(env) = y.peek_env() {
+        title.push_str(&format!("— peek: {} ", env.name));
+    }
+    let marked = y.marked_ids().len();
+    if marked > 0 {
+        title.push_str(&format!("— {marked} marked "));
     }
     let block = Block::default()
         .title(title)
@@ -151,6 +208,14 @@ fn draw_tree(frame: &mut Frame, area: Rect, state: &State) {
     // Simple auto-scroll to keep the selection in view.
     let offset = if sel >= inner_h { sel + 1 - inner_h } else { 0 };
 
+    // Verify badges belong to the environment they were checked with.
+    let env = state.selected_env().map(|e| e.name.as_str());
+    // Without filtering, matches are picked out in the full tree instead.
+    let matches: HashSet<usize> = if y.search_jump && y.search_active() {
+        y.search_matches().into_iter().collect()
+    } else {
+        HashSet::new()
+    };
     let mut lines: Vec<Line> = Vec::new();
     for (i, &id) in visible.iter().enumerate().skip(offset).take(inner_h) {
         let node = &y.doc().nodes()[id];
@@ -176,25 +241,56 @@ fn draw_tree(frame: &mut Frame, area: Rect, state: &State) {
             } else {
                 theme::hint()
             };
-            Span::styled("● ", style)
+            Span::styled("●", style)
+        } else {
+            Span::raw(" ")
+        };
+        // Marks share the gutter, in its second cell.
+        let mark = if y.is_marked(id) {
+            Span::styled("*", Style::default().fg(theme::accent()))
         } else {
-            Span::raw("  ")
+            Span::raw(" ")
         };
-        let mut spans = vec![gutter, Span::raw(format!("{indent}{marker}"))];
+        let mut spans = vec![gutter, mark, Span::raw(format!("{indent}{marker}"))];
         let label_style = if i == sel {
             Style::default()
                 .fg(theme::accent())
                 .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        } else if matches.contains(&id) {
+            Style::default().add_modifier(Modifier::REVERSED)
         } else if modified {
             Style::default().add_modifier(Modifier::BOLD)
         } else {
             Style::default()
         };
         spans.push(Span::styled(node.label.clone(), label_style));
-        if node.kind == NodeKind::Scalar {
+        if let Some(plain) = y.peeked(id) {
+            spans.push(Span::styled(": ", theme::hint()));
+            spans.push(Span::styled(plain.to_string(), peek_style()));
+        } else if node.kind == NodeKind::Scalar {
             let val = display_value(y, id);
             spans.push(Span::styled(format!(": {val}"), theme::hint()));
         }
+        if let Some(env) = env {
+            let badge = match node.kind {
+                NodeKind::Scalar => y.verdict(id, env).map(|v| match v {
+                    Verdict::Ok => ("  ✓ ok", theme::success()),
+                    Verdict::Fails(_) => ("  ✗ fails", theme::error()),
+                }),
+                _ => y.subtree_fails(id, env).then_some(("  ✗", theme::error())),
+            };
+            if let Some((text, color)) = badge {
+                spans.push(Span::styled(text, Style::default().fg(color)));
+            }
+        }
+        // Expanded containers leave the flag to their children.
+        let suspicious = match node.kind {
+            NodeKind::Scalar => y.suspicion(id).is_some(),
+            _ => !y.is_expanded(&node.path) && y.subtree_suspicious(id),
+        };
+        if suspicious {
+            spans.push(Span::styled("  ⚠", Style::default().fg(theme::error())));
+        }
         lines.push(Line::from(spans));
     }
     frame.render_widget(Paragraph::new(lines).block(block), area);
@@ -207,8 +303,13 @@ fn draw_property(frame: &mut Frame, area: Rect, state: &State) {
 
     if let Some(field) = &y.editing {
         let width = area.width.saturating_sub(2) as usize;
-        let mut spans = vec![Span::styled("edit: ", theme::label())];
-        spans.extend(field.spans(width.saturating_sub(6), true, ""));
+        let label = match y.edit_target {
+            EditTarget::Value => "edit: ",
+            EditTarget::Comment => "comment: # ",
+            EditTarget::Bookmark => "bookmark: ",
+        };
+        let mut spans = vec![Span::styled(label, theme::label())];
+        spans.extend(field.spans(width.saturating_sub(label.len()), true, ""));
         lines.push(Line::from(spans));
         lines.push(Line::from(Span::styled(
             "Enter apply · Esc cancel",
@@ -224,21 +325,51 @@ fn draw_property(frame: &mut Frame, area: Rect, state: &State) {
             let (type_str, encrypted) = match kind {
                 NodeKind::Scalar => {
                     let logical = y.doc().logical_value(sel_id).unwrap_or_default();
-                    (scalar_type(style, &logical), document::is_wrapped(&logical))
+                    // Property values are always strings; JSON strings are
+                    // always quoted, so "quoted" says nothing there.
+                    let type_str = match y.doc().format() {
+                        DocFormat::Properties => "string".to_string(),
+                        DocFormat::Json if style == ScalarStyle::DoubleQuoted => {
+                            "string".to_string()
+                        }
+                        DocFormat::Yaml | DocFormat::Json => scalar_type(style, &logical),
+                    };
+                    (type_str, document::is_wrapped(&logical))
                 }
                 NodeKind::Mapping => ("mapping".to_string(), false),
                 NodeKind::Sequence => ("sequence".to_string(), false),
             };
-            lines.push(Line::from(vec![
-                Span::styled("value: ", theme::label()),
-                Span::raw(display_value(y, sel_id)),
-            ]));
+            let value = match y.peeked(sel_id) {
+                Some(plain) => vec![
+                    Span::styled(plain.to_string(), peek_style()),
+                    Span::styled("  (peek)", theme::hint()),
+                ],
+                None => vec![Span::raw(display_value(y, sel_id))],
+            };
+            let mut spans = vec![Span::styled("value: ", theme::label())];
+            spans.extend(value);
+            lines.push(Line::from(spans));
             lines.push(Line::from(vec![
                 Span::styled("type: ", theme::label()),
                 Span::raw(type_str),
                 Span::styled("   encrypted: ", theme::label()),
                 Span::raw(if encrypted { "yes" } else { "no" }),
             ]));
+            let node = &y.doc().nodes()[sel_id];
+            // The pane is short: show the comment lines closest to the node.
+            let skip = node.leading_comments.len().saturating_sub(2);
+            for comment in &node.leading_comments[skip..] {
+                lines.push(Line::from(Span::styled(
+                    format!("# {comment}"),
+                    theme::hint(),
+                )));
+            }
+            if let Some(comment) = &node.inline_comment {
+                lines.push(Line::from(vec![
+                    Span::styled("comment: ", theme::label()),
+                    Span::styled(format!("# {comment}"), theme::hint()),
+                ]));
+            }
         }
     }
 
@@ -269,6 +400,13 @@ fn display_value(y: &crate::yaml_editor::state::YamlEditorState, id: usize) -> S
     }
 }
 
+/// Peeked plaintext, set apart from values as stored.
+fn peek_style() -> Style {
+    Style::default()
+        .fg(theme::accent())
+        .add_modifier(Modifier::ITALIC)
+}
+
 fn scalar_type(style: ScalarStyle, logical: &str) -> String {
     match style {
         ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted => "string (quoted)".to_string(),
@@ -295,9 +433,11 @@ fn draw_overlays(frame: &mut Frame, area: Rect, state: &State) {
     if let Some(modal) = &y.open_modal {
         let popup = centered(70, 70, area);
         frame.render_widget(Clear, popup);
-        let title = match modal.mode {
-            OpenMode::Browse => " Open YAML — Browse (Tab: type a path) ",
-            OpenMode::Path => " Open YAML — Path (Tab: browse) ",
+        let title = match (modal.compare, modal.mode) {
+            (false, OpenMode::Browse) => " Open YAML — Browse (Tab: type a path) ",
+            (false, OpenMode::Path) => " Open YAML — Path (Tab: browse) ",
+            (true, OpenMode::Browse) => " Compare with — Browse (Tab: type a path) ",
+            (true, OpenMode::Path) => " Compare with — Path (Tab: browse) ",
         };
         let block = Block::default()
             .title(title)
@@ -358,7 +498,7 @@ fn draw_overlays(frame: &mut Frame, area: Rect, state: &State) {
                 let spans = modal.path_input.spans(
                     input.width as usize,
                     true,
-                    "e.g. ./config.yaml or ~/config.yml",
+                    "e.g. ./config.yaml or ~/app.properties",
                 );
                 frame.render_widget(Line::from(spans), input);
                 // Navigation hints live in the shared footer; show only errors here.
@@ -372,6 +512,58 @@ fn draw_overlays(frame: &mut Frame, area: Rect, state: &State) {
         }
     }
 
+    if let (Some(project), Some(panel)) = (&y.project, &y.project_panel) {
+        draw_project_panel(frame, area, project, panel, state);
+    }
+
+    if let Some(view) = &y.compare {
+        draw_compare(frame, area, y, view);
+    }
+
+    if let Some(panel) = &y.suspicious_panel {
+        draw_suspicious(frame, area, panel);
+    }
+
+    if let Some(view) = &y.merge {
+        draw_merge(frame, area, view);
+    }
+
+    if let Some(view) = &y.history_view {
+        draw_history(frame, area, y, view);
+    }
+
+    if let Some(view) = &y.backup_view {
+        draw_backups(frame, area, view);
+    }
+
+    if let Some(prompt) = &y.goto {
+        draw_goto(frame, area, y, prompt);
+    }
+
+    if let Some(view) = &y.bookmark_view {
+        draw_bookmarks(frame, area, y, view);
+    }
+
+    if let Some(menu) = &y.copy_menu {
+        draw_copy_menu(frame, area, y, menu);
+    }
+
+    if let Some(view) = &y.review {
+        draw_review(frame, area, view);
+    }
+
+    if let Some(picker) = &y.rekey_picker {
+        draw_rekey_picker(frame, area, picker);
+    }
+
+    if let Some(report) = &y.rekey_report {
+        draw_rekey_report(frame, area, report);
+    }
+
+    if let Some(report) = &y.verify_report {
+        draw_verify_report(frame, area, report);
+    }
+
     if let Some(confirm) = &y.confirm {
         let (title, msg, kind) = match confirm {
             Confirm::Restore => (
@@ -398,15 +590,21 @@ fn draw_overlays(frame: &mut Frame, area: Rect, state: &State) {
     if let Some(guard) = y.guard() {
         let (msg, kind) = match guard {
             Guard::Quit => (
-                vec!["You have unsaved changes. Quit anyway?".to_string()],
+                vec![
+                    "You have unsaved changes. Quit anyway?".to_string(),
+                    y.unsaved_files().join(", "),
+                ],
                 ConfirmationKind::UnsavedQuit,
             ),
-            Guard::Open(path) => (
+            Guard::Close => (
                 vec![
-                    "You have unsaved changes. Open another file?".to_string(),
-                    path.clone(),
+                    "You have unsaved changes. Close this file?".to_string(),
+                    y.file_path
+                        .as_deref()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_default(),
                 ],
-                ConfirmationKind::UnsavedOpen,
+                ConfirmationKind::UnsavedClose,
             ),
         };
         render_popup(
@@ -420,6 +618,828 @@ fn draw_overlays(frame: &mut Frame, area: Rect, state: &State) {
     }
 }
 
+/// The project panel: one row per config file with its value counts.
+fn draw_project_panel(
+    frame: &mut Frame,
+    area: Rect,
+    project: &Project,
+    panel: &ProjectPanel,
+    state: &State,
+) {
+    let popup = centered(80, 70, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Project — {} ", project.name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [dir, body, hint] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Fill(1),
+        Constraint::Length(1),
+    ])
+    .areas(inner);
+    // Navigation hints live in the shared footer; show only errors here.
+    let error = panel.error.clone().or_else(|| {
+        project
+            .secure_errors
+            .first()
+            .map(|e| format!("Ignored secure-properties config: {e}"))
+    });
+    if let Some(msg) = error {
+        frame.render_widget(
+            Line::from(Span::styled(msg, Style::default().fg(theme::error()))),
+            hint,
+        );
+    }
+    if let Some(report) = &panel.report {
+        draw_xref_report(frame, dir, body, report, panel.finding);
+        return;
+    }
+    frame.render_widget(
+        Line::from(Span::styled(
+            project.root.join(RESOURCES_DIR).display().to_string(),
+            theme::hint(),
+        )),
+        dir,
+    );
+    if project.configs.is_empty() {
+        frame.render_widget(
+            Line::from(Span::styled(
+                "No .yaml/.yml/.properties/.json files found.",
+                theme::hint_italic(),
+            )),
+            body,
+        );
+        return;
+    }
+    let width = project
+        .configs
+        .iter()
+        .map(|c| c.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let items: Vec<ListItem> = project
+        .configs
+        .iter()
+        .map(|c| {
+            let mut spans = vec![Span::raw(format!("{:<width$}  ", c.name))];
+            match &c.counts {
+                Ok(n) => {
+                    spans.push(Span::styled(
+                        format!("{} encrypted", n.encrypted),
+                        Style::default().fg(theme::success()),
+                    ));
+                    spans.push(Span::styled(
+                        format!(" · {} plaintext", n.plaintext),
+                        theme::hint(),
+                    ));
+                    if n.suspicious > 0 {
+                        spans.push(Span::styled(
+                            format!(" · {} suspicious", n.suspicious),
+                            Style::default().fg(theme::error()),
+                        ));
+                    }
+                }
+                Err(e) => spans.push(Span::styled(e.clone(), Style::default().fg(theme::error()))),
+            }
+            if let Some(env) = project.linked_env(&c.path) {
+                if state.envs.environments.iter().any(|e| e.name == env) {
+                    spans.push(Span::styled(
+                        format!("  → {env}"),
+                        Style::default().fg(theme::success()),
+                    ));
+                } else {
+                    spans.push(Span::styled(
+                        format!("  → {env} (n: create)"),
+                        theme::hint(),
+                    ));
+                }
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(panel.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The placeholder cross-check: a summary line, then one row per finding.
+fn draw_xref_report(frame: &mut Frame, head: Rect, body: Rect, report: &Report, selected: usize) {
+    frame.render_widget(
+        Line::from(Span::styled(
+            format!(
+                "{} XML files · {} placeholders · {} findings",
+                report.xml_files,
+                report.references,
+                report.findings.len()
+            ),
+            theme::hint(),
+        )),
+        head,
+    );
+    if report.findings.is_empty() {
+        frame.render_widget(
+            Line::from(Span::styled(
+                "Every placeholder resolves, and every encrypted value is referenced.",
+                Style::default().fg(theme::success()),
+            )),
+            body,
+        );
+        return;
+    }
+    let items: Vec<ListItem> = report
+        .findings
+        .iter()
+        .map(|finding| {
+            let (tag, text) = match finding {
+                Finding::Missing(r) => (
+                    "missing  ",
+                    format!(
+                        "{} — {}:{}, defined in no config",
+                        placeholder(r),
+                        r.file,
+                        r.line
+                    ),
+                ),
+                Finding::SecurePlaintext(r, loc) => (
+                    "plaintext",
+                    format!(
+                        "{} — stored unencrypted in {} ({}:{})",
+                        placeholder(r),
+                        loc.name,
+                        r.file,
+                        r.line
+                    ),
+                ),
+                Finding::Unreferenced(loc) => (
+                    "unused   ",
+                    format!(
+                        "{} in {} — encrypted but never referenced",
+                        document::path_to_string(&loc.path),
+                        loc.name
+                    ),
+                ),
+            };
+            let color = match finding {
+                Finding::Unreferenced(_) => theme::accent(),
+                _ => theme::error(),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{tag}  "), Style::default().fg(color)),
+                Span::raw(text),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The compare view: a summary line, then one row per key with the value on
+/// each side and what differs.
+fn draw_compare(frame: &mut Frame, area: Rect, y: &YamlEditorState, view: &CompareView) {
+    let popup = centered(90, 85, area);
+    frame.render_widget(Clear, popup);
+    let name = |p: &std::path::Path| {
+        p.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let left = y.file_path.as_deref().map(name).unwrap_or_default();
+    let block = Block::default()
+        .title(format!(" Compare — {left} ↔ {} ", name(&view.path)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [head, body] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+
+    let count = |f: fn(Status) -> bool| view.rows.iter().filter(|r| f(r.status)).count();
+    let mut summary = format!(
+        "{} keys · {} missing here · {} missing there · {} encrypted on one side · {} type mismatches",
+        view.rows.len(),
+        count(|s| s == Status::Missing(Side::Left)),
+        count(|s| s == Status::Missing(Side::Right)),
+        count(|s| s == Status::Encryption),
+        count(|s| s == Status::Type),
+    );
+    if view.differences_only {
+        summary.push_str(" (differences only)");
+    }
+    frame.render_widget(Line::from(Span::styled(summary, theme::hint())), head);
+
+    let visible = view.visible();
+    if visible.is_empty() {
+        frame.render_widget(
+            Line::from(Span::styled(
+                "Both files have the same keys, encrypted alike.",
+                Style::default().fg(theme::success()),
+            )),
+            body,
+        );
+        return;
+    }
+    let width = body.width.saturating_sub(2) as usize;
+    let key_w = visible
+        .iter()
+        .map(|&i| view.rows[i].key.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(width / 3);
+    let status_w = 22;
+    let value_w = width.saturating_sub(key_w + status_w + 6) / 2;
+    let cell = |entry: Option<&compare::Entry>| -> Span<'static> {
+        let Some(entry) = entry else {
+            return Span::styled(
+                format!("{:<value_w$}", "—"),
+                Style::default().fg(theme::error()),
+            );
+        };
+        let text = match (&entry.value, entry.kind) {
+            (_, ValueKind::Mapping) => "{…}".to_string(),
+            (_, ValueKind::Sequence) => "[…]".to_string(),
+            (Some(_), _) if entry.encrypted && !y.reveal => "![••••••]".to_string(),
+            (Some(v), _) => v.replace(['\n', '\r'], " "),
+            (None, _) => String::new(),
+        };
+        let text = clip(&text, value_w);
+        if entry.encrypted {
+            Span::styled(
+                format!("{text:<value_w$}"),
+                Style::default().fg(theme::success()),
+            )
+        } else {
+            Span::raw(format!("{text:<value_w$}"))
+        }
+    };
+    let items: Vec<ListItem> = visible
+        .iter()
+        .map(|&i| {
+            let row = &view.rows[i];
+            let (note, color) = match row.status {
+                Status::Same => (String::new(), None),
+                Status::Missing(Side::Left) => ("missing here".to_string(), Some(theme::error())),
+                Status::Missing(Side::Right) => ("missing there".to_string(), Some(theme::error())),
+                Status::Encryption => {
+                    let side = if row.left.as_ref().is_some_and(|e| e.encrypted) {
+                        "left"
+                    } else {
+                        "right"
+                    };
+                    (format!("encrypted {side} only"), Some(theme::accent()))
+                }
+                Status::Type => {
+                    let kind = |e: Option<&compare::Entry>| e.map_or("", |e| e.kind.label());
+                    (
+                        format!("{} ≠ {}", kind(row.left.as_ref()), kind(row.right.as_ref())),
+                        Some(theme::accent()),
+                    )
+                }
+            };
+            let key_style = color.map_or_else(Style::default, |c| Style::default().fg(c));
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<key_w$}  ", clip(&row.key, key_w)), key_style),
+                cell(row.left.as_ref()),
+                Span::raw("  "),
+                cell(row.right.as_ref()),
+                Span::raw("  "),
+                Span::styled(note, key_style),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(view.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The target picker for a re-key: every environment but the source.
+fn draw_rekey_picker(frame: &mut Frame, area: Rect, picker: &RekeyPicker) {
+    let popup = centered(60, 50, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Re-key from {} to ", picker.from.name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [head, body] = Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner);
+    frame.render_widget(
+        Paragraph::new(format!(
+            "Every ![...] value is decrypted with {} and encrypted with the environment picked below.",
+            picker.from.name
+        ))
+        .style(theme::hint())
+        .wrap(Wrap { trim: true }),
+        head,
+    );
+    let items: Vec<ListItem> = picker
+        .choices
+        .iter()
+        .map(|env| {
+            ListItem::new(Line::from(vec![
+                Span::raw(env.name.clone()),
+                Span::styled(
+                    format!("  {:?} / {:?}", env.algorithm, env.state),
+                    theme::hint(),
+                ),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(picker.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The summary of a finished re-key: one row per value, failures first.
+fn draw_rekey_report(frame: &mut Frame, area: Rect, report: &RekeyReport) {
+    let popup = centered(80, 70, area);
+    frame.render_widget(Clear, popup);
+    let failed = !report.summary.failed.is_empty();
+    let block = Block::default()
+        .title(format!(" Re-key — {} → {} ", report.from, report.to))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(if failed {
+            theme::error()
+        } else {
+            theme::accent()
+        }));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [head, body] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+    frame.render_widget(
+        Line::from(Span::styled(report.summary.counts(), theme::hint())),
+        head,
+    );
+    let items: Vec<ListItem> = report
+        .rows()
+        .into_iter()
+        .map(|(outcome, path, reason)| {
+            let color = match outcome {
+                "failed" => theme::error(),
+                "rotated" => theme::success(),
+                _ => theme::accent(),
+            };
+            let mut spans = vec![
+                Span::styled(format!("{outcome:<7}  "), Style::default().fg(color)),
+                Span::raw(document::path_to_string(path)),
+            ];
+            if let Some(reason) = reason {
+                spans.push(Span::styled(format!(" — {reason}"), theme::hint()));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(report.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The values a verify could not decrypt, with the tool's error.
+fn draw_verify_report(frame: &mut Frame, area: Rect, report: &VerifyReport) {
+    let popup = centered(80, 70, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Verify — {} ", report.env))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::error()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [head, body] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+    frame.render_widget(
+        Line::from(Span::styled(
+            format!(
+                "{} of {} encrypted value(s) do not decrypt with {}",
+                report.failures.len(),
+                report.checked,
+                report.env
+            ),
+            theme::hint(),
+        )),
+        head,
+    );
+    let items: Vec<ListItem> = report
+        .failures
+        .iter()
+        .map(|(path, reason)| {
+            ListItem::new(Line::from(vec![
+                Span::styled("fails  ", Style::default().fg(theme::error())),
+                Span::raw(document::path_to_string(path)),
+                Span::styled(format!(" — {reason}"), theme::hint()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(report.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// Plaintext values that look like secrets, with why.
+fn draw_suspicious(frame: &mut Frame, area: Rect, panel: &SuspiciousPanel) {
+    let popup = centered(80, 70, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Suspicious values — {} ", panel.items.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::error()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [head, body] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+    frame.render_widget(
+        Line::from(Span::styled(
+            "Plaintext that looks like a secret; E encrypts all of them with the selected environment",
+            theme::hint(),
+        )),
+        head,
+    );
+    let items: Vec<ListItem> = panel
+        .items
+        .iter()
+        .map(|(path, signal)| {
+            ListItem::new(Line::from(vec![
+                Span::styled("⚠  ", Style::default().fg(theme::error())),
+                Span::raw(document::path_to_string(path)),
+                Span::styled(format!(" — {}", signal.label()), theme::hint()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(panel.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// Our changes against the file changed on disk, one row per path: `✓`
+/// keeps ours, `·` takes the disk's, `!` marks a conflict.
+fn draw_merge(frame: &mut Frame, area: Rect, view: &MergeView) {
+    let popup = centered(80, 70, area);
+    frame.render_widget(Clear, popup);
+    let conflicts = view.rows.iter().filter(|r| r.is_conflict()).count();
+    let block = Block::default()
+        .title(format!(" Changed on disk — {conflicts} conflict(s) "))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::error()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [head, body] = Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner);
+    let note = if view.rows.is_empty() {
+        "Only comments or layout changed here; Enter takes the disk version"
+    } else {
+        "Enter merges your changes onto the disk version; Space picks yours or the disk's"
+    };
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(Span::styled(note, theme::hint())),
+            Line::from(Span::styled(
+                "Comment and layout edits are not carried over",
+                theme::hint(),
+            )),
+        ]),
+        head,
+    );
+    let items: Vec<ListItem> = view
+        .rows
+        .iter()
+        .map(|row| {
+            let (mark, color) = match (row.keep_mine, row.is_conflict()) {
+                (true, _) => ("✓ ", theme::success()),
+                (false, true) => ("! ", theme::error()),
+                (false, false) => ("· ", theme::accent()),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(mark, Style::default().fg(color)),
+                Span::raw(document::path_to_string(&row.path)),
+                Span::styled(format!(" — {}", row.summary()), theme::hint()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(view.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The undo history, newest first. Undone entries are dimmed; Enter makes
+/// the selected row the latest applied change.
+fn draw_history(frame: &mut Frame, area: Rect, y: &YamlEditorState, view: &HistoryView) {
+    let rows = y.history_rows();
+    let popup = centered(60, 60, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" History — {} change(s) ", rows.len() - 1))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [head, body] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+    frame.render_widget(
+        Line::from(Span::styled(
+            "Dimmed changes are undone; they can be redone until the next edit",
+            theme::hint(),
+        )),
+        head,
+    );
+    // The first applied row is where the document is now.
+    let current = rows.iter().position(|(_, applied)| *applied);
+    let items: Vec<ListItem> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, (label, applied))| {
+            let marker = if Some(i) == current { "● " } else { "  " };
+            let style = if *applied {
+                Style::default()
+            } else {
+                theme::hint()
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(marker, Style::default().fg(theme::accent())),
+                Span::styled(label.clone(), style),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(view.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The go-to prompt: the typed path, then the matching paths to pick from.
+fn draw_goto(frame: &mut Frame, area: Rect, y: &YamlEditorState, prompt: &GotoPrompt) {
+    let candidates = y.goto_candidates();
+    let popup = centered(60, 60, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Go to path — {} match(es) ", candidates.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [input, head, body] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+    ])
+    .areas(inner);
+    let mut spans = vec![Span::styled("path: ", theme::label())];
+    spans.extend(
+        prompt
+            .field
+            .spans((input.width as usize).saturating_sub(6), true, ""),
+    );
+    frame.render_widget(Line::from(spans), input);
+    frame.render_widget(
+        Line::from(Span::styled(
+            "Tab completes the picked path; Enter goes there and unfolds it",
+            theme::hint(),
+        )),
+        head,
+    );
+    let items: Vec<ListItem> = candidates.into_iter().map(ListItem::new).collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(prompt.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The copy menu: each item with its key and a preview of what it copies.
+/// Plaintext is never previewed.
+fn draw_copy_menu(frame: &mut Frame, area: Rect, y: &YamlEditorState, menu: &CopyMenu) {
+    let popup = centered(60, 40, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(" Copy to clipboard ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let width = inner.width as usize;
+    let items: Vec<ListItem> = menu
+        .items
+        .iter()
+        .map(|&item| {
+            let preview = match (item, y.copy_text(item)) {
+                (CopyItem::Plaintext, Ok(_)) => "decrypted, hidden".to_string(),
+                (_, Ok((text, _))) => text.lines().next().unwrap_or("").to_string(),
+                (_, Err(e)) => e,
+            };
+            let head = format!("{}  {:<20}", item.key(), item.label());
+            let room = width.saturating_sub(head.chars().count() + 4);
+            ListItem::new(Line::from(vec![
+                Span::styled(head, Style::default().fg(theme::accent())),
+                Span::styled(format!("  {}", clip(&preview, room)), theme::hint()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(menu.selected));
+    frame.render_stateful_widget(list, inner, &mut ls);
+}
+
+/// The open file's bookmarks; one whose path is gone from the file is
+/// dimmed.
+fn draw_bookmarks(frame: &mut Frame, area: Rect, y: &YamlEditorState, view: &BookmarkView) {
+    let rows = y.bookmark_rows();
+    let popup = centered(60, 50, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Bookmarks — {} ", rows.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [head, body] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+    frame.render_widget(
+        Line::from(Span::styled(
+            "a bookmarks the selected node; bookmarks are kept across sessions",
+            theme::hint(),
+        )),
+        head,
+    );
+    let items: Vec<ListItem> = rows
+        .into_iter()
+        .map(|(name, path, found)| {
+            let mut spans = vec![
+                Span::styled(name, Style::default().fg(theme::accent())),
+                Span::styled(format!("  {path}"), theme::hint()),
+            ];
+            if !found {
+                spans.push(Span::styled(" (not in this file)", theme::hint()));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(view.selected));
+    frame.render_stateful_widget(list, body, &mut ls);
+}
+
+/// The file's backups, newest first, on the left; what loading the selected
+/// one would change, by path, on the right.
+fn draw_backups(frame: &mut Frame, area: Rect, view: &BackupView) {
+    let popup = centered(90, 70, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Backups — {} version(s) ", view.backups.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [list_area, detail_area] =
+        Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(inner);
+
+    let items: Vec<ListItem> = view
+        .backups
+        .iter()
+        .map(|(backup, changes)| {
+            let env = backup.env.as_deref().unwrap_or("before first save");
+            let differs = match changes.len() {
+                0 => "same values".to_string(),
+                n => format!("{n} path(s) differ"),
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(backup.when()),
+                Span::styled(format!("  {env}"), Style::default().fg(theme::accent())),
+                Span::styled(format!("  {differs}"), theme::hint()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(view.selected));
+    frame.render_stateful_widget(list, list_area, &mut ls);
+
+    let Some((_, changes)) = view.backups.get(view.selected) else {
+        return;
+    };
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Loading it changes, from the working document:",
+            theme::hint(),
+        )),
+        Line::raw(""),
+    ];
+    if changes.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "no values; comments or layout at most",
+            theme::hint(),
+        )));
+    }
+    lines.extend(
+        changes
+            .iter()
+            .map(|s| Line::from(Span::styled(s.clone(), theme::label()))),
+    );
+    let detail = Block::default()
+        .borders(Borders::LEFT)
+        .border_style(theme::hint());
+    frame.render_widget(Paragraph::new(lines).block(detail), detail_area);
+}
+
+/// The unsaved changes: the hunks on the left, the selected one's value
+/// changes and lines (ciphertext masked) on the right.
+fn draw_review(frame: &mut Frame, area: Rect, view: &ReviewView) {
+    let popup = centered(90, 80, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .title(format!(" Review — {} change(s) ", view.hunks.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::accent()));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [list_area, detail_area] =
+        Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(inner);
+
+    let items: Vec<ListItem> = view
+        .hunks
+        .iter()
+        .map(|hunk| {
+            let summaries = hunk.summaries();
+            let mut spans = vec![
+                Span::styled(format!("line {:<5}", hunk.working.start + 1), theme::hint()),
+                Span::raw(summaries[0].clone()),
+            ];
+            if summaries.len() > 1 {
+                spans.push(Span::styled(
+                    format!(" (+{} more)", summaries.len() - 1),
+                    theme::hint(),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+    let mut ls = ListState::default().with_selected(Some(view.selected));
+    frame.render_stateful_widget(list, list_area, &mut ls);
+
+    let Some(hunk) = view.hunks.get(view.selected) else {
+        return;
+    };
+    let mut lines: Vec<Line> = hunk
+        .summaries()
+        .into_iter()
+        .map(|s| Line::from(Span::styled(s, theme::label())))
+        .collect();
+    lines.push(Line::raw(""));
+    let removed = Style::default().fg(theme::error());
+    let added = Style::default().fg(theme::success());
+    lines.extend(
+        hunk.removed
+            .iter()
+            .map(|l| Line::from(Span::styled(format!("- {l}"), removed))),
+    );
+    lines.extend(
+        hunk.added
+            .iter()
+            .map(|l| Line::from(Span::styled(format!("+ {l}"), added))),
+    );
+    let detail = Block::default()
+        .borders(Borders::LEFT)
+        .border_style(theme::hint());
+    frame.render_widget(Paragraph::new(lines).block(detail), detail_area);
+}
+
+/// Cut `s` to `width` characters, marking the cut with `…`.
+fn clip(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.to_string();
+    }
+    let mut out: String = s.chars().take(width.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+fn placeholder(r: &Reference) -> String {
+    if r.secure {
+        format!("${{secure::{}}}", r.key)
+    } else {
+        format!("${{{}}}", r.key)
+    }
+}
+
 fn centered(px: u16, py: u16, area: Rect) -> Rect {
     let v = Layout::vertical([
         Constraint::Percentage((100 - py) / 2),
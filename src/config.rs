@@ -9,7 +9,11 @@ use ratatui::style::{Color, Modifier, Style};
 use serde::{de::Deserializer, Deserialize};
 use tracing::error;
 
-use crate::{action::Action, app::Mode};
+use crate::{
+    action::Action,
+    app::Mode,
+    yaml_editor::{backup, suspicious::SecretRules},
+};
 
 const CONFIG: &str = include_str!("../.config/config.json");
 
@@ -37,6 +41,14 @@ pub struct Config {
     /// UI. See [`crate::theme`].
     #[serde(default)]
     pub theme: HashMap<String, String>,
+    /// What the YAML screen flags as a plaintext secret. See
+    /// [`crate::yaml_editor::suspicious`].
+    #[serde(default)]
+    pub secrets: SecretRules,
+    /// How many saved versions of each file are kept. See
+    /// [`crate::yaml_editor::backup`].
+    #[serde(default)]
+    pub backups: backup::Settings,
 }
 
 lazy_static! {
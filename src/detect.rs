@@ -0,0 +1,188 @@
+//! Finding out which environment encrypted a value.
+//!
+//! The value is decrypted with every environment and each attempt is
+//! classified from the tool's outcome: readable text is a match, a padding
+//! failure (or bytes that are not text) means the wrong key or settings, and
+//! input that is not Base64 or not a whole number of cipher blocks is
+//! malformed whatever the key. The plaintext of a match is never kept.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dencrypt::decrypt;
+use crate::environment::Environment;
+use crate::yaml_editor::document;
+
+/// How one environment fared against the value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    /// Decrypts to readable text.
+    Match,
+    /// Bad padding, or decrypts to bytes that are not text: another key,
+    /// mode or IV setting.
+    WrongKey,
+    /// Not Base64, or not a whole number of blocks for the algorithm.
+    Malformed,
+    /// The environment could not be tried (unsupported algorithm, invalid
+    /// key length, no Java, …).
+    Unusable(String),
+}
+
+/// One environment's attempt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attempt {
+    pub env: String,
+    pub outcome: Outcome,
+}
+
+/// Classify the result of decrypting with one environment.
+pub fn classify(result: Result<String, String>) -> Outcome {
+    match result {
+        Ok(plain) => {
+            let text = !plain.is_empty()
+                && !plain.chars().any(|c| {
+                    c == char::REPLACEMENT_CHARACTER || (c.is_control() && !c.is_whitespace())
+                });
+            if text {
+                Outcome::Match
+            } else {
+                Outcome::WrongKey
+            }
+        }
+        Err(e) => {
+            let lower = e.to_ascii_lowercase();
+            if lower.contains("base64")
+                || lower.contains("input length must be multiple")
+                || lower.contains("illegalblocksize")
+            {
+                Outcome::Malformed
+            } else if lower.contains("padded")
+                || lower.contains("badpadding")
+                || lower.contains("bad key")
+                || lower.contains("tag mismatch")
+            {
+                Outcome::WrongKey
+            } else {
+                Outcome::Unusable(e.lines().next().unwrap_or("").trim().to_string())
+            }
+        }
+    }
+}
+
+/// Try `value` (wrapped as `![...]` or bare) against every environment, in
+/// parallel, in the order given.
+pub fn detect(value: &str, envs: &[Environment], jar: &Path) -> Vec<Attempt> {
+    let cipher = document::unwrap_cipher(value);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = envs
+            .iter()
+            .map(|env| {
+                let cipher = &cipher;
+                scope.spawn(move || {
+                    if !env.algorithm.supports_modes() {
+                        return Outcome::Unusable(format!(
+                            "{:?} is not supported by the tool",
+                            env.algorithm
+                        ));
+                    }
+                    classify(decrypt(cipher, env, jar))
+                })
+            })
+            .collect();
+        envs.iter()
+            .zip(handles)
+            .map(|(env, handle)| Attempt {
+                env: env.name.clone(),
+                outcome: handle
+                    .join()
+                    .unwrap_or_else(|_| Outcome::Unusable("the attempt panicked".to_string())),
+            })
+            .collect()
+    })
+}
+
+/// A one-line verdict over all attempts.
+pub fn verdict(attempts: &[Attempt]) -> String {
+    let matches: Vec<&str> = attempts
+        .iter()
+        .filter(|a| a.outcome == Outcome::Match)
+        .map(|a| a.env.as_str())
+        .collect();
+    let tried: Vec<&Attempt> = attempts
+        .iter()
+        .filter(|a| !matches!(a.outcome, Outcome::Unusable(_)))
+        .collect();
+    let malformed = !tried.is_empty() && tried.iter().all(|a| a.outcome == Outcome::Malformed);
+    match matches.as_slice() {
+        [] if malformed => "The value is malformed: no key can decrypt it.".to_string(),
+        [] => "No environment decrypts this value.".to_string(),
+        [one] => format!("Encrypted with {one}."),
+        many => format!("Decrypts with {}.", many.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_tool_outcomes() {
+        assert_eq!(classify(Ok("hunter2".to_string())), Outcome::Match);
+        // A random-IV mismatch "succeeds" with nothing or garbage.
+        assert_eq!(classify(Ok(String::new())), Outcome::WrongKey);
+        assert_eq!(
+            classify(Ok("\u{fffd}\u{1}x".to_string())),
+            Outcome::WrongKey
+        );
+        assert_eq!(
+            classify(Err("Given final block not properly padded. Such issues can arise if a bad key is used during decryption.".to_string())),
+            Outcome::WrongKey
+        );
+        assert_eq!(
+            classify(Err(
+                "Input length must be multiple of 16 when decrypting with padded cipher"
+                    .to_string()
+            )),
+            Outcome::Malformed
+        );
+        assert_eq!(
+            classify(Err("Exception in thread \"main\" java.lang.IllegalArgumentException: Illegal base64 character 40\n\tat ...".to_string())),
+            Outcome::Malformed
+        );
+        assert_eq!(
+            classify(Err("Invalid AES key length: 5 bytes".to_string())),
+            Outcome::Unusable("Invalid AES key length: 5 bytes".to_string())
+        );
+    }
+
+    #[test]
+    fn verdicts() {
+        let attempt = |env: &str, outcome| Attempt {
+            env: env.to_string(),
+            outcome,
+        };
+        let unusable = || Outcome::Unusable("x".to_string());
+        assert_eq!(
+            verdict(&[
+                attempt("dev", Outcome::WrongKey),
+                attempt("prod", Outcome::Match)
+            ]),
+            "Encrypted with prod."
+        );
+        assert_eq!(
+            verdict(&[
+                attempt("dev", Outcome::Malformed),
+                attempt("old", unusable())
+            ]),
+            "The value is malformed: no key can decrypt it."
+        );
+        assert_eq!(
+            verdict(&[
+                attempt("dev", Outcome::Malformed),
+                attempt("bf", Outcome::WrongKey)
+            ]),
+            "No environment decrypts this value."
+        );
+    }
+}
@@ -103,7 +103,7 @@ fn cycle_variant<T: Copy + PartialEq>(all: &[T], current: T, forward: bool) -> T
     all[next]
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Environment {
     pub name: String,
     pub algorithm: Algorithm,
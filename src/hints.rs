@@ -84,6 +84,18 @@ pub struct YamlHints {
     pub dirty: bool,
     pub env_selected: bool,
     pub selection: Option<YamlSelectionKind>,
+    /// Whether the open format has inline comments (`.properties` does not).
+    pub comments: bool,
+    /// Whether a Mule project is loaded (`P` shows its config files).
+    pub project: bool,
+    /// Whether decrypted previews are shown (`O` turns them off).
+    pub peek: bool,
+    /// How many nodes are marked (`Space`).
+    pub marks: usize,
+    /// How many files are open (`[`/`]` switch between them).
+    pub buffers: usize,
+    /// Whether a search is active (`n`/`N` step through its matches).
+    pub searching: bool,
 }
 
 /// The kind of confirmation dialog currently open.
@@ -95,8 +107,8 @@ pub enum ConfirmationKind {
     OverwriteExternal,
     /// Unsaved changes when quitting (save/discard/cancel).
     UnsavedQuit,
-    /// Unsaved changes when opening another file (save/discard/cancel).
-    UnsavedOpen,
+    /// Unsaved changes when closing a file (save/discard/cancel).
+    UnsavedClose,
     /// Delete an environment (y/n).
     DeleteEnv,
 }
@@ -121,10 +133,41 @@ pub enum HintContext {
     Yaml(YamlHints),
     /// YAML scalar edit mode.
     YamlEditing,
+    /// Typing the YAML tree search; `jump` when it leaves the tree unfiltered.
+    YamlSearching { jump: bool },
     /// File browser inside the open-file modal.
     FileBrowser { on_dir: bool, on_yaml: bool },
     /// Manual path entry inside the open-file modal.
     PathInput,
+    /// The Mule project panel: its config files, or the cross-check report.
+    ProjectPanel { report: bool },
+    /// The compare view; `missing` when the selected key can be copied,
+    /// `in_open` when it exists in the open file.
+    Compare { missing: bool, in_open: bool },
+    /// The list of suspicious plaintext values.
+    Suspicious,
+    /// Merging unsaved changes with the file changed on disk.
+    Merge,
+    /// The undo history.
+    History,
+    /// The open file's backups.
+    Backups,
+    /// The go-to prompt.
+    GoTo,
+    /// The copy menu.
+    CopyMenu,
+    /// The open file's bookmarks.
+    Bookmarks,
+    /// The review of unsaved changes.
+    Review,
+    /// Picking the environment to re-key a file to.
+    RekeyPicker,
+    /// The summary of a finished re-key.
+    RekeyReport,
+    /// The values a verify could not decrypt.
+    VerifyReport,
+    /// The detect popup; `running` until every environment was tried.
+    Detect { running: bool },
     /// A confirmation / unsaved-changes popup.
     Confirmation(ConfirmationKind),
     /// The add/edit environment form.
@@ -145,6 +188,7 @@ pub fn contextual_hints(ctx: &HintContext) -> Vec<KeyHint> {
             KeyHint::secondary("A", "Add"),
             KeyHint::secondary("Enter", "Edit"),
             KeyHint::secondary("X", "Delete"),
+            KeyHint::secondary("I", "Detect"),
             KeyHint::secondary("R", "Reveal"),
             KeyHint::primary("W/S", "Move"),
             KeyHint::secondary("Tab", "Focus"),
@@ -182,6 +226,13 @@ pub fn contextual_hints(ctx: &HintContext) -> Vec<KeyHint> {
             KeyHint::critical("Esc", "Cancel"),
             KeyHint::secondary("←/→", "Cursor"),
         ],
+        C::YamlSearching { jump } => vec![
+            KeyHint::primary("Enter", "Apply"),
+            KeyHint::primary("Tab", "Search mode"),
+            KeyHint::secondary("Shift+Tab", "Filter"),
+            KeyHint::secondary("Ctrl+F", if *jump { "Filter tree" } else { "Jump only" }),
+            KeyHint::critical("Esc", "Clear"),
+        ],
         C::FileBrowser { on_dir, on_yaml } => {
             let open = if *on_dir {
                 "Open directory"
@@ -202,6 +253,92 @@ pub fn contextual_hints(ctx: &HintContext) -> Vec<KeyHint> {
             KeyHint::secondary("Tab", "Browse"),
             KeyHint::critical("Esc", "Cancel"),
         ],
+        C::ProjectPanel { report: false } => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::primary("Enter", "Open file"),
+            KeyHint::secondary("X", "Cross-check XML"),
+            KeyHint::secondary("N", "Create environment"),
+            KeyHint::secondary("C", "Compare with open file"),
+            KeyHint::critical("Esc", "Close"),
+        ],
+        C::ProjectPanel { report: true } => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::primary("Enter", "Go to value"),
+            KeyHint::secondary("X", "Re-run"),
+            KeyHint::critical("Esc", "Back"),
+        ],
+        C::Compare { missing, in_open } => {
+            let mut hints = vec![KeyHint::primary("↑/↓", "Navigate")];
+            if *missing {
+                hints.push(KeyHint::primary("C", "Copy key"));
+            }
+            if *in_open {
+                hints.push(KeyHint::secondary("Enter", "Go to key"));
+            }
+            hints.push(KeyHint::secondary("F", "Differences only"));
+            hints.push(KeyHint::critical("Esc", "Close"));
+            hints
+        }
+        C::Suspicious => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::primary("Enter", "Go to value"),
+            KeyHint::critical("E", "Encrypt all"),
+            KeyHint::critical("Esc", "Close"),
+        ],
+        C::Merge => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::primary("Space", "Yours / disk"),
+            KeyHint::critical("Enter", "Merge"),
+            KeyHint::critical("Esc", "Keep yours"),
+        ],
+        C::History => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::primary("Enter", "Go back/forward to here"),
+            KeyHint::critical("Esc", "Close"),
+        ],
+        C::Backups => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::critical("Enter", "Load version"),
+            KeyHint::critical("Esc", "Close"),
+        ],
+        C::CopyMenu => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::primary("P/V/C/T/Y/M", "Copy item"),
+            KeyHint::critical("Enter", "Copy"),
+            KeyHint::critical("Esc", "Close"),
+        ],
+        C::GoTo => vec![
+            KeyHint::primary("↑/↓", "Pick path"),
+            KeyHint::primary("Tab", "Complete"),
+            KeyHint::critical("Enter", "Go"),
+            KeyHint::critical("Esc", "Cancel"),
+        ],
+        C::Bookmarks => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::primary("Enter", "Go to bookmark"),
+            KeyHint::primary("A", "Bookmark selected node"),
+            KeyHint::critical("X", "Delete"),
+            KeyHint::critical("Esc", "Close"),
+        ],
+        C::Review => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::critical("R", "Revert change"),
+            KeyHint::secondary("Enter", "Go to value"),
+            KeyHint::primary("Ctrl+S", "Save"),
+            KeyHint::critical("Esc", "Close"),
+        ],
+        C::RekeyPicker => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::critical("Enter", "Re-key"),
+            KeyHint::critical("Esc", "Cancel"),
+        ],
+        C::RekeyReport | C::VerifyReport => vec![
+            KeyHint::primary("↑/↓", "Navigate"),
+            KeyHint::primary("Enter", "Go to value"),
+            KeyHint::critical("Esc", "Close"),
+        ],
+        C::Detect { running: true } => vec![KeyHint::secondary("…", "Trying environments")],
+        C::Detect { running: false } => vec![KeyHint::critical("Esc", "Close")],
         C::Confirmation(kind) => confirmation_hints(*kind),
         C::EnvForm => vec![
             KeyHint::primary("Tab/↑/↓", "Move"),
@@ -224,7 +361,7 @@ fn screens_hint() -> KeyHint {
 fn confirmation_hints(kind: ConfirmationKind) -> Vec<KeyHint> {
     use ConfirmationKind as K;
     match kind {
-        K::UnsavedQuit | K::UnsavedOpen => vec![
+        K::UnsavedQuit | K::UnsavedClose => vec![
             KeyHint::critical("S", "Save"),
             KeyHint::critical("D", "Discard"),
             KeyHint::critical("Esc", "Cancel"),
@@ -247,12 +384,16 @@ fn confirmation_hints(kind: ConfirmationKind) -> Vec<KeyHint> {
 fn yaml_hints(y: &YamlHints) -> Vec<KeyHint> {
     // No file open: only opening a file and leaving are valid.
     if !y.file_open {
-        return vec![
-            KeyHint::primary("Ctrl+O", "Open file"),
+        let mut hints = vec![KeyHint::primary("Ctrl+O", "Open file")];
+        if y.project {
+            hints.push(KeyHint::primary("P", "Project"));
+        }
+        hints.extend([
             KeyHint::secondary("Tab", "Focus"),
             screens_hint(),
             KeyHint::critical("Esc", "Back"),
-        ];
+        ]);
+        return hints;
     }
 
     // Environment pane focused: only environment actions.
@@ -289,17 +430,57 @@ fn yaml_hints(y: &YamlHints) -> Vec<KeyHint> {
             if y.env_selected && !y.crypto_in_progress {
                 hints.push(KeyHint::primary("D", "Decrypt"));
             }
+            hints.push(KeyHint::secondary("I", "Detect"));
             hints.push(KeyHint::secondary("R", "Reveal"));
         }
         Some(YamlSelectionKind::ScalarUneditable) => {}
         None => {}
     }
+    if y.selection.is_some() && y.comments {
+        hints.push(KeyHint::secondary("#", "Comment"));
+    }
+    if y.selection.is_some() {
+        hints.push(KeyHint::secondary("Space", "Mark"));
+    }
+    if y.searching {
+        hints.push(KeyHint::secondary("N/Shift+N", "Next/prev match"));
+    }
+    hints.push(KeyHint::secondary("G", "Go to path"));
+    hints.push(KeyHint::secondary("'", "Bookmarks"));
+    if y.selection.is_some() || y.marks > 0 {
+        hints.push(KeyHint::primary("Y", "Copy…"));
+    }
+    if y.marks > 0 {
+        hints.push(KeyHint::secondary("M", "Clear marks"));
+    }
     hints.push(KeyHint::primary("Tab", "Next pane"));
     if y.crypto_in_progress {
         hints.push(KeyHint::secondary("…", "Working"));
     }
     hints.push(KeyHint::secondary("Ctrl+O", "Open"));
+    if y.buffers > 1 {
+        hints.push(KeyHint::secondary("[/]", "Switch file"));
+    }
+    hints.push(KeyHint::secondary("Ctrl+W", "Close file"));
+    hints.push(KeyHint::secondary("C", "Compare"));
+    hints.push(KeyHint::secondary("!", "Suspicious"));
+    if y.peek {
+        hints.push(KeyHint::secondary("O", "Peek off"));
+    }
+    if y.env_selected && !y.crypto_in_progress {
+        if !y.peek {
+            hints.push(KeyHint::secondary("O", "Peek"));
+        }
+        hints.push(KeyHint::secondary("V", "Verify"));
+        hints.push(KeyHint::secondary("K", "Re-key"));
+    }
+    if y.project {
+        hints.push(KeyHint::secondary("P", "Project"));
+    }
+    hints.push(KeyHint::secondary("U", "History"));
+    hints.push(KeyHint::secondary("B", "Backups"));
     if y.dirty {
+        hints.push(KeyHint::secondary("Ctrl+D", "Review"));
         hints.push(KeyHint::secondary("Ctrl+S", "Save"));
         hints.push(KeyHint::secondary("Ctrl+R", "Restore"));
     }
@@ -432,6 +613,12 @@ mod tests {
             dirty: false,
             env_selected: true,
             selection,
+            comments: true,
+            project: false,
+            peek: false,
+            marks: 0,
+            buffers: 1,
+            searching: false,
         }))
     }
 
@@ -455,6 +642,12 @@ mod tests {
                 dirty: true,
                 env_selected: true,
                 selection: Some(YamlSelectionKind::ScalarPlain),
+                comments: true,
+                project: false,
+                peek: false,
+                marks: 0,
+                buffers: 1,
+                searching: false,
             }),
             HintContext::Yaml(YamlHints {
                 focus: YamlHintFocus::Environments,
@@ -463,6 +656,12 @@ mod tests {
                 dirty: false,
                 env_selected: true,
                 selection: None,
+                comments: true,
+                project: false,
+                peek: false,
+                marks: 0,
+                buffers: 1,
+                searching: false,
             }),
             HintContext::Yaml(YamlHints {
                 focus: YamlHintFocus::Tree,
@@ -471,6 +670,12 @@ mod tests {
                 dirty: false,
                 env_selected: false,
                 selection: None,
+                comments: true,
+                project: false,
+                peek: false,
+                marks: 0,
+                buffers: 1,
+                searching: false,
             }),
         ];
         for ctx in &with_shortcuts {
@@ -486,7 +691,26 @@ mod tests {
             HintContext::MainSearching,
             HintContext::PlaygroundEditing,
             HintContext::YamlEditing,
+            HintContext::YamlSearching { jump: false },
             HintContext::PathInput,
+            HintContext::ProjectPanel { report: false },
+            HintContext::ProjectPanel { report: true },
+            HintContext::Compare {
+                missing: true,
+                in_open: true,
+            },
+            HintContext::Suspicious,
+            HintContext::Merge,
+            HintContext::History,
+            HintContext::Backups,
+            HintContext::GoTo,
+            HintContext::CopyMenu,
+            HintContext::Bookmarks,
+            HintContext::Review,
+            HintContext::RekeyPicker,
+            HintContext::RekeyReport,
+            HintContext::VerifyReport,
+            HintContext::Detect { running: false },
             HintContext::FileBrowser {
                 on_dir: false,
                 on_yaml: true,
@@ -577,6 +801,18 @@ mod tests {
         assert!(!has_desc(&h, "Navigate"));
     }
 
+    #[test]
+    fn yaml_search_offers_modes_filters_and_jumping() {
+        let filtering = contextual_hints(&HintContext::YamlSearching { jump: false });
+        assert!(has_desc(&filtering, "Search mode"));
+        assert!(has_desc(&filtering, "Filter"));
+        assert!(has_desc(&filtering, "Jump only"));
+        assert!(has_desc(&filtering, "Clear"));
+        let jumping = contextual_hints(&HintContext::YamlSearching { jump: true });
+        assert!(has_desc(&jumping, "Filter tree"));
+        assert!(!has_desc(&jumping, "Encrypt"));
+    }
+
     #[test]
     fn file_browser_hints() {
         let dir = contextual_hints(&HintContext::FileBrowser {
@@ -645,6 +881,12 @@ mod tests {
                 dirty: true,
                 env_selected: true,
                 selection: Some(YamlSelectionKind::ScalarEncrypted),
+                comments: true,
+                project: false,
+                peek: false,
+                marks: 0,
+                buffers: 1,
+                searching: false,
             }),
         ] {
             let h = contextual_hints(&ctx);
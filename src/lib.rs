@@ -5,10 +5,12 @@ pub mod clipboard;
 pub mod components;
 pub mod config;
 pub mod dencrypt;
+pub mod detect;
 pub mod environment;
 pub mod errors;
 pub mod hints;
 pub mod logging;
+pub mod mule;
 pub mod panes;
 pub mod state;
 pub mod text_field;
@@ -1,15 +1,54 @@
 use clap::Parser;
-use cli::Cli;
-use color_eyre::Result;
+use cli::{Cli, Command};
+use color_eyre::{eyre::eyre, Result};
 
-use lazyprop::{app::App, cli};
+use lazyprop::{
+    app::App,
+    cli, config,
+    environment::Environments,
+    yaml_editor::{backup, rekey},
+};
 #[tokio::main]
 async fn main() -> Result<()> {
     lazyprop::errors::init()?;
     lazyprop::logging::init()?;
 
     let args = Cli::parse();
+    if let Some(Command::Rekey {
+        from,
+        to,
+        dry_run,
+        file,
+    }) = args.command
+    {
+        let envs_path = config::resolve_envs_path(args.envs)?;
+        let jar = config::resolve_jar_path(args.jar)?;
+        let envs = Environments::new(envs_path.to_string_lossy())?;
+        // Re-keys are backed up like saves from the editor.
+        let keep =
+            config::Config::new().map_or(backup::Settings::default().keep, |c| c.backups.keep);
+        let backups =
+            (keep > 0).then(|| backup::Store::new(config::lazyprop_home().join("backups"), keep));
+        let summary = rekey::run_command(
+            &envs,
+            &jar,
+            &from,
+            &to,
+            file.as_ref(),
+            dry_run,
+            backups.as_ref(),
+        )
+        .map_err(|e| eyre!(e))?;
+        print!("{}", rekey::format_summary(&summary));
+        if !summary.failed.is_empty() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     let mut app = App::new(args.tick_rate, args.frame_rate, args.envs, args.jar)?;
+    if let Some(dir) = args.project {
+        app.open_project(&dir);
+    }
     if let Some(file) = args.file {
         app.open_yaml_file(&file);
     }
@@ -0,0 +1,316 @@
+//! A Mule project workspace: the config files under `src/main/resources` and
+//! how many of their values are encrypted, plaintext, or plaintext that looks
+//! like a secret (see [`SecretRules`]).
+
+use std::path::{Path, PathBuf};
+
+use super::secure_config::{self, SecureConfig};
+use crate::environment::Environment;
+use crate::yaml_editor::document::{self, DocFormat, Document, NodeKind};
+use crate::yaml_editor::per_file::PerFile;
+use crate::yaml_editor::suspicious::SecretRules;
+
+/// Where a Mule app keeps its config files, relative to the project root.
+pub const RESOURCES_DIR: &str = "src/main/resources";
+
+/// Value counts for one config file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValueCounts {
+    /// Values wrapped as `![...]`.
+    pub encrypted: usize,
+    /// Every other non-empty value, suspicious ones included.
+    pub plaintext: usize,
+    /// Plaintext values that look like secrets (see [`SecretRules`]).
+    pub suspicious: usize,
+}
+
+/// One config file in the project.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    /// The path relative to [`RESOURCES_DIR`], for display.
+    pub name: String,
+    /// The counts, or why the file could not be read.
+    pub counts: Result<ValueCounts, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub root: PathBuf,
+    /// The directory name, shown as the project's title.
+    pub name: String,
+    pub configs: Vec<ConfigFile>,
+    /// The app's `<secure-properties:config>` elements.
+    pub secure_configs: Vec<SecureConfig>,
+    /// Elements that could not be used (e.g. an unsupported algorithm).
+    pub secure_errors: Vec<String>,
+    /// Environments created for a config file, overriding the proposed name.
+    pub links: Links,
+    /// What counts as a suspicious value.
+    pub rules: SecretRules,
+}
+
+/// Which environment each config file was linked to, kept across sessions
+/// in `project-links.yaml` in the lazyprop home.
+pub type Links = PerFile<String>;
+
+impl Project {
+    /// Load the Mule project rooted at `root`, listing its config files.
+    /// Saved links are read from `links` (see [`Links`]); `None` keeps them
+    /// for the session only.
+    pub fn load(
+        root: &Path,
+        rules: SecretRules,
+        links: Option<PathBuf>,
+    ) -> Result<Project, String> {
+        if !root.is_dir() {
+            return Err(format!("Not a directory: {}", root.display()));
+        }
+        if !is_mule_project(root) {
+            return Err(format!(
+                "Not a Mule project (no mule-artifact.json or Mule pom.xml): {}",
+                root.display()
+            ));
+        }
+        let name = root
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| root.display().to_string());
+        let mut project = Project {
+            root: root.to_path_buf(),
+            name,
+            configs: Vec::new(),
+            secure_configs: Vec::new(),
+            secure_errors: Vec::new(),
+            links: links.map(Links::load).unwrap_or_default(),
+            rules,
+        };
+        project.rescan();
+        Ok(project)
+    }
+
+    /// Re-list the config files, recount their values and re-read the
+    /// secure-properties elements from disk.
+    pub fn rescan(&mut self) {
+        (self.secure_configs, self.secure_errors) = secure_config::discover(&self.root);
+        let resources = self.root.join(RESOURCES_DIR);
+        let mut paths = Vec::new();
+        collect_configs(&resources, &mut paths);
+        paths.sort();
+        self.configs = paths
+            .into_iter()
+            .map(|path| ConfigFile {
+                name: path
+                    .strip_prefix(&resources)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                counts: load_config(&path).map(|doc| count_values(&doc, &self.rules)),
+                path,
+            })
+            .collect();
+    }
+
+    /// The environment the app's `<secure-properties:config>` implies for
+    /// `config`: its algorithm, mode and IV setting, named after the project
+    /// and whatever the `file` placeholder matched (`my-app-dev`).
+    pub fn proposal(&self, config: &ConfigFile) -> Option<Environment> {
+        self.secure_configs.iter().find_map(|sc| {
+            let matched = sc.matches(&config.name)?;
+            let name = if matched.is_empty() {
+                self.name.clone()
+            } else {
+                format!("{}-{matched}", self.name)
+            };
+            Some(sc.environment(&name))
+        })
+    }
+
+    /// The name of the environment linked to the config at `path`: one
+    /// created for it, else the proposed name.
+    pub fn linked_env(&self, path: &Path) -> Option<String> {
+        if let Some(name) = self.links.get(path) {
+            return Some(name.clone());
+        }
+        let config = self.configs.iter().find(|c| c.path == path)?;
+        self.proposal(config).map(|env| env.name)
+    }
+}
+
+/// Whether `dir` holds a Mule app: a `mule-artifact.json`, or a `pom.xml`
+/// that mentions Mule (`mule-application` packaging or the Mule plugin).
+pub fn is_mule_project(dir: &Path) -> bool {
+    dir.join("mule-artifact.json").is_file()
+        || std::fs::read_to_string(dir.join("pom.xml"))
+            .is_ok_and(|pom| pom.contains("mule-application") || pom.contains("mule-maven-plugin"))
+}
+
+/// Recursively collect the files under `dir` the editor can open. Hidden
+/// directories are skipped; unreadable ones are ignored. Symlinked
+/// directories are not followed, so a link cycle cannot recurse forever.
+fn collect_configs(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_configs(&path, out);
+        } else if DocFormat::from_path(&path).is_some() {
+            out.push(path);
+        }
+    }
+}
+
+/// Read, validate and parse a config file.
+pub fn load_config(path: &Path) -> Result<Document, String> {
+    let format = DocFormat::from_path(path).ok_or("Unsupported file type")?;
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Document::validate_as(&content, format)
+        .map_err(|e| format!("Invalid {}: {e}", format.label()))?;
+    Ok(Document::parse_as(&content, format))
+}
+
+/// Count the encrypted, plaintext and suspicious scalar values in `doc`.
+pub fn count_values(doc: &Document, rules: &SecretRules) -> ValueCounts {
+    let mut counts = ValueCounts::default();
+    for node in doc.nodes() {
+        if node.kind != NodeKind::Scalar {
+            continue;
+        }
+        let Some(value) = doc.logical_value(node.id) else {
+            continue;
+        };
+        if document::is_wrapped(&value) {
+            counts.encrypted += 1;
+        } else if !value.trim().is_empty() {
+            counts.plaintext += 1;
+            if rules.check(&node.path, &value).is_some() {
+                counts.suspicious += 1;
+            }
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mule::test_support::TempDir;
+
+    #[test]
+    fn lists_configs_with_counts() {
+        let dir = TempDir::new();
+        dir.write("mule-artifact.json", "{}");
+        dir.write(
+            "src/main/resources/config-dev.yaml",
+            "db:\n  user: admin\n  password: hunter2\n  url: \"${db.host}\"\napi:\n  clientSecret: \"![abc]\"\n",
+        );
+        dir.write(
+            "src/main/resources/props/config-prod.properties",
+            "db.password=![xyz]\nempty=\n",
+        );
+        dir.write("src/main/resources/broken.yaml", "a: [\n");
+        dir.write("src/main/resources/log4j2.xml", "<Configuration/>");
+        let project = Project::load(&dir.0, SecretRules::default(), None).unwrap();
+        let names: Vec<&str> = project.configs.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "broken.yaml",
+                "config-dev.yaml",
+                "props/config-prod.properties"
+            ]
+        );
+        assert!(project.configs[0].counts.is_err());
+        assert_eq!(
+            project.configs[1].counts,
+            Ok(ValueCounts {
+                encrypted: 1,
+                plaintext: 3,
+                suspicious: 1
+            })
+        );
+        assert_eq!(
+            project.configs[2].counts,
+            Ok(ValueCounts {
+                encrypted: 1,
+                plaintext: 0,
+                suspicious: 0
+            })
+        );
+    }
+
+    #[test]
+    fn proposes_environments_from_secure_configs() {
+        let dir = TempDir::new();
+        dir.write("mule-artifact.json", "{}");
+        dir.write(
+            "src/main/mule/global.xml",
+            r#"<mule>
+<secure-properties:config name="Env" file="config/${env}-${region}.yaml" key="${k}"/>
+<secure-properties:config name="Shared" file="shared.properties" key="${k}"/>
+</mule>"#,
+        );
+        dir.write("src/main/resources/config/dev-eu.yaml", "a: 1\n");
+        dir.write("src/main/resources/shared.properties", "a=1\n");
+        dir.write("src/main/resources/other.yaml", "a: 1\n");
+        let mut project = Project::load(&dir.0, SecretRules::default(), None).unwrap();
+        let proposed: Vec<Option<String>> = project
+            .configs
+            .iter()
+            .map(|c| project.proposal(c).map(|env| env.name))
+            .collect();
+        let name = project.name.clone();
+        assert_eq!(
+            proposed,
+            vec![Some(format!("{name}-dev-eu")), None, Some(name.clone())]
+        );
+        let dev = project.configs[0].path.clone();
+        project.links.insert(&dev, "mine".to_string()).unwrap();
+        assert_eq!(project.linked_env(&dev).as_deref(), Some("mine"));
+    }
+
+    #[test]
+    fn links_survive_a_reload() {
+        let dir = TempDir::new();
+        dir.write("mule-artifact.json", "{}");
+        dir.write("src/main/resources/config-dev.yaml", "a: 1\n");
+        let store = dir.0.join("home/project-links.yaml");
+        let load = || Project::load(&dir.0, SecretRules::default(), Some(store.clone())).unwrap();
+        let mut project = load();
+        let dev = project.configs[0].path.clone();
+        assert_eq!(project.linked_env(&dev), None);
+        project.links.insert(&dev, "dev".to_string()).unwrap();
+        assert_eq!(load().linked_env(&dev).as_deref(), Some("dev"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_are_not_followed() {
+        let dir = TempDir::new();
+        dir.write("mule-artifact.json", "{}");
+        dir.write("src/main/resources/app.yaml", "a: 1\n");
+        let resources = dir.0.join("src/main/resources");
+        std::os::unix::fs::symlink(&resources, resources.join("loop")).unwrap();
+        let project = Project::load(&dir.0, SecretRules::default(), None).unwrap();
+        assert_eq!(project.configs.len(), 1);
+    }
+
+    #[test]
+    fn detects_mule_projects() {
+        let dir = TempDir::new();
+        assert!(Project::load(&dir.0, SecretRules::default(), None).is_err());
+        dir.write("pom.xml", "<project><packaging>jar</packaging></project>");
+        assert!(!is_mule_project(&dir.0));
+        dir.write(
+            "pom.xml",
+            "<project><packaging>mule-application</packaging></project>",
+        );
+        assert!(is_mule_project(&dir.0));
+    }
+}
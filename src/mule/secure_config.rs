@@ -0,0 +1,290 @@
+//! Discovering encryption settings from a Mule app's
+//! `<secure-properties:config>` elements.
+//!
+//! ```xml
+//! <secure-properties:config name="Secure" file="config-${env}.yaml" key="${secure.key}">
+//!     <secure-properties:encrypt algorithm="Blowfish" mode="CBC" useRandomIVs="true"/>
+//! </secure-properties:config>
+//! ```
+//!
+//! Each element says which config files it decrypts (`file`, usually with an
+//! `${env}` placeholder) and how. Matching it against the project's config
+//! files yields one proposed [`Environment`] per file.
+
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::xref::{collect_xml, MULE_DIR};
+use crate::environment::{Algorithm, Environment, State as CipherMode};
+
+const CONFIG_TAG: &str = "<secure-properties:config";
+const ENCRYPT_TAG: &str = "<secure-properties:encrypt";
+const CONFIG_END: &str = "</secure-properties:config>";
+
+lazy_static! {
+    /// A `${...}` placeholder in a `file` attribute.
+    static ref PLACEHOLDER: Regex = Regex::new(r"\$\{[^}]*\}").expect("valid regex");
+    /// A `name="value"` or `name='value'` attribute.
+    static ref ATTRIBUTE: Regex =
+        Regex::new(r#"([\w:.-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid regex");
+}
+
+/// One `<secure-properties:config>` element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecureConfig {
+    /// The element's `name`.
+    pub name: String,
+    /// The `file` attribute, relative to `src/main/resources`.
+    pub file: String,
+    /// The `key` attribute as written, usually a `${...}` placeholder.
+    pub key: String,
+    pub algorithm: Algorithm,
+    pub mode: CipherMode,
+    pub use_random_ivs: bool,
+    /// The XML file it was declared in, relative to the project root.
+    pub source: String,
+}
+
+impl SecureConfig {
+    /// Whether this element decrypts the config at `name` (relative to the
+    /// resources directory). Returns what its placeholders matched, joined
+    /// with `-` — `"dev"` for `config-${env}.yaml` against `config-dev.yaml`,
+    /// or an empty string for a literal file name.
+    pub fn matches(&self, name: &str) -> Option<String> {
+        let literals: Vec<&str> = PLACEHOLDER.split(&self.file).collect();
+        let (first, literals) = literals.split_first()?;
+        let rest = name.strip_prefix(first)?;
+        let mut caps = Vec::new();
+        fill_placeholders(rest, literals, &mut caps).then(|| caps.join("-"))
+    }
+
+    /// The key, when the XML spells it out rather than using a placeholder.
+    pub fn literal_key(&self) -> Option<&str> {
+        (!self.key.is_empty() && !self.key.contains("${")).then_some(self.key.as_str())
+    }
+
+    /// An environment with these settings. The key is empty unless the XML
+    /// holds it literally; it is normally supplied at deploy time.
+    pub fn environment(&self, name: &str) -> Environment {
+        Environment::new(
+            name.to_string(),
+            self.algorithm,
+            self.mode,
+            self.use_random_ivs,
+            self.literal_key().unwrap_or_default().to_string(),
+        )
+    }
+}
+
+/// Match `rest` against placeholders each followed by one of `literals`,
+/// pushing what each placeholder took onto `caps`. A placeholder takes at
+/// least one character and as few as it can.
+fn fill_placeholders<'a>(rest: &'a str, literals: &[&str], caps: &mut Vec<&'a str>) -> bool {
+    let Some((literal, more)) = literals.split_first() else {
+        return rest.is_empty();
+    };
+    let ends = rest
+        .char_indices()
+        .skip(1)
+        .map(|(i, _)| i)
+        .chain((!rest.is_empty()).then_some(rest.len()));
+    for end in ends {
+        if !rest[end..].starts_with(literal) {
+            continue;
+        }
+        caps.push(&rest[..end]);
+        if fill_placeholders(&rest[end + literal.len()..], more, caps) {
+            return true;
+        }
+        caps.pop();
+    }
+    false
+}
+
+/// Parse every `<secure-properties:config>` element in `xml`. Elements using
+/// an algorithm or mode lazyprop cannot run are reported as errors.
+pub fn parse(xml: &str, source: &str) -> Vec<Result<SecureConfig, String>> {
+    let xml = strip_comments(xml);
+    let mut out = Vec::new();
+    let mut rest = xml.as_str();
+    while let Some(start) = rest.find(CONFIG_TAG) {
+        let element = &rest[start..];
+        let Some(tag_end) = element.find('>') else {
+            break;
+        };
+        let tag = &element[..tag_end];
+        let attrs = attributes(tag);
+        // The <encrypt> child, if the element is not self-closing.
+        let body = if tag.ends_with('/') {
+            ""
+        } else {
+            let body = &element[tag_end..];
+            &body[..body.find(CONFIG_END).unwrap_or(body.len())]
+        };
+        let encrypt = body
+            .find(ENCRYPT_TAG)
+            .map(|i| {
+                let tag = &body[i..];
+                attributes(&tag[..tag.find('>').unwrap_or(tag.len())])
+            })
+            .unwrap_or_default();
+        out.push(secure_config(&attrs, &encrypt, source));
+        rest = &element[tag_end..];
+    }
+    out
+}
+
+fn secure_config(
+    attrs: &[(String, String)],
+    encrypt: &[(String, String)],
+    source: &str,
+) -> Result<SecureConfig, String> {
+    let get = |list: &[(String, String)], key: &str| {
+        list.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+    };
+    let name = get(attrs, "name").unwrap_or_default();
+    let algorithm = match get(encrypt, "algorithm") {
+        None => Algorithm::AES,
+        Some(a) => Algorithm::ALL
+            .into_iter()
+            .find(|alg| format!("{alg:?}").eq_ignore_ascii_case(&a))
+            .ok_or_else(|| format!("{source}: {name}: unsupported algorithm {a}"))?,
+    };
+    let mode = match get(encrypt, "mode") {
+        None => CipherMode::CBC,
+        Some(m) => CipherMode::ALL
+            .into_iter()
+            .find(|mode| format!("{mode:?}").eq_ignore_ascii_case(&m))
+            .ok_or_else(|| format!("{source}: {name}: unsupported mode {m}"))?,
+    };
+    Ok(SecureConfig {
+        file: get(attrs, "file").unwrap_or_default(),
+        key: get(attrs, "key").unwrap_or_default(),
+        algorithm,
+        mode: algorithm.reconcile_mode(mode),
+        use_random_ivs: get(encrypt, "useRandomIVs").is_some_and(|v| v == "true"),
+        source: source.to_string(),
+        name,
+    })
+}
+
+/// `name="value"` pairs in a start tag, with the basic XML entities decoded.
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    ATTRIBUTE
+        .captures_iter(tag)
+        .map(|c| {
+            let value = c.get(2).or_else(|| c.get(3)).map_or("", |m| m.as_str());
+            (c[1].to_string(), unescape_xml(value))
+        })
+        .collect()
+}
+
+/// `xml` without its `<!-- ... -->` comments, so commented-out elements are
+/// not read.
+fn strip_comments(xml: &str) -> String {
+    let mut out = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find("<!--") {
+        out.push_str(&rest[..start]);
+        let comment = &rest[start..];
+        rest = &comment[comment.find("-->").map_or(comment.len(), |e| e + 3)..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn unescape_xml(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// All `<secure-properties:config>` elements in the project's flow XML, and
+/// the ones that could not be used.
+pub fn discover(root: &Path) -> (Vec<SecureConfig>, Vec<String>) {
+    let mut files = Vec::new();
+    collect_xml(&root.join(MULE_DIR), &mut files);
+    files.sort();
+    let (mut configs, mut errors) = (Vec::new(), Vec::new());
+    for path in files {
+        let Ok(xml) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let source = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        for parsed in parse(&xml, &source) {
+            match parsed {
+                Ok(config) => configs.push(config),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+    (configs, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<mule>
+  <secure-properties:config name="Secure" file="config/config-${env}.yaml" key="${secure.key}" doc:name="Secure">
+    <secure-properties:encrypt algorithm="Blowfish" mode="CFB" useRandomIVs="true"/>
+  </secure-properties:config>
+  <secure-properties:config name="Literal" file='shared.properties' key="0123456789abcdef"/>
+  <secure-properties:config name="Odd" file="x.yaml" key="k">
+    <secure-properties:encrypt algorithm="Twofish"/>
+  </secure-properties:config>
+  <!-- <secure-properties:config name="Old" file="old.yaml" key="k"/> -->
+</mule>"#;
+
+    #[test]
+    fn parses_elements_with_defaults() {
+        let parsed = parse(XML, "src/main/mule/global.xml");
+        assert_eq!(parsed.len(), 3);
+        let first = parsed[0].as_ref().unwrap();
+        assert_eq!(first.file, "config/config-${env}.yaml");
+        assert_eq!(first.key, "${secure.key}");
+        assert_eq!(first.algorithm, Algorithm::Blowfish);
+        assert_eq!(first.mode, CipherMode::CFB);
+        assert!(first.use_random_ivs);
+        assert_eq!(first.literal_key(), None);
+        let second = parsed[1].as_ref().unwrap();
+        assert_eq!(second.algorithm, Algorithm::AES);
+        assert_eq!(second.mode, CipherMode::CBC);
+        assert!(!second.use_random_ivs);
+        assert_eq!(second.literal_key(), Some("0123456789abcdef"));
+        assert!(parsed[2].as_ref().unwrap_err().contains("Twofish"));
+    }
+
+    #[test]
+    fn file_placeholders_match_config_names() {
+        let parsed = parse(XML, "global.xml");
+        let first = parsed[0].as_ref().unwrap();
+        assert_eq!(
+            first.matches("config/config-dev.yaml").as_deref(),
+            Some("dev")
+        );
+        assert_eq!(first.matches("config-dev.yaml"), None);
+        assert_eq!(first.matches("config/config-.yaml"), None);
+        let two = SecureConfig {
+            file: "${app}-${env}.yaml".to_string(),
+            ..first.clone()
+        };
+        assert_eq!(
+            two.matches("my-app-dev.yaml").as_deref(),
+            Some("my-app-dev")
+        );
+        let second = parsed[1].as_ref().unwrap();
+        assert_eq!(second.matches("shared.properties").as_deref(), Some(""));
+        let env = first.environment("app-dev");
+        assert_eq!(env.name, "app-dev");
+        assert!(env.key.is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+//! Cross-checking the property placeholders in a Mule app's XML against its
+//! config files.
+//!
+//! Every `${key}` and `${secure::key}` in the flows under `src/main/mule` is
+//! resolved in each config file through [`Document::find_by_path`]. A key
+//! defined in no config, a secure reference whose value is stored in
+//! plaintext, and an encrypted value nothing references are reported.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::project::{self, Project};
+use crate::yaml_editor::document::{self, Document, NodeKind, PathSeg};
+use crate::yaml_editor::query;
+
+/// Where a Mule app keeps its flow XML, relative to the project root.
+pub const MULE_DIR: &str = "src/main/mule";
+
+/// Runtime properties Mule defines itself; references to them are never
+/// reported missing.
+const RUNTIME_PREFIXES: [&str; 2] = ["mule.", "app."];
+const RUNTIME_KEYS: [&str; 1] = ["env"];
+
+/// One `${...}` placeholder in an XML file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// The XML file, relative to the project root.
+    pub file: String,
+    pub line: usize,
+    /// The property key, without `secure::`.
+    pub key: String,
+    /// Whether it was written `${secure::key}`.
+    pub secure: bool,
+}
+
+/// A value in one config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub config: PathBuf,
+    /// The config's name as listed in the project.
+    pub name: String,
+    pub path: Vec<PathSeg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// No config file defines the referenced key.
+    Missing(Reference),
+    /// A `${secure::...}` reference whose value is stored in plaintext.
+    SecurePlaintext(Reference, Location),
+    /// An encrypted value that no XML placeholder references.
+    Unreferenced(Location),
+}
+
+impl Finding {
+    /// The config value this finding points at, if any.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Finding::Missing(_) => None,
+            Finding::SecurePlaintext(_, loc) | Finding::Unreferenced(loc) => Some(loc),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub xml_files: usize,
+    pub references: usize,
+    pub findings: Vec<Finding>,
+}
+
+/// Scan the project's XML and check every placeholder against its configs.
+/// Config files that fail to parse are skipped (the project list already
+/// shows why).
+pub fn cross_check(project: &Project) -> Report {
+    let mut xml = Vec::new();
+    collect_xml(&project.root.join(MULE_DIR), &mut xml);
+    xml.sort();
+    let mut references = Vec::new();
+    for path in &xml {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let file = path
+            .strip_prefix(&project.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        references.extend(
+            scan_placeholders(&text)
+                .into_iter()
+                .map(|(line, key, secure)| Reference {
+                    file: file.clone(),
+                    line,
+                    key,
+                    secure,
+                }),
+        );
+    }
+    let configs: Vec<(&project::ConfigFile, Document)> = project
+        .configs
+        .iter()
+        .filter_map(|c| project::load_config(&c.path).ok().map(|doc| (c, doc)))
+        .collect();
+    Report {
+        xml_files: xml.len(),
+        references: references.len(),
+        findings: check(&references, &configs),
+    }
+}
+
+fn check(references: &[Reference], configs: &[(&project::ConfigFile, Document)]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    // Node ids each config's references resolved to, for the unused check.
+    let mut used: Vec<HashSet<usize>> = vec![HashSet::new(); configs.len()];
+    // Each plaintext value and each missing key is reported once, at its
+    // first reference.
+    let mut reported = HashSet::new();
+    let mut missing = HashSet::new();
+    for reference in references {
+        let mut found = false;
+        for (i, (config, doc)) in configs.iter().enumerate() {
+            let Some(id) = resolve(doc, &reference.key) else {
+                continue;
+            };
+            found = true;
+            used[i].insert(id);
+            let plaintext = doc
+                .logical_value(id)
+                .is_some_and(|v| !document::is_wrapped(&v));
+            if reference.secure && plaintext && reported.insert((i, id)) {
+                findings.push(Finding::SecurePlaintext(
+                    reference.clone(),
+                    location(config, doc, id),
+                ));
+            }
+        }
+        if !found && !is_runtime(&reference.key) && missing.insert(reference.key.as_str()) {
+            findings.push(Finding::Missing(reference.clone()));
+        }
+    }
+    for (i, (config, doc)) in configs.iter().enumerate() {
+        for node in doc.nodes() {
+            let encrypted = node.kind == NodeKind::Scalar
+                && doc
+                    .logical_value(node.id)
+                    .is_some_and(|v| document::is_wrapped(&v));
+            if encrypted && !used[i].contains(&node.id) {
+                findings.push(Finding::Unreferenced(location(config, doc, node.id)));
+            }
+        }
+    }
+    findings
+}
+
+fn location(config: &project::ConfigFile, doc: &Document, id: usize) -> Location {
+    Location {
+        config: config.path.clone(),
+        name: config.name.clone(),
+        path: doc.nodes()[id].path.clone(),
+    }
+}
+
+/// The node a property key names: a dotted path into nested keys
+/// (`db.password`), or a single key containing dots, as in `.properties`.
+pub fn resolve(doc: &Document, key: &str) -> Option<usize> {
+    query::parse_path(key)
+        .ok()
+        .and_then(|path| doc.find_by_path(&path))
+        .or_else(|| doc.find_by_path(&[PathSeg::Key(key.to_string())]))
+}
+
+fn is_runtime(key: &str) -> bool {
+    RUNTIME_KEYS.contains(&key) || RUNTIME_PREFIXES.iter().any(|p| key.starts_with(p))
+}
+
+/// `(line, key, secure)` for each `${...}` placeholder in `text`, skipping
+/// XML comments.
+pub fn scan_placeholders(text: &str) -> Vec<(usize, String, bool)> {
+    let mut out = Vec::new();
+    let (mut pos, mut line, mut counted) = (0, 1, 0);
+    while let Some(i) = text[pos..].find(['$', '<']) {
+        let start = pos + i;
+        let at = &text[start..];
+        if at.starts_with("<!--") {
+            pos = start + at.find("-->").map_or(at.len(), |e| e + 3);
+            continue;
+        }
+        pos = start + 1;
+        let Some(body) = at.strip_prefix("${") else {
+            continue;
+        };
+        let Some(close) = body.find(['}', '\n', '<', '"']) else {
+            continue;
+        };
+        if body.as_bytes()[close] != b'}' {
+            continue;
+        }
+        let raw = body[..close].trim();
+        let (key, secure) = match raw.strip_prefix("secure::") {
+            Some(k) => (k.trim(), true),
+            None => (raw, false),
+        };
+        if !key.is_empty() && !key.contains("${") {
+            line += text[counted..start].matches('\n').count();
+            counted = start;
+            out.push((line, key.to_string(), secure));
+        }
+    }
+    out
+}
+
+/// Recursively collect the `.xml` files under `dir`, without following
+/// symlinked directories (a link cycle would recurse forever).
+pub(super) fn collect_xml(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_xml(&path, out);
+        } else if path.extension().is_some_and(|e| e == "xml") {
+            out.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mule::test_support::TempDir;
+    use crate::yaml_editor::suspicious::SecretRules;
+
+    #[test]
+    fn finds_placeholders_outside_comments() {
+        let xml = "<flow>\n  <!-- ${secure::old.key} -->\n  <db:config password=\"${secure::db.password}\"\n    user=\"${ db.user }\" url=\"jdbc:${db.host}:${db.port}\"/>\n  <set-payload value=\"${unterminated\"/>\n</flow>\n";
+        assert_eq!(
+            scan_placeholders(xml),
+            vec![
+                (3, "db.password".to_string(), true),
+                (4, "db.user".to_string(), false),
+                (4, "db.host".to_string(), false),
+                (4, "db.port".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_plaintext_and_unused() {
+        let dir = TempDir::new();
+        dir.write("mule-artifact.json", "{}");
+        dir.write(
+            "src/main/mule/api.xml",
+            "<mule>\n<db password=\"${secure::db.password}\" user=\"${db.user}\"/>\n<http key=\"${secure::api.key}\" home=\"${mule.home}\"/>\n</mule>\n",
+        );
+        dir.write(
+            "src/main/resources/config-dev.yaml",
+            "db:\n  user: admin\n  password: hunter2\nlegacy:\n  token: \"![old]\"\n",
+        );
+        dir.write(
+            "src/main/resources/config-prod.properties",
+            "db.user=admin\ndb.password=![enc]\n",
+        );
+        let project = Project::load(&dir.0, SecretRules::default(), None).unwrap();
+        let report = cross_check(&project);
+        assert_eq!(report.xml_files, 1);
+        assert_eq!(report.references, 4);
+        let reference = |line, key: &str| Reference {
+            file: "src/main/mule/api.xml".to_string(),
+            line,
+            key: key.to_string(),
+            secure: true,
+        };
+        let location = |name: &str, path: &[&str]| Location {
+            config: project.root.join("src/main/resources").join(name),
+            name: name.to_string(),
+            path: path.iter().map(|k| PathSeg::Key(k.to_string())).collect(),
+        };
+        assert_eq!(
+            report.findings,
+            vec![
+                Finding::SecurePlaintext(
+                    reference(2, "db.password"),
+                    location("config-dev.yaml", &["db", "password"])
+                ),
+                Finding::Missing(reference(3, "api.key")),
+                Finding::Unreferenced(location("config-dev.yaml", &["legacy", "token"])),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycles_are_not_followed() {
+        let dir = TempDir::new();
+        dir.write("src/main/mule/flows/app.xml", "<mule/>");
+        let mule = dir.0.join(MULE_DIR);
+        std::os::unix::fs::symlink(&mule, mule.join("flows/loop")).unwrap();
+        let mut xml = Vec::new();
+        collect_xml(&mule, &mut xml);
+        assert_eq!(xml, vec![mule.join("flows/app.xml")]);
+    }
+}
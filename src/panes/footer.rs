@@ -9,7 +9,11 @@ use crate::{
     },
     panes::Pane,
     state::{InputMode, PlaygroundField, State},
-    yaml_editor::state::{Confirm, Guard, OpenMode, YamlFocus},
+    yaml_editor::{
+        compare::Status,
+        document::DocFormat,
+        state::{Confirm, Guard, OpenMode, YamlFocus},
+    },
 };
 
 /// A one-line footer showing the actions valid for the current screen, focus and
@@ -34,6 +38,11 @@ pub fn hint_context(state: &State) -> HintContext {
     if state.pending_delete.is_some() {
         return HintContext::Confirmation(ConfirmationKind::DeleteEnv);
     }
+    if let Some(report) = &state.detect {
+        return HintContext::Detect {
+            running: report.attempts.is_none(),
+        };
+    }
 
     match state.mode {
         Mode::Main => {
@@ -60,7 +69,7 @@ fn yaml_context(state: &State) -> HintContext {
     if let Some(guard) = y.guard() {
         return HintContext::Confirmation(match guard {
             Guard::Quit => ConfirmationKind::UnsavedQuit,
-            Guard::Open(_) => ConfirmationKind::UnsavedOpen,
+            Guard::Close => ConfirmationKind::UnsavedClose,
         });
     }
     if let Some(confirm) = y.confirm {
@@ -85,9 +94,59 @@ fn yaml_context(state: &State) -> HintContext {
             OpenMode::Path => HintContext::PathInput,
         };
     }
+    if let Some(panel) = &y.project_panel {
+        return HintContext::ProjectPanel {
+            report: panel.report.is_some(),
+        };
+    }
+    if let Some(view) = &y.compare {
+        let row = view.selected_row().map(|i| &view.rows[i]);
+        return HintContext::Compare {
+            missing: row.is_some_and(|r| matches!(r.status, Status::Missing(_))),
+            in_open: row.is_some_and(|r| r.left.is_some()),
+        };
+    }
+    if y.suspicious_panel.is_some() {
+        return HintContext::Suspicious;
+    }
+    if y.merge.is_some() {
+        return HintContext::Merge;
+    }
+    if y.history_view.is_some() {
+        return HintContext::History;
+    }
+    if y.backup_view.is_some() {
+        return HintContext::Backups;
+    }
+    if y.goto.is_some() {
+        return HintContext::GoTo;
+    }
+    if y.copy_menu.is_some() {
+        return HintContext::CopyMenu;
+    }
+    if y.bookmark_view.is_some() {
+        return HintContext::Bookmarks;
+    }
+    if y.review.is_some() {
+        return HintContext::Review;
+    }
+    if y.rekey_picker.is_some() {
+        return HintContext::RekeyPicker;
+    }
+    if y.rekey_report.is_some() {
+        return HintContext::RekeyReport;
+    }
+    if y.verify_report.is_some() {
+        return HintContext::VerifyReport;
+    }
     if y.editing.is_some() {
         return HintContext::YamlEditing;
     }
+    if y.search_editing {
+        return HintContext::YamlSearching {
+            jump: y.search_jump,
+        };
+    }
     HintContext::Yaml(YamlHints {
         focus: match y.focus {
             YamlFocus::Environments => YamlHintFocus::Environments,
@@ -98,6 +157,12 @@ fn yaml_context(state: &State) -> HintContext {
         dirty: y.dirty(),
         env_selected: state.selected_env().is_some(),
         selection: y.selection_kind(),
+        comments: y.doc().format() == DocFormat::Yaml,
+        project: y.project.is_some(),
+        peek: y.peek_active(),
+        marks: y.marked_ids().len(),
+        buffers: y.buffer_count(),
+        searching: y.search_active(),
     })
 }
 
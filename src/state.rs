@@ -4,9 +4,10 @@ use crate::{
     action::Action,
     app::Mode,
     dencrypt::{decrypt, encrypt},
+    detect::{self, Attempt},
     environment::{Algorithm, Environment, Environments, State as CipherMode},
     text_field::TextField,
-    yaml_editor::state::YamlEditorState,
+    yaml_editor::{rekey, state::YamlEditorState},
 };
 use color_eyre::Result;
 use serde::{Deserialize, Serialize};
@@ -51,6 +52,62 @@ pub fn spawn_crypto(
     });
 }
 
+/// Re-key one `![...]` value off the UI thread and deliver the new value
+/// back as an [`Action::RekeyDone`]. The plaintext stays inside the task.
+pub fn spawn_rekey(
+    tx: UnboundedSender<Action>,
+    jar: PathBuf,
+    from: Environment,
+    to: Environment,
+    value: String,
+) {
+    tokio::spawn(async move {
+        let outcome =
+            tokio::task::spawn_blocking(move || rekey::rekey_value(&value, &from, &to, &jar))
+                .await
+                .unwrap_or_else(|e| Err(format!("background task failed: {e}")));
+        let _ = tx.send(Action::RekeyDone(outcome));
+    });
+}
+
+/// Try `value` with every environment off the UI thread and deliver the
+/// attempts as an [`Action::DetectDone`].
+pub fn spawn_detect(
+    tx: UnboundedSender<Action>,
+    jar: PathBuf,
+    envs: Vec<Environment>,
+    value: String,
+) {
+    tokio::spawn(async move {
+        let attempts = tokio::task::spawn_blocking(move || detect::detect(&value, &envs, &jar))
+            .await
+            .unwrap_or_default();
+        let _ = tx.send(Action::DetectDone(attempts));
+    });
+}
+
+/// Check that `value` decrypts with `env` off the UI thread and deliver the
+/// verdict as an [`Action::VerifyDone`]. The plaintext never leaves the task.
+pub fn spawn_verify(tx: UnboundedSender<Action>, jar: PathBuf, env: Environment, value: String) {
+    tokio::spawn(async move {
+        let outcome = tokio::task::spawn_blocking(move || decrypt(&value, &env, &jar).map(drop))
+            .await
+            .unwrap_or_else(|e| Err(format!("background task failed: {e}")));
+        let _ = tx.send(Action::VerifyDone(outcome));
+    });
+}
+
+/// Decrypt `value` with `env` off the UI thread for peek and deliver the
+/// plaintext as an [`Action::PeekDone`].
+pub fn spawn_peek(tx: UnboundedSender<Action>, jar: PathBuf, env: Environment, value: String) {
+    tokio::spawn(async move {
+        let outcome = tokio::task::spawn_blocking(move || decrypt(&value, &env, &jar))
+            .await
+            .unwrap_or_else(|e| Err(format!("background task failed: {e}")));
+        let _ = tx.send(Action::PeekDone(outcome));
+    });
+}
+
 impl Operation {
     /// Past-tense label for a completed operation ("Encrypted" / "Decrypted").
     pub fn label(&self) -> &'static str {
@@ -83,6 +140,15 @@ pub struct CryptoResult {
     pub outcome: std::result::Result<String, String>,
 }
 
+/// The detect popup: which environments decrypt a value.
+#[derive(Debug, Clone)]
+pub struct DetectReport {
+    /// What was tested, for the title (a YAML path or "the input value").
+    pub subject: String,
+    /// `None` while the attempts are running.
+    pub attempts: Option<Vec<Attempt>>,
+}
+
 /// Whether the environment form is creating a new entry or editing an existing
 /// one (by index into the full environments list).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -155,6 +221,20 @@ impl EnvForm {
         }
     }
 
+    /// An add form filled in from a proposed environment, starting on the key
+    /// when the proposal has none.
+    fn prefilled(env: &Environment) -> Self {
+        Self {
+            mode: FormMode::Add,
+            field: if env.key.is_empty() {
+                FormField::Key
+            } else {
+                FormField::Name
+            },
+            ..Self::edit(0, env)
+        }
+    }
+
     pub fn title(&self) -> &'static str {
         match self.mode {
             FormMode::Add => " Add environment ",
@@ -399,6 +479,8 @@ pub struct State {
     pub playground: Playground,
     /// State of the YAML editor screen.
     pub yaml: YamlEditorState,
+    /// The detect popup, when shown. It stays open while the attempts run.
+    pub detect: Option<DetectReport>,
     /// A one-off message to surface right after startup (e.g. a bad envs file).
     pub startup_message: Option<String>,
 }
@@ -444,6 +526,7 @@ impl State {
             pending_delete: None,
             playground: Playground::default(),
             yaml: YamlEditorState::default(),
+            detect: None,
         })
     }
 
@@ -468,26 +551,97 @@ impl State {
             pending_delete: None,
             playground: Playground::default(),
             yaml: YamlEditorState::default(),
+            detect: None,
         }
     }
 
-    /// Start an encrypt/decrypt for the selected YAML scalar, using the
-    /// selected environment, off the UI thread.
-    pub fn yaml_begin_crypto(&mut self, tx: UnboundedSender<Action>, op: Operation) {
-        if self.busy || self.yaml.crypto_in_progress {
+    /// Try the Main screen's input value, or the selected YAML value, with
+    /// every environment.
+    pub fn begin_detect(&mut self, tx: UnboundedSender<Action>) {
+        if self.detect.is_some() {
             return;
         }
+        let (subject, value) = if self.mode == Mode::Yaml {
+            match self.yaml.selected_value() {
+                Ok((path, value)) => (crate::yaml_editor::document::path_to_string(&path), value),
+                Err(e) => {
+                    self.yaml.report(e, true);
+                    return;
+                }
+            }
+        } else {
+            ("the input value".to_string(), self.input_value.value())
+        };
+        let message = if value.trim().is_empty() {
+            Some("The value is empty.")
+        } else if self.envs.is_empty() {
+            Some("No environments to try.")
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            if self.mode == Mode::Yaml {
+                self.yaml.report(message, true);
+            } else {
+                self.result = Some(CryptoResult {
+                    op: Operation::Decrypt,
+                    outcome: Err(message.to_string()),
+                });
+            }
+            return;
+        }
+        self.detect = Some(DetectReport {
+            subject,
+            attempts: None,
+        });
+        spawn_detect(
+            tx,
+            self.jar_path.clone(),
+            self.envs.environments.clone(),
+            value,
+        );
+    }
+
+    /// Close the detect popup once its attempts are in.
+    pub fn close_detect(&mut self) {
+        if self.detect.as_ref().is_some_and(|d| d.attempts.is_some()) {
+            self.detect = None;
+        }
+    }
+
+    /// Show the attempts of a finished detect.
+    pub fn finish_detect(&mut self, attempts: Vec<Attempt>) {
+        if let Some(report) = self.detect.as_mut() {
+            report.attempts = Some(attempts);
+        }
+    }
+
+    /// The selected environment, if the tool can use it; otherwise the reason
+    /// is reported on the YAML screen.
+    fn yaml_tool_env(&mut self) -> Option<Environment> {
         let Some(env) = self.selected_env().cloned() else {
             self.yaml.report("No environment selected.", true);
-            return;
+            return None;
         };
         if !env.algorithm.supports_modes() {
             self.yaml.report(
                 format!("{:?} is not supported by the tool.", env.algorithm),
                 true,
             );
+            return None;
+        }
+        Some(env)
+    }
+
+    /// Start an encrypt/decrypt for the selected YAML scalar, using the
+    /// selected environment, off the UI thread.
+    pub fn yaml_begin_crypto(&mut self, tx: UnboundedSender<Action>, op: Operation) {
+        if self.busy || self.yaml.crypto_in_progress {
             return;
         }
+        let Some(env) = self.yaml_tool_env() else {
+            return;
+        };
         match self.yaml.begin_crypto(op) {
             Ok(value) => {
                 self.busy = true;
@@ -510,24 +664,161 @@ impl State {
         if self.busy || self.yaml.crypto_in_progress || self.yaml.bulk_in_progress() {
             return;
         }
-        let Some(env) = self.selected_env().cloned() else {
+        if self.yaml_tool_env().is_none() {
+            return;
+        }
+        if let Err(e) = self.yaml.start_bulk(op) {
+            self.yaml.report(e, true);
+            return;
+        }
+        self.yaml_pump_bulk(tx);
+    }
+
+    /// Open the re-key picker: the selected environment is the source, the
+    /// target is picked from the others.
+    pub fn yaml_open_rekey(&mut self) {
+        if self.busy || self.yaml.crypto_in_progress || self.yaml.rekey_in_progress() {
+            return;
+        }
+        let Some(from) = self.selected_env().cloned() else {
             self.yaml.report("No environment selected.", true);
             return;
         };
-        if !env.algorithm.supports_modes() {
-            self.yaml.report(
-                format!("{:?} is not supported by the tool.", env.algorithm),
-                true,
-            );
+        self.yaml.open_rekey_picker(from, &self.envs.environments);
+    }
+
+    /// Start re-keying every encrypted value in the file to the picked
+    /// environment, one value at a time off the UI thread.
+    pub fn yaml_start_rekey(&mut self, tx: UnboundedSender<Action>) {
+        if let Err(e) = self.yaml.start_rekey() {
+            self.yaml.report(e, true);
             return;
         }
-        if let Err(e) = self.yaml.start_bulk(op) {
+        self.yaml_pump_rekey(tx);
+    }
+
+    /// Record a re-keyed value and spawn the next one, if any.
+    pub fn yaml_finish_rekey(
+        &mut self,
+        tx: UnboundedSender<Action>,
+        outcome: std::result::Result<String, String>,
+    ) {
+        self.busy = false;
+        self.yaml.finish_rekey(outcome);
+        self.yaml_pump_rekey(tx);
+    }
+
+    fn yaml_pump_rekey(&mut self, tx: UnboundedSender<Action>) {
+        if let Some((from, to, value)) = self.yaml.next_rekey_value() {
+            self.busy = true;
+            spawn_rekey(tx, self.jar_path.clone(), from, to, value);
+        }
+    }
+
+    /// Check that every encrypted value under the selection (or in the whole
+    /// file) decrypts with the selected environment, without changing it.
+    pub fn yaml_start_verify(&mut self, tx: UnboundedSender<Action>, whole_file: bool) {
+        if self.busy || self.yaml.crypto_in_progress {
+            return;
+        }
+        let Some(env) = self.yaml_tool_env() else {
+            return;
+        };
+        if let Err(e) = self.yaml.start_verify(env, whole_file) {
+            self.yaml.report(e, true);
+            return;
+        }
+        self.yaml_pump_verify(tx);
+    }
+
+    /// Record a verify verdict and spawn the next value, if any.
+    pub fn yaml_finish_verify(
+        &mut self,
+        tx: UnboundedSender<Action>,
+        outcome: std::result::Result<(), String>,
+    ) {
+        self.busy = false;
+        self.yaml.finish_verify(outcome);
+        self.yaml_pump_verify(tx);
+    }
+
+    fn yaml_pump_verify(&mut self, tx: UnboundedSender<Action>) {
+        if let Some((env, value)) = self.yaml.next_verify_value() {
+            self.busy = true;
+            spawn_verify(tx, self.jar_path.clone(), env, value);
+        }
+    }
+
+    /// Encrypt exactly the values listed in the suspicious panel with the
+    /// selected environment, one at a time off the UI thread.
+    pub fn yaml_encrypt_suspicious(&mut self, tx: UnboundedSender<Action>) {
+        if self.busy || self.yaml.crypto_in_progress || self.yaml.bulk_in_progress() {
+            return;
+        }
+        if self.yaml_tool_env().is_none() {
+            return;
+        }
+        if let Err(e) = self.yaml.start_encrypt_suspicious() {
             self.yaml.report(e, true);
             return;
         }
         self.yaml_pump_bulk(tx);
     }
 
+    /// Toggle peek: wipe the previews if it is on, otherwise decrypt the
+    /// encrypted values under the selection (or all visible ones) with the
+    /// selected environment for display only.
+    pub fn yaml_toggle_peek(&mut self, tx: UnboundedSender<Action>, all_visible: bool) {
+        if self.yaml.peek_active() {
+            self.yaml.stop_peek();
+            self.yaml
+                .report("Peek off; decrypted values cleared.", false);
+            return;
+        }
+        if self.busy || self.yaml.crypto_in_progress {
+            return;
+        }
+        let Some(env) = self.yaml_tool_env() else {
+            return;
+        };
+        if let Err(e) = self.yaml.start_peek(env, all_visible) {
+            self.yaml.report(e, true);
+            return;
+        }
+        self.yaml_pump_peek(tx);
+    }
+
+    /// Cache a peeked plaintext and spawn the next value, if any.
+    pub fn yaml_finish_peek(
+        &mut self,
+        tx: UnboundedSender<Action>,
+        outcome: std::result::Result<String, String>,
+    ) {
+        self.busy = false;
+        self.yaml.finish_peek(outcome);
+        self.yaml_pump_peek(tx);
+    }
+
+    fn yaml_pump_peek(&mut self, tx: UnboundedSender<Action>) {
+        if let Some((env, value)) = self.yaml.next_peek_value() {
+            self.busy = true;
+            spawn_peek(tx, self.jar_path.clone(), env, value);
+        }
+    }
+
+    /// Turn peek off once the selected environment is no longer the one the
+    /// previews were decrypted with (another one picked, edited or deleted).
+    pub fn sync_peek(&mut self) {
+        let Some(env) = self.yaml.peek_env() else {
+            return;
+        };
+        if self.selected_env() != Some(env) {
+            self.yaml.stop_peek();
+            self.yaml
+                .report("Peek off: the environment changed.", false);
+        }
+    }
+
     /// Spawn the next queued bulk item, if any.
     pub fn yaml_pump_bulk(&mut self, tx: UnboundedSender<Action>) {
         if let Some((op, value)) = self.yaml.next_bulk_value() {
@@ -656,12 +947,67 @@ impl State {
         if let Some(index) = self.envs.environments.iter().position(|e| e.name == name) {
             self.current_env_index = index;
         }
+        if let (Some(path), Some(project)) =
+            (self.yaml.pending_link.take(), self.yaml.project.as_mut())
+        {
+            if let Err(e) = project.links.insert(&path, name.clone()) {
+                self.yaml
+                    .report(format!("Could not save the file's link: {e}"), true);
+            }
+        }
         self.form = None;
         Ok(())
     }
 
     pub fn cancel_form(&mut self) {
         self.form = None;
+        self.yaml.pending_link = None;
+    }
+
+    /// `n` in the project panel: open the add form pre-filled with the
+    /// environment the project's `<secure-properties:config>` proposes for
+    /// the selected file. Saving it links the file to the new environment.
+    pub fn open_env_proposal(&mut self) {
+        let Some(config) = self.yaml.selected_config() else {
+            return;
+        };
+        let (path, name) = (config.path.clone(), config.name.clone());
+        let Some(env) = self.yaml.project.as_ref().and_then(|p| p.proposal(config)) else {
+            self.yaml.report(
+                format!("No secure-properties config decrypts {name}."),
+                true,
+            );
+            return;
+        };
+        if let Some(index) = self
+            .envs
+            .environments
+            .iter()
+            .position(|e| e.name == env.name)
+        {
+            self.current_env_index = index;
+            self.yaml
+                .report(format!("Environment {} already exists.", env.name), false);
+            return;
+        }
+        self.yaml.pending_link = Some(path);
+        self.form = Some(EnvForm::prefilled(&env));
+    }
+
+    /// Select the environment linked to the file the YAML screen just opened,
+    /// if one by that name exists.
+    pub fn apply_linked_env(&mut self) {
+        let Some(name) = self.yaml.pending_env.take() else {
+            return;
+        };
+        let Some(index) = self.envs.environments.iter().position(|e| e.name == name) else {
+            return;
+        };
+        if index != self.current_env_index {
+            self.current_env_index = index;
+            self.yaml
+                .report(format!("Selected environment {name} for this file."), false);
+        }
     }
 
     // --- Environment deletion ----------------------------------------------
@@ -971,4 +1317,58 @@ mod tests {
         assert_eq!(state.playground.algorithm, env.algorithm);
         assert_eq!(state.playground.field, PlaygroundField::Value);
     }
+
+    #[test]
+    fn project_proposes_and_links_environments() {
+        let dir = crate::mule::test_support::TempDir::new();
+        dir.write("mule-artifact.json", "{}");
+        dir.write(
+            "src/main/mule/global.xml",
+            r#"<secure-properties:config name="S" file="config-${env}.yaml" key="${key}">
+  <secure-properties:encrypt algorithm="Blowfish" mode="CFB"/>
+</secure-properties:config>"#,
+        );
+        dir.write("src/main/resources/config-dev.yaml", "a: 1\n");
+        dir.write("src/main/resources/config-prod.yaml", "a: 2\n");
+        let mut state = State::for_test();
+        state.envs_path = dir.0.join("envs.yaml");
+        state
+            .envs
+            .add(Environment::new(
+                "other",
+                Algorithm::AES,
+                CipherMode::CBC,
+                false,
+                "k",
+            ))
+            .unwrap();
+        state.yaml.open_project(&dir.0.to_string_lossy()).unwrap();
+        let project_name = state.yaml.project.as_ref().unwrap().name.clone();
+
+        // config-dev.yaml: the form opens pre-filled on the key field.
+        state.open_env_proposal();
+        let form = state.form.as_mut().expect("form opens");
+        assert_eq!(form.name.value(), format!("{project_name}-dev"));
+        assert_eq!(form.algorithm, Algorithm::Blowfish);
+        assert_eq!(form.cipher, CipherMode::CFB);
+        assert_eq!(form.field, FormField::Key);
+        form.name = TextField::from_text("dev");
+        form.key = TextField::from_text("secret");
+        state.submit_form().unwrap();
+        assert_eq!(state.selected_env().unwrap().name, "dev");
+
+        // Reopening the linked file selects the renamed environment.
+        state.current_env_index = 0;
+        let dev = dir.0.join("src/main/resources/config-dev.yaml");
+        state.yaml.open_path(&dev.to_string_lossy()).unwrap();
+        state.apply_linked_env();
+        assert_eq!(state.selected_env().unwrap().name, "dev");
+
+        // config-prod.yaml has no environment yet, so nothing changes.
+        state.current_env_index = 0;
+        let prod = dir.0.join("src/main/resources/config-prod.yaml");
+        state.yaml.open_path(&prod.to_string_lossy()).unwrap();
+        state.apply_linked_env();
+        assert_eq!(state.selected_env().unwrap().name, "other");
+    }
 }
@@ -0,0 +1,349 @@
+//! Rotating backups of saved files.
+//!
+//! Every save keeps a copy of the written text in the lazyprop home, under
+//! `backups/<file name>-<path hash>/<unix millis>-<environment>.bak`, so a
+//! save made with the wrong environment can be undone after the editor's own
+//! history is gone. A save is preceded by the text the file had before it
+//! whenever no backup holds that text yet (the first save, or after the file
+//! changed outside the editor). Only the newest [`Settings::keep`] copies are
+//! kept. The `rekey` command backs up its writes the same way.
+//!
+//! Backups hold whatever the file held, plaintext secrets included, so on Unix
+//! the directories are private to the user (`0700`) and the copies `0600`.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::document::{self, Document};
+use super::review::{scalar_values, PathChange};
+
+/// The `backups` section of the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// How many versions to keep per file; `0` turns backups off.
+    pub keep: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self { keep: 10 }
+    }
+}
+
+/// One saved version of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// When it was saved, in milliseconds since the Unix epoch.
+    pub millis: u64,
+    /// The environment selected when it was saved; `None` for the text the
+    /// file had before a save.
+    pub env: Option<String>,
+}
+
+impl Backup {
+    pub fn read(&self) -> Result<String, String> {
+        std::fs::read_to_string(&self.path).map_err(|e| e.to_string())
+    }
+
+    /// `2026-10-19 14:03:12 UTC`.
+    pub fn when(&self) -> String {
+        let secs = self.millis / 1000;
+        let (days, rest) = (secs / 86_400, secs % 86_400);
+        let (y, m, d) = civil_from_days(days as i64);
+        format!(
+            "{y:04}-{m:02}-{d:02} {:02}:{:02}:{:02} UTC",
+            rest / 3600,
+            rest % 3600 / 60,
+            rest % 60
+        )
+    }
+}
+
+/// Where backups live and how many are kept.
+#[derive(Debug, Clone)]
+pub struct Store {
+    root: PathBuf,
+    keep: usize,
+}
+
+impl Store {
+    pub fn new(root: PathBuf, keep: usize) -> Self {
+        Self { root, keep }
+    }
+
+    /// The directory holding `file`'s backups. The path hash tells apart
+    /// files of the same name.
+    fn dir(&self, file: &Path) -> PathBuf {
+        let file = std::fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        let name = file
+            .file_name()
+            .map(|n| sanitize(&n.to_string_lossy()))
+            .unwrap_or_default();
+        self.root
+            .join(format!("{name}-{:016x}", fnv1a(&file.to_string_lossy())))
+    }
+
+    /// `file`'s backups, newest first.
+    pub fn list(&self, file: &Path) -> Vec<Backup> {
+        let Ok(entries) = std::fs::read_dir(self.dir(file)) else {
+            return Vec::new();
+        };
+        let mut backups: Vec<Backup> = entries
+            .flatten()
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().into_owned();
+                let stem = name.strip_suffix(".bak")?;
+                let (millis, env) = match stem.split_once('-') {
+                    Some((millis, env)) => (millis, Some(env.to_string())),
+                    None => (stem, None),
+                };
+                Some(Backup {
+                    path: e.path(),
+                    millis: millis.parse().ok()?,
+                    env,
+                })
+            })
+            .collect();
+        backups.sort_by_key(|b| std::cmp::Reverse(b.millis));
+        backups
+    }
+
+    /// Keep `content`, saved to `file` with `env` selected, and drop the
+    /// oldest copies beyond the limit. `previous` is the text the file had
+    /// before the save; it is kept too unless the newest backup already holds
+    /// it. Text identical to the newest backup is not kept again.
+    pub fn record(
+        &self,
+        file: &Path,
+        previous: Option<&str>,
+        content: &str,
+        env: Option<&str>,
+    ) -> Result<(), String> {
+        if self.keep == 0 {
+            return Ok(());
+        }
+        let dir = self.dir(file);
+        create_private_dir(&dir)?;
+        let backups = self.list(file);
+        let newest = backups.first();
+        let newest_text = newest.and_then(|b| b.read().ok());
+        if newest_text.as_deref() == Some(content) {
+            return Ok(());
+        }
+        // Names must stay unique and ordered even within one millisecond.
+        let mut millis = now_millis().max(newest.map_or(0, |b| b.millis + 2));
+        if let Some(previous) =
+            previous.filter(|p| newest_text.as_deref() != Some(*p) && *p != content)
+        {
+            write_private(&dir.join(format!("{}.bak", millis)), previous)?;
+            millis += 1;
+        }
+        let name = match env {
+            Some(env) => format!("{millis}-{}.bak", sanitize(env)),
+            None => format!("{millis}.bak"),
+        };
+        write_private(&dir.join(name), content)?;
+        for old in self.list(file).iter().skip(self.keep) {
+            let _ = std::fs::remove_file(&old.path);
+        }
+        Ok(())
+    }
+}
+
+/// What loading `backup` over `current` would change, by logical path:
+/// `before` is the current value and `after` the backup's.
+pub fn diff(current: &Document, backup: &str) -> Vec<PathChange> {
+    let old = Document::parse_as(backup, current.format());
+    let (now, then) = (scalar_values(current), scalar_values(&old));
+    let mut changes: Vec<PathChange> = Vec::new();
+    for node in current.nodes().iter().chain(old.nodes()) {
+        if changes.iter().any(|c| c.path == node.path) {
+            continue;
+        }
+        if let Some(change) =
+            PathChange::between(&node.path, now.get(&node.path), then.get(&node.path))
+        {
+            changes.push(change);
+        }
+    }
+    changes
+}
+
+/// `db.password: encrypted → plaintext` and the like, one per change.
+pub fn summaries(changes: &[PathChange]) -> Vec<String> {
+    changes
+        .iter()
+        .map(|c| format!("{}: {}", document::path_to_string(&c.path), c.summary()))
+        .collect()
+}
+
+/// Create `dir` and its missing parents readable by the user only. An
+/// existing `dir` is tightened too.
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        builder.mode(0o700);
+        builder.create(dir).map_err(|e| e.to_string())?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(not(unix))]
+    builder.create(dir).map_err(|e| e.to_string())
+}
+
+/// Write a new file readable by the user only.
+fn write_private(path: &Path, content: &str) -> Result<(), String> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut f| f.write_all(content.as_bytes()))
+        .map_err(|e| e.to_string())
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Letters, digits, `.` and `_` kept; anything else becomes `_`, so the text
+/// is safe in a file name and never holds the `-` separator.
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A hash that stays the same across builds, unlike `DefaultHasher`.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Year, month and day of a day count since 1970-01-01 (Howard Hinnant's
+/// algorithm).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mule::test_support::TempDir;
+
+    #[test]
+    fn keeps_the_newest_versions_with_their_environment() {
+        let dir = TempDir::new();
+        dir.write("app.yaml", "a: 1\n");
+        let file = dir.0.join("app.yaml");
+        let store = Store::new(dir.0.join("backups"), 3);
+
+        store
+            .record(&file, Some("a: 1\n"), "a: 2\n", Some("Dev env/eu"))
+            .unwrap();
+        // Saving the same text again keeps no new copy.
+        store
+            .record(&file, Some("a: 2\n"), "a: 2\n", Some("Dev"))
+            .unwrap();
+        let backups = store.list(&file);
+        let envs: Vec<Option<&str>> = backups.iter().map(|b| b.env.as_deref()).collect();
+        assert_eq!(envs, vec![Some("Dev_env_eu"), None]);
+        assert_eq!(backups[1].read().unwrap(), "a: 1\n");
+
+        store.record(&file, Some("a: 2\n"), "a: 3\n", None).unwrap();
+        store
+            .record(&file, Some("a: 3\n"), "a: 4\n", Some("Prod"))
+            .unwrap();
+        let texts: Vec<String> = store
+            .list(&file)
+            .iter()
+            .map(|b| b.read().unwrap())
+            .collect();
+        assert_eq!(texts, vec!["a: 4\n", "a: 3\n", "a: 2\n"]);
+
+        // A change made outside the editor is kept before the next save.
+        store
+            .record(&file, Some("a: 5\n"), "a: 6\n", Some("Prod"))
+            .unwrap();
+        let backups = store.list(&file);
+        assert_eq!(backups[0].read().unwrap(), "a: 6\n");
+        assert_eq!(
+            (
+                backups[1].read().unwrap().as_str(),
+                backups[1].env.as_deref()
+            ),
+            ("a: 5\n", None)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn backups_are_private_to_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new();
+        dir.write("app.yaml", "pw: hunter2\n");
+        let file = dir.0.join("app.yaml");
+        let store = Store::new(dir.0.join("backups"), 3);
+        store
+            .record(&file, Some("pw: hunter2\n"), "pw: x\n", Some("Dev"))
+            .unwrap();
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir.0.join("backups")), 0o700);
+        let backups = store.list(&file);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(mode(backups[0].path.parent().unwrap()), 0o700);
+        for backup in &backups {
+            assert_eq!(mode(&backup.path), 0o600);
+        }
+    }
+
+    #[test]
+    fn diffs_by_path_and_formats_utc_times() {
+        let current = Document::parse("db:\n  password: \"![c2VjcmV0]\"\n  port: 1\nnew: x\n");
+        let changes = diff(&current, "db:\n  password: hunter2\n  port: 1\nold: y\n");
+        assert_eq!(
+            summaries(&changes),
+            vec![
+                "db.password: encrypted → plaintext",
+                "new: removed, was \"x\"",
+                "old: added, \"y\"",
+            ]
+        );
+        let backup = Backup {
+            path: PathBuf::new(),
+            millis: 1_792_418_592_000,
+            env: None,
+        };
+        assert_eq!(backup.when(), "2026-10-19 14:03:12 UTC");
+    }
+}
@@ -0,0 +1,104 @@
+//! Named bookmarks on paths, per file, kept across sessions.
+//!
+//! Bookmarks are stored in `bookmarks.yaml` in the lazyprop home, keyed by the
+//! file's canonical path. A bookmark holds the path as text (see
+//! [`path_to_string`](super::document::path_to_string)), so it outlives edits
+//! and still shows when its key is gone from the file.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::per_file::PerFile;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    by_file: PerFile<Vec<Bookmark>>,
+}
+
+impl Bookmarks {
+    /// Read the bookmarks saved at `store`; a missing file starts empty.
+    pub fn load(store: PathBuf) -> Self {
+        Self {
+            by_file: PerFile::load(store),
+        }
+    }
+
+    /// Why the saved bookmarks could not be read, if they couldn't.
+    pub fn error(&self) -> Option<&str> {
+        self.by_file.error()
+    }
+
+    /// `file`'s bookmarks, in the order they were added.
+    pub fn for_file(&self, file: &Path) -> &[Bookmark] {
+        self.by_file.get(file).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bookmark `path` in `file` as `name`, replacing every bookmark of the
+    /// same name or on the same path. It takes the place of the first one.
+    pub fn add(&mut self, file: &Path, name: &str, path: String) -> Result<(), String> {
+        let mut list = self.for_file(file).to_vec();
+        let bookmark = Bookmark {
+            name: name.to_string(),
+            path,
+        };
+        let same = |b: &Bookmark| b.name == bookmark.name || b.path == bookmark.path;
+        let at = list.iter().position(same).unwrap_or(list.len());
+        list.retain(|b| !same(b));
+        list.insert(at.min(list.len()), bookmark);
+        self.by_file.insert(file, list)
+    }
+
+    /// Remove `file`'s bookmark at `index`.
+    pub fn remove(&mut self, file: &Path, index: usize) -> Result<(), String> {
+        let mut list = self.for_file(file).to_vec();
+        if index < list.len() {
+            list.remove(index);
+        }
+        if list.is_empty() {
+            self.by_file.remove(file)
+        } else {
+            self.by_file.insert(file, list)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mule::test_support::TempDir;
+
+    #[test]
+    fn bookmarks_are_per_file_and_survive_a_reload() {
+        let dir = TempDir::new();
+        let store = dir.0.join("bookmarks.yaml");
+        let (a, b) = (Path::new("/configs/a.yaml"), Path::new("/configs/b.yaml"));
+        let mut marks = Bookmarks::load(store.clone());
+        marks.add(a, "db", "db.password".to_string()).unwrap();
+        marks.add(a, "api", "api.key".to_string()).unwrap();
+        marks.add(b, "db", "database.pwd".to_string()).unwrap();
+        // Same name: replaced in place.
+        marks.add(a, "db", "db.user".to_string()).unwrap();
+        marks.add(a, "extra", "extra".to_string()).unwrap();
+        // The name of one and the path of another: both are replaced.
+        marks.add(a, "extra", "api.key".to_string()).unwrap();
+
+        let mut marks = Bookmarks::load(store.clone());
+        let names: Vec<(&str, &str)> = marks
+            .for_file(a)
+            .iter()
+            .map(|b| (b.name.as_str(), b.path.as_str()))
+            .collect();
+        assert_eq!(names, vec![("db", "db.user"), ("extra", "api.key")]);
+        assert_eq!(marks.for_file(b).len(), 1);
+
+        marks.remove(a, 0).unwrap();
+        assert_eq!(Bookmarks::load(store.clone()).for_file(a)[0].name, "extra");
+    }
+}
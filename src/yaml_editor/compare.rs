@@ -0,0 +1,357 @@
+//! Comparing two config files key by key.
+//!
+//! Rows are aligned by flattened key (`db.password`, `servers[0].host`), so a
+//! YAML file lines up with a `.properties` file holding the same keys. Each
+//! row says whether the key is missing on one side, encrypted on only one
+//! side, or holds a different kind of value. A missing key can be copied to
+//! the other file with a placeholder value (see [`Document::insert_key`]).
+
+use std::collections::HashMap;
+
+use super::document::{self, DocFormat, Document, NodeKind, PathSeg, ScalarStyle};
+
+/// The value a copied key gets, to be filled in (or encrypted) afterwards.
+pub const PLACEHOLDER: &str = "CHANGE_ME";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+/// What a value holds, for the type check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Mapping,
+    Sequence,
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+impl ValueKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ValueKind::Mapping => "mapping",
+            ValueKind::Sequence => "sequence",
+            ValueKind::String => "string",
+            ValueKind::Number => "number",
+            ValueKind::Bool => "boolean",
+            ValueKind::Null => "null",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Same,
+    /// The key exists only on the other side.
+    Missing(Side),
+    /// Encrypted on one side, plaintext on the other.
+    Encryption,
+    /// A different kind of value on each side.
+    Type,
+}
+
+/// One side of a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub path: Vec<PathSeg>,
+    pub kind: ValueKind,
+    pub encrypted: bool,
+    /// The logical value of a scalar.
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// The flattened key both sides are aligned by.
+    pub key: String,
+    pub left: Option<Entry>,
+    pub right: Option<Entry>,
+    pub status: Status,
+}
+
+impl Row {
+    pub fn entry(&self, side: Side) -> Option<&Entry> {
+        match side {
+            Side::Left => self.left.as_ref(),
+            Side::Right => self.right.as_ref(),
+        }
+    }
+}
+
+/// `db.password`, `servers[0].host`: keys joined with dots, unquoted, so a
+/// nested YAML key and a flat property key compare equal.
+pub fn flat_key(path: &[PathSeg]) -> String {
+    let mut out = String::new();
+    for seg in path {
+        match seg {
+            PathSeg::Key(k) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(k);
+            }
+            PathSeg::Index(i) => out.push_str(&format!("[{i}]")),
+        }
+    }
+    out
+}
+
+/// Align `left` and `right` by key. Rows follow the left file's order, with
+/// keys only the right file has placed after their predecessor there. When
+/// either side is a `.properties` file only scalar values are compared, since
+/// it has no nesting and stores every value as a string.
+pub fn compare(left: &Document, right: &Document) -> Vec<Row> {
+    let flat = left.format() == DocFormat::Properties || right.format() == DocFormat::Properties;
+    let mut rows: Vec<Row> = entries(left, flat)
+        .map(|(key, entry)| Row {
+            key,
+            left: Some(entry),
+            right: None,
+            status: Status::Same,
+        })
+        .collect();
+    let mut position: HashMap<String, usize> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (r.key.clone(), i))
+        .collect();
+    let mut after = 0;
+    for (key, entry) in entries(right, flat) {
+        match position.get(&key) {
+            Some(&i) => {
+                // Duplicate keys keep the first occurrence, like the index.
+                rows[i].right.get_or_insert(entry);
+                after = i + 1;
+            }
+            None => {
+                rows.insert(
+                    after,
+                    Row {
+                        key: key.clone(),
+                        left: None,
+                        right: Some(entry),
+                        status: Status::Same,
+                    },
+                );
+                for i in position.values_mut().filter(|i| **i >= after) {
+                    *i += 1;
+                }
+                position.insert(key, after);
+                after += 1;
+            }
+        }
+    }
+    for row in &mut rows {
+        row.status = status(row, flat);
+    }
+    rows
+}
+
+fn status(row: &Row, flat: bool) -> Status {
+    let (Some(left), Some(right)) = (&row.left, &row.right) else {
+        return if row.left.is_some() {
+            Status::Missing(Side::Right)
+        } else {
+            Status::Missing(Side::Left)
+        };
+    };
+    if !flat && left.kind != right.kind {
+        Status::Type
+    } else if left.encrypted != right.encrypted {
+        Status::Encryption
+    } else {
+        Status::Same
+    }
+}
+
+fn entries(doc: &Document, scalars_only: bool) -> impl Iterator<Item = (String, Entry)> + '_ {
+    doc.nodes()
+        .iter()
+        .filter(move |n| !scalars_only || n.kind == NodeKind::Scalar)
+        .map(|n| {
+            let value = (n.kind == NodeKind::Scalar)
+                .then(|| doc.logical_value(n.id))
+                .flatten();
+            let entry = Entry {
+                path: n.path.clone(),
+                kind: value_kind(doc, n.id),
+                encrypted: value.as_deref().is_some_and(document::is_wrapped),
+                value,
+            };
+            (flat_key(&n.path), entry)
+        })
+}
+
+fn value_kind(doc: &Document, id: usize) -> ValueKind {
+    let node = &doc.nodes()[id];
+    match node.kind {
+        NodeKind::Mapping => return ValueKind::Mapping,
+        NodeKind::Sequence => return ValueKind::Sequence,
+        NodeKind::Scalar => {}
+    }
+    let Some(source) = doc.value_source(id) else {
+        return ValueKind::Null;
+    };
+    let plain = match doc.format() {
+        DocFormat::Properties => return ValueKind::String,
+        DocFormat::Json => !source.starts_with('"'),
+        DocFormat::Yaml => node.style == ScalarStyle::Plain,
+    };
+    if !plain {
+        return ValueKind::String;
+    }
+    match serde_yaml::from_str::<serde_yaml::Value>(source) {
+        Ok(serde_yaml::Value::Bool(_)) => ValueKind::Bool,
+        Ok(serde_yaml::Value::Number(_)) => ValueKind::Number,
+        Ok(serde_yaml::Value::Null) => ValueKind::Null,
+        _ => ValueKind::String,
+    }
+}
+
+/// Where `entry` (from a `from` file) goes in a `to` file: a `.properties`
+/// file takes the flattened key, and a flat property key is split at its
+/// dots for a nested format.
+pub fn target_path(entry: &Entry, from: DocFormat, to: DocFormat) -> Vec<PathSeg> {
+    match (from, to) {
+        (_, DocFormat::Properties) => vec![PathSeg::Key(flat_key(&entry.path))],
+        (DocFormat::Properties, _) => flat_key(&entry.path)
+            .split('.')
+            .map(|k| PathSeg::Key(k.to_string()))
+            .collect(),
+        _ => entry.path.clone(),
+    }
+}
+
+/// Add every scalar key under `rows[index]` that `to` lacks, each with a
+/// [`PLACEHOLDER`] value. Returns the new text of `to` and how many keys were
+/// added.
+pub fn copy_missing(
+    rows: &[Row],
+    index: usize,
+    from: &Document,
+    to: &Document,
+    side: Side,
+) -> Result<(String, usize), String> {
+    let row = rows.get(index).ok_or("nothing selected")?;
+    if row.status != Status::Missing(side) {
+        return Err(format!("{} is not missing there", row.key));
+    }
+    let under = |key: &str| {
+        key == row.key
+            || key
+                .strip_prefix(row.key.as_str())
+                .is_some_and(|rest| rest.starts_with(['.', '[']))
+    };
+    let mut text = to.raw().to_string();
+    let mut added = 0;
+    for r in rows.iter().filter(|r| under(&r.key)) {
+        let Some(entry) = r.entry(side.other()) else {
+            continue;
+        };
+        if r.status != Status::Missing(side) || entry.value.is_none() {
+            continue;
+        }
+        let doc = Document::parse_as(&text, to.format());
+        let token = doc.encode_value(PLACEHOLDER);
+        text = doc.insert_key(&target_path(entry, from.format(), to.format()), &token)?;
+        added += 1;
+    }
+    if added == 0 {
+        return Err(format!("{} holds no values to copy", row.key));
+    }
+    Ok((text, added))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEV: &str = "\
+db:
+  user: admin
+  password: \"![abc]\"
+  port: 5432
+api:
+  key: k1
+";
+
+    const PROD: &str = "\
+db:
+  user: admin
+  password: plain
+  port: \"5432\"
+  pool: 10
+";
+
+    fn statuses(rows: &[Row]) -> Vec<(&str, Status)> {
+        rows.iter().map(|r| (r.key.as_str(), r.status)).collect()
+    }
+
+    #[test]
+    fn aligns_by_path_and_flags_differences() {
+        let dev = Document::parse(DEV);
+        let prod = Document::parse(PROD);
+        let rows = compare(&dev, &prod);
+        assert_eq!(
+            statuses(&rows),
+            vec![
+                ("db", Status::Same),
+                ("db.user", Status::Same),
+                ("db.password", Status::Encryption),
+                ("db.port", Status::Type),
+                ("db.pool", Status::Missing(Side::Left)),
+                ("api", Status::Missing(Side::Right)),
+                ("api.key", Status::Missing(Side::Right)),
+            ]
+        );
+    }
+
+    #[test]
+    fn yaml_lines_up_with_properties() {
+        let dev = Document::parse(DEV);
+        let props = Document::parse_as(
+            "db.user=admin\ndb.password=![x]\ndb.port=5432\nextra=1\n",
+            DocFormat::Properties,
+        );
+        let rows = compare(&dev, &props);
+        assert_eq!(
+            statuses(&rows),
+            vec![
+                ("db.user", Status::Same),
+                ("db.password", Status::Same),
+                ("db.port", Status::Same),
+                ("extra", Status::Missing(Side::Left)),
+                ("api.key", Status::Missing(Side::Right)),
+            ]
+        );
+        let (text, added) = copy_missing(&rows, 4, &dev, &props, Side::Right).unwrap();
+        assert_eq!(added, 1);
+        assert!(text.ends_with("extra=1\napi.key=CHANGE_ME\n"), "{text}");
+        let (text, _) = copy_missing(&rows, 3, &props, &dev, Side::Left).unwrap();
+        assert_eq!(text, format!("{DEV}extra: \"CHANGE_ME\"\n"));
+    }
+
+    #[test]
+    fn copying_a_mapping_adds_its_values() {
+        let dev = Document::parse(DEV);
+        let prod = Document::parse(PROD);
+        let rows = compare(&dev, &prod);
+        let (text, added) = copy_missing(&rows, 5, &dev, &prod, Side::Right).unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(text, format!("{PROD}api:\n  key: \"CHANGE_ME\"\n"));
+        assert!(copy_missing(&rows, 1, &dev, &prod, Side::Right).is_err());
+    }
+}
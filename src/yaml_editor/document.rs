@@ -1,12 +1,26 @@
 //! A source-preserving YAML document model.
 //!
-//! The document keeps the original text verbatim and parses it into a tree of
-//! nodes where every scalar remembers the **byte span** of its value token.
+//! The document keeps the original text verbatim and parses it (with
+//! yaml-rust2's event parser) into a tree of nodes where every scalar
+//! remembers the **byte span** of its value token.
 //! Edits (manual edit, encrypt, decrypt) replace only that one span, so
 //! comments, ordering, blank lines, indentation and unrelated quoting are left
 //! untouched. Constructs we cannot edit safely (flow style, block/multiline
 //! scalars, anchors/aliases/tags) are parsed for navigation but flagged
 //! non-editable rather than silently rewritten.
+//!
+//! Byte-for-byte fidelity extends to a leading UTF-8 BOM, `\r\n` line endings
+//! and trailing whitespace: spans never cover them, so an edit cannot disturb
+//! them. Hard tabs in indentation are rejected by [`Document::validate`].
+
+use std::collections::{HashMap, HashSet};
+
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+use super::json;
+use super::properties;
+use super::query::{self, PathQuery};
 
 /// A segment of a node's logical path.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -34,7 +48,7 @@ pub enum ScalarStyle {
     Unsupported,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Node {
     /// Index into [`Document::nodes`]; used only for rendering.
     pub id: usize,
@@ -50,6 +64,20 @@ pub struct Node {
     /// Byte span of the scalar value token in [`Document::raw`].
     pub value_span: Option<(usize, usize)>,
     pub style: ScalarStyle,
+    /// Comment lines directly above the node (no blank line in between),
+    /// without the leading `#`.
+    pub leading_comments: Vec<String>,
+    /// The comment at the end of the node's own line, without the `#`.
+    pub inline_comment: Option<String>,
+    /// Byte span of the inline comment (from `#` to end of text) in
+    /// [`Document::raw`].
+    pub comment_span: Option<(usize, usize)>,
+    /// Byte offset just past the line's content, where a new inline comment
+    /// is inserted.
+    pub line_end: usize,
+    /// Byte offset where the node's entry starts in a YAML document: its key,
+    /// or a sequence item's first token. `None` inside flow collections.
+    pub entry_start: Option<usize>,
 }
 
 impl Node {
@@ -60,7 +88,57 @@ impl Node {
     }
 }
 
-/// Render a path as a dotted/bracketed string (`servers[0].host`).
+/// The file formats a [`Document`] can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DocFormat {
+    #[default]
+    Yaml,
+    /// Java `.properties` (see [`super::properties`]).
+    Properties,
+    /// JSON (see [`super::json`]).
+    Json,
+}
+
+impl DocFormat {
+    /// The format for a file name's extension, if it is one we edit.
+    pub fn from_path(path: &std::path::Path) -> Option<DocFormat> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(DocFormat::Yaml),
+            "properties" => Some(DocFormat::Properties),
+            "json" => Some(DocFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DocFormat::Yaml => "YAML",
+            DocFormat::Properties => "properties",
+            DocFormat::Json => "JSON",
+        }
+    }
+}
+
+/// The line-ending style of a document, detected from its first line break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Render a path as a dotted/bracketed string (`servers[0].host`). Keys that
+/// would be ambiguous are quoted, so the result parses back with
+/// [`query::parse_path`].
 pub fn path_to_string(path: &[PathSeg]) -> String {
     let mut out = String::new();
     for seg in path {
@@ -69,7 +147,7 @@ pub fn path_to_string(path: &[PathSeg]) -> String {
                 if !out.is_empty() {
                     out.push('.');
                 }
-                out.push_str(k);
+                out.push_str(&query::quote_key(k));
             }
             PathSeg::Index(i) => {
                 out.push('[');
@@ -86,6 +164,9 @@ pub struct Document {
     raw: String,
     nodes: Vec<Node>,
     roots: Vec<usize>,
+    /// Path → node id, so lookups by path don't scan every node.
+    index: HashMap<Vec<PathSeg>, usize>,
+    format: DocFormat,
 }
 
 impl Document {
@@ -93,6 +174,10 @@ impl Document {
         &self.raw
     }
 
+    pub fn format(&self) -> DocFormat {
+        self.format
+    }
+
     pub fn nodes(&self) -> &[Node] {
         &self.nodes
     }
@@ -105,9 +190,31 @@ impl Document {
         self.nodes.get(id)
     }
 
+    /// The line-ending style of the source; `Lf` when it has no line break.
+    pub fn line_ending(&self) -> LineEnding {
+        match self.raw.find('\n') {
+            Some(i) if self.raw[..i].ends_with('\r') => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+
     /// Find a node id by its logical path.
     pub fn find_by_path(&self, path: &[PathSeg]) -> Option<usize> {
-        self.nodes.iter().find(|n| n.path == path).map(|n| n.id)
+        self.index.get(path).copied()
+    }
+
+    /// Node ids whose path matches `query`, in document order.
+    pub fn query(&self, query: &PathQuery) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .filter(|n| query.matches(&n.path))
+            .map(|n| n.id)
+            .collect()
+    }
+
+    /// Parse `query` (see [`super::query`]) and return the matching node ids.
+    pub fn select(&self, query: &str) -> Result<Vec<usize>, String> {
+        Ok(self.query(&PathQuery::parse(query)?))
     }
 
     /// The raw source text of a scalar's value (including any quotes).
@@ -120,25 +227,190 @@ impl Document {
     pub fn logical_value(&self, id: usize) -> Option<String> {
         let node = self.nodes.get(id)?;
         let src = self.value_source(id)?;
-        Some(scalar_logical_value(src, node.style))
+        Some(match self.format {
+            DocFormat::Yaml => scalar_logical_value(src, node.style),
+            DocFormat::Properties => properties::unescape(src),
+            DocFormat::Json => json::logical_value(src),
+        })
+    }
+
+    /// Node `id` and everything under it as standalone YAML: `key:` and its
+    /// subtree for a keyed node, the bare value for a sequence item. From a
+    /// YAML document this is the node's own source, comments and quoting
+    /// included; otherwise plain values keep their types (`8081`, `true`).
+    pub fn subtree_yaml(&self, id: usize) -> Option<String> {
+        let node = self.nodes.get(id)?;
+        if self.format == DocFormat::Yaml {
+            if let Some(source) = self.subtree_source(id) {
+                return Some(source);
+            }
+        }
+        let value = self.yaml_value(id);
+        let value = match node.path.last() {
+            Some(PathSeg::Key(k)) => {
+                let mut map = serde_yaml::Mapping::new();
+                map.insert(serde_yaml::Value::String(k.clone()), value);
+                serde_yaml::Value::Mapping(map)
+            }
+            _ => value,
+        };
+        serde_yaml::to_string(&value).ok()
+    }
+
+    /// The source lines of node `id` and its descendants, from its entry to
+    /// the end of its last line, dedented so the entry starts at column 0.
+    fn subtree_source(&self, id: usize) -> Option<String> {
+        let start = self.nodes[id].entry_start?;
+        let mut end = start;
+        let mut stack = vec![id];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            let spans = [node.value_span, node.comment_span];
+            end = spans
+                .iter()
+                .flatten()
+                .map(|&(_, e)| e)
+                .fold(end.max(node.line_end), usize::max);
+            stack.extend(&node.children);
+        }
+        let raw = &self.raw;
+        let line_start = raw[..start].rfind('\n').map_or(0, |i| i + 1);
+        let end = raw[end..].find('\n').map_or(raw.len(), |i| end + i + 1);
+        let column = start - line_start;
+        let mut out = String::with_capacity(end - start);
+        out.push_str(&raw[start..end]);
+        let mut lines = out.split_inclusive('\n');
+        let mut dedented = lines.next().unwrap_or_default().to_string();
+        for line in lines {
+            let indent = line.len() - line.trim_start_matches(' ').len();
+            dedented.push_str(&line[indent.min(column)..]);
+        }
+        if !dedented.ends_with('\n') {
+            dedented.push('\n');
+        }
+        Some(dedented)
+    }
+
+    fn yaml_value(&self, id: usize) -> serde_yaml::Value {
+        use serde_yaml::Value;
+        let node = &self.nodes[id];
+        match node.kind {
+            NodeKind::Mapping => Value::Mapping(
+                node.children
+                    .iter()
+                    .map(|&c| {
+                        let key = match self.nodes[c].path.last() {
+                            Some(PathSeg::Key(k)) => k.clone(),
+                            _ => self.nodes[c].label.clone(),
+                        };
+                        (Value::String(key), self.yaml_value(c))
+                    })
+                    .collect(),
+            ),
+            NodeKind::Sequence => {
+                Value::Sequence(node.children.iter().map(|&c| self.yaml_value(c)).collect())
+            }
+            NodeKind::Scalar => {
+                let Some(value) = self.logical_value(id) else {
+                    return Value::Null;
+                };
+                // Plain tokens are typed the way YAML reads them; block
+                // scalars, tags and the like are read from their source.
+                let typed = match node.style {
+                    ScalarStyle::Plain => serde_yaml::from_str(&value).ok(),
+                    ScalarStyle::Unsupported => self
+                        .value_source(id)
+                        .and_then(|src| serde_yaml::from_str(src).ok()),
+                    _ => None,
+                };
+                typed
+                    .filter(|v: &Value| !v.is_mapping() && !v.is_sequence())
+                    .unwrap_or(Value::String(value))
+            }
+        }
+    }
+
+    /// The source token that stores `value` as a string in this format: a
+    /// quoted YAML or JSON string, or an escaped property value.
+    pub fn encode_value(&self, value: &str) -> String {
+        match self.format {
+            DocFormat::Yaml => serialize_scalar_quoted(value),
+            DocFormat::Properties => properties::escape_value(value),
+            DocFormat::Json => json::quote(value),
+        }
     }
 
     /// Parse raw YAML text into a source-preserving document.
     pub fn parse(raw: &str) -> Document {
-        let mut nodes: Vec<Node> = Vec::new();
-        let lines = significant_lines(raw);
-        let mut cursor = 0usize;
-        let indent = lines.first().map(|l| l.indent).unwrap_or(0);
-        let roots = parse_block(raw, &lines, &mut cursor, indent, &[], None, &mut nodes);
+        Document::parse_as(raw, DocFormat::Yaml)
+    }
+
+    /// Parse raw text of the given format into a source-preserving document.
+    pub fn parse_as(raw: &str, format: DocFormat) -> Document {
+        let (nodes, roots) = match format {
+            DocFormat::Yaml => parse_yaml(raw),
+            DocFormat::Properties => properties::parse(raw),
+            DocFormat::Json => json::parse(raw),
+        };
+        let mut index = HashMap::with_capacity(nodes.len());
+        for node in &nodes {
+            match format {
+                // With duplicate keys the first occurrence wins, as before.
+                DocFormat::Yaml => {
+                    index.entry(node.path.clone()).or_insert(node.id);
+                }
+                // `Properties::load` and JSON readers keep the last one.
+                DocFormat::Properties | DocFormat::Json => {
+                    index.insert(node.path.clone(), node.id);
+                }
+            }
+        }
         Document {
             raw: raw.to_string(),
             nodes,
             roots,
+            index,
+            format,
         }
     }
 
+    /// Validate that `text` is well-formed in the given format.
+    pub fn validate_as(text: &str, format: DocFormat) -> Result<(), String> {
+        match format {
+            DocFormat::Yaml => Document::validate(text),
+            DocFormat::Properties => properties::validate(text),
+            DocFormat::Json => json::validate(text),
+        }
+    }
+}
+
+/// Build the node tree of a YAML document.
+fn parse_yaml(raw: &str) -> (Vec<Node>, Vec<usize>) {
+    let mut builder = Builder {
+        raw,
+        events: collect_events(raw),
+        pos: 0,
+        nodes: Vec::new(),
+        entries: Vec::new(),
+    };
+    let roots = builder.roots();
+    builder.attach_comments(&line_infos(raw));
+    for (node, &(at, in_flow)) in builder.nodes.iter_mut().zip(&builder.entries) {
+        if !in_flow {
+            node.entry_start = at;
+        }
+    }
+    (builder.nodes, roots)
+}
+
+impl Document {
     /// Validate that `text` is well-formed YAML (used before saving/replacing).
+    /// Tab-indented lines are rejected even where serde_yaml would tolerate
+    /// them, since the span scanner measures indentation in spaces.
     pub fn validate(text: &str) -> Result<(), String> {
+        check_indentation(text)?;
+        // libyaml can misread a BOM-prefixed stream as several documents.
+        let text = text.strip_prefix(BOM).unwrap_or(text);
         serde_yaml::from_str::<serde_yaml::Value>(text)
             .map(|_| ())
             .map_err(|e| e.to_string())
@@ -160,9 +432,262 @@ impl Document {
         text.push_str(&self.raw[..s]);
         text.push_str(new_source);
         text.push_str(&self.raw[e..]);
+        Document::validate_as(&text, self.format)?;
+        Ok(text)
+    }
+
+    /// Replace the scalar value at `id` with `new_source` in place, without
+    /// re-parsing the file: the token is checked on its own, then the text is
+    /// spliced and the spans after it shifted. A single editable scalar token
+    /// cannot change the document's structure, so the result is exactly what
+    /// [`Document::parse`] would build. Returns the replaced span as
+    /// `(at, removed, inserted)`. Never mutates on failure.
+    pub fn replace_scalar(
+        &mut self,
+        id: usize,
+        new_source: &str,
+    ) -> Result<(usize, String, String), String> {
+        let node = self
+            .nodes
+            .get(id)
+            .ok_or_else(|| "node not found".to_string())?;
+        if !node.is_editable_scalar() {
+            return Err("this value cannot be edited in place".to_string());
+        }
+        let style = match self.format {
+            DocFormat::Yaml => scalar_token_style(new_source)?,
+            DocFormat::Properties => {
+                properties::check_token(new_source)?;
+                ScalarStyle::Plain
+            }
+            DocFormat::Json => json::scalar_token_style(new_source)?,
+        };
+        let (s, e) = node.value_span.unwrap();
+        let removed = self.raw[s..e].to_string();
+        self.raw.replace_range(s..e, new_source);
+        let end = s + new_source.len();
+        let shift = |p: usize| if p >= e { p + end - e } else { p };
+        // Nodes are in document order: only the edited node, the ones after
+        // it and its ancestors (whose line or closing bracket may follow the
+        // value) can hold an offset past the edit.
+        let mut ancestors = Vec::new();
+        let mut up = node.parent;
+        while let Some(p) = up {
+            ancestors.push(p);
+            up = self.nodes[p].parent;
+        }
+        for i in ancestors.into_iter().chain(id..self.nodes.len()) {
+            let node = &mut self.nodes[i];
+            node.value_span = node.value_span.map(|(a, b)| (shift(a), shift(b)));
+            node.comment_span = node.comment_span.map(|(a, b)| (shift(a), shift(b)));
+            node.line_end = shift(node.line_end);
+            node.entry_start = node.entry_start.map(shift);
+        }
+        let node = &mut self.nodes[id];
+        node.value_span = Some((s, end));
+        node.style = style;
+        Ok((s, removed, new_source.to_string()))
+    }
+
+    /// Set, replace or (with an empty `comment`) remove the inline comment on
+    /// the line of node `id`, leaving its value untouched. Returns the new full
+    /// text; never mutates on failure.
+    pub fn replace_inline_comment(&self, id: usize, comment: &str) -> Result<String, String> {
+        let node = self
+            .nodes
+            .get(id)
+            .ok_or_else(|| "node not found".to_string())?;
+        if self.format != DocFormat::Yaml {
+            return Err(format!(
+                "{} files have no inline comments",
+                self.format.label()
+            ));
+        }
+        if comment.contains(['\n', '\r']) {
+            return Err("a comment must fit on one line".to_string());
+        }
+        let comment = comment.trim();
+        let (s, e) = match node.comment_span {
+            // Removing: also drop the whitespace separating it from the value.
+            Some((s, e)) if comment.is_empty() => {
+                let kept = self.raw[..s].trim_end_matches([' ', '\t']).len();
+                (kept, e)
+            }
+            Some(span) => span,
+            None if comment.is_empty() => return Ok(self.raw.clone()),
+            None => (node.line_end, node.line_end),
+        };
+        let token = match (comment.is_empty(), node.comment_span) {
+            (true, _) => String::new(),
+            (false, Some(_)) => format!("# {comment}"),
+            (false, None) => format!(" # {comment}"),
+        };
+        let mut text = String::with_capacity(self.raw.len() + token.len());
+        text.push_str(&self.raw[..s]);
+        text.push_str(&token);
+        text.push_str(&self.raw[e..]);
         Document::validate(&text)?;
         Ok(text)
     }
+
+    /// Add a scalar entry `token` (a source token, as for
+    /// [`Document::replace_scalar`]) at `path`, creating missing parent
+    /// mappings, and return the new full text. The entry goes after the
+    /// parent's last entry in the file's own indentation and line endings;
+    /// nothing else changes. Never mutates.
+    pub fn insert_key(&self, path: &[PathSeg], token: &str) -> Result<String, String> {
+        let shown = path_to_string(path);
+        if path.is_empty() {
+            return Err("nothing to insert".to_string());
+        }
+        if self.find_by_path(path).is_some() {
+            return Err(format!("{shown} already exists"));
+        }
+        let text = match self.format {
+            DocFormat::Yaml => {
+                scalar_token_style(token)?;
+                self.insert_yaml(path, token)?
+            }
+            DocFormat::Properties => properties::insert(self, path, token)?,
+            DocFormat::Json => json::insert(self, path, token)?,
+        };
+        Document::validate_as(&text, self.format)?;
+        if Document::parse_as(&text, self.format)
+            .find_by_path(path)
+            .is_none()
+        {
+            return Err(format!("cannot add {shown} without reformatting the file"));
+        }
+        Ok(text)
+    }
+
+    /// The node of the longest prefix of `path` that exists (`None` for the
+    /// top level), and the keys below it that must be created.
+    pub(super) fn split_missing<'p>(
+        &self,
+        path: &'p [PathSeg],
+    ) -> Result<(Option<usize>, Vec<&'p str>), String> {
+        let existing = (0..path.len())
+            .rev()
+            .find(|&k| k == 0 || self.find_by_path(&path[..k]).is_some())
+            .unwrap_or(0);
+        let parent = (existing > 0)
+            .then(|| self.find_by_path(&path[..existing]))
+            .flatten();
+        if let Some(id) = parent {
+            if self.nodes[id].kind != NodeKind::Mapping {
+                return Err(format!(
+                    "{} is not a mapping",
+                    path_to_string(&path[..existing])
+                ));
+            }
+        }
+        let keys = path[existing..]
+            .iter()
+            .map(|seg| match seg {
+                PathSeg::Key(k) => Ok(k.as_str()),
+                PathSeg::Index(_) => Err("only mapping keys can be added".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((parent, keys))
+    }
+
+    fn insert_yaml(&self, path: &[PathSeg], token: &str) -> Result<String, String> {
+        let (parent, keys) = self.split_missing(path)?;
+        let children = match parent {
+            Some(id) => &self.nodes[id].children,
+            None => &self.roots,
+        };
+        let raw = self.raw.as_str();
+        let body = raw.trim_start_matches(BOM).trim_start();
+        let block = match (parent, children.first()) {
+            (Some(id), Some(&first)) => self.nodes[first].line_end > self.nodes[id].line_end,
+            (None, Some(_)) => !body.starts_with(['{', '[']),
+            (Some(_), None) => false,
+            (None, None) => true,
+        };
+        if !block {
+            return Err("entries can only be added to block mappings".to_string());
+        }
+        let line_start = |at: usize| raw[..at].rfind('\n').map_or(0, |i| i + 1);
+        let line_at = |at: usize| {
+            let start = line_start(at);
+            &raw[start..start + raw[start..].find(['\r', '\n']).unwrap_or(raw.len() - start)]
+        };
+        let (indent, first_line) = match children.first() {
+            Some(&first) => {
+                let at = self.nodes[first].line_end;
+                (key_column(line_at(at)), line_start(at))
+            }
+            None => (0, 0),
+        };
+        // Nested keys indent by the parent's own step, else two spaces.
+        let step = match parent {
+            Some(id) if line_start(self.nodes[id].line_end) < first_line => {
+                indent.saturating_sub(key_column(line_at(self.nodes[id].line_end)))
+            }
+            _ => 0,
+        };
+        let step = if step == 0 { 2 } else { step };
+        // The parent's block runs until a content line indented less than its
+        // entries (or a document marker); the new entry goes after its last
+        // content line.
+        let mut insert_at = None;
+        let mut offset = first_line;
+        for line in raw[first_line..].split_inclusive('\n') {
+            let content = line.trim_end_matches(['\n', '\r']).trim_start_matches(BOM);
+            let trimmed = content.trim_start();
+            let column = content.len() - trimmed.len();
+            let is_content = !trimmed.is_empty() && !trimmed.starts_with('#');
+            if is_content {
+                if offset > first_line && (column < indent || trimmed == "---" || trimmed == "...")
+                {
+                    break;
+                }
+                insert_at = Some(offset + line.len());
+            }
+            offset += line.len();
+        }
+        let eol = self.line_ending().as_str();
+        let insert_at = insert_at.unwrap_or(raw.len());
+        let mut entry = String::new();
+        if insert_at > 0 && !raw[..insert_at].ends_with('\n') {
+            entry.push_str(eol);
+        }
+        for (depth, key) in keys.iter().enumerate() {
+            entry.push_str(&" ".repeat(indent + depth * step));
+            entry.push_str(&serialize_scalar(key));
+            entry.push(':');
+            if depth + 1 == keys.len() {
+                entry.push(' ');
+                entry.push_str(token);
+            }
+            entry.push_str(eol);
+        }
+        let mut text = String::with_capacity(raw.len() + entry.len());
+        text.push_str(&raw[..insert_at]);
+        text.push_str(&entry);
+        text.push_str(&raw[insert_at..]);
+        Ok(text)
+    }
+}
+
+/// Column where the key on a block mapping line starts, past any `- `
+/// sequence indicators.
+fn key_column(line: &str) -> usize {
+    let mut rest = line.trim_start_matches(BOM);
+    let mut column = 0;
+    loop {
+        let trimmed = rest.trim_start_matches(' ');
+        column += rest.len() - trimmed.len();
+        match trimmed.strip_prefix('-') {
+            Some(after) if after.starts_with(' ') => {
+                column += 1;
+                rest = after;
+            }
+            _ => return column,
+        }
+    }
 }
 
 // --- encrypted-value wrapper helpers ---------------------------------------
@@ -265,244 +790,552 @@ pub fn scalar_logical_value(source: &str, style: ScalarStyle) -> String {
     }
 }
 
-// --- block parser -----------------------------------------------------------
+// --- event parser -----------------------------------------------------------
+//
+// Structure and token positions come from yaml-rust2's event stream, so the
+// tree is exactly what a YAML loader sees. The parser only reports where each
+// token *starts*; the end of a value token is recovered from the source.
 
-struct SigLine<'a> {
-    indent: usize,
-    /// Byte offset in `raw` where the content (after indentation) starts.
-    content_start: usize,
-    text: &'a str,
+const BOM: char = '\u{feff}';
+
+/// Reject hard tabs in the indentation of any non-blank, non-comment line
+/// outside a scalar: the lines a block or multi-line quoted scalar continues
+/// onto are content, where tabs are allowed.
+fn check_indentation(text: &str) -> Result<(), String> {
+    let events = collect_events(text);
+    let line_start = |at: usize| text[..at].rfind('\n').map_or(0, |i| i + 1);
+    let mut content = Vec::new();
+    for (i, (ev, at)) in events.iter().enumerate() {
+        let Event::Scalar(_, style, ..) = ev else {
+            continue;
+        };
+        let at = *at;
+        // A block scalar's marker is its first content line and it runs up to
+        // the line of the next event; a quoted one's is its opening quote.
+        let span = match style {
+            TScalarStyle::Literal | TScalarStyle::Folded => events
+                .get(i + 1)
+                .map(|&(_, next)| line_start(at)..line_start(next)),
+            TScalarStyle::DoubleQuoted if text[at..].starts_with('"') => {
+                find_double_quote_end(&text[at..]).map(|end| at + 1..at + end)
+            }
+            TScalarStyle::SingleQuoted if text[at..].starts_with('\'') => {
+                find_single_quote_end(&text[at..]).map(|end| at + 1..at + end)
+            }
+            _ => None,
+        };
+        content.extend(span);
+    }
+    let mut start = 0;
+    for (n, line) in text.split_inclusive('\n').enumerate() {
+        let at = start;
+        start += line.len();
+        let line = line
+            .strip_prefix(BOM)
+            .unwrap_or(line)
+            .trim_end_matches(['\n', '\r']);
+        let body = line.trim_start_matches([' ', '\t']);
+        if body.is_empty() || body.starts_with('#') {
+            continue;
+        }
+        if content.iter().any(|r| r.contains(&at)) {
+            continue;
+        }
+        if line[..line.len() - body.len()].contains('\t') {
+            return Err(format!(
+                "line {}: tab character in indentation (YAML indents with spaces only)",
+                n + 1
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Per-line facts used to attach comments to nodes.
+struct LineInfo {
+    /// Byte offset in `raw` where the line starts.
+    start: usize,
+    /// Byte offset just past the line's content (before any comment and
+    /// trailing whitespace).
+    end: usize,
+    /// Comment lines directly above this one (only for content lines).
+    leading: Vec<String>,
+    /// Span of a trailing `# ...` comment on this line.
+    comment: Option<(usize, usize)>,
 }
 
-/// Collect non-blank, non-comment lines with their indentation and byte offset.
-fn significant_lines(raw: &str) -> Vec<SigLine<'_>> {
+fn line_infos(raw: &str) -> Vec<LineInfo> {
     let mut out = Vec::new();
-    let mut offset = 0usize;
-    for line in raw.split_inclusive('\n') {
-        let trimmed_len = line.trim_end_matches(['\n', '\r']).len();
-        let content = &line[..trimmed_len];
+    // A leading BOM is not part of the first line's content.
+    let mut offset = if raw.starts_with(BOM) {
+        BOM.len_utf8()
+    } else {
+        0
+    };
+    let mut leading = Vec::new();
+    for line in raw[offset..].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
         let indent = content.len() - content.trim_start().len();
         let body = content.trim_start();
-        if !body.is_empty() && !body.starts_with('#') && body != "---" && body != "..." {
-            out.push(SigLine {
-                indent,
-                content_start: offset + indent,
-                text: body,
-            });
+        let start = offset + indent;
+        let mut info = LineInfo {
+            start: offset,
+            end: start + body.trim_end().len(),
+            leading: Vec::new(),
+            comment: None,
+        };
+        if body.is_empty() || body == "---" || body == "..." {
+            leading.clear();
+        } else if let Some(c) = body.strip_prefix('#') {
+            leading.push(comment_text(c));
+        } else {
+            info.comment = find_comment(body).map(|i| (start + i, start + body.trim_end().len()));
+            let text_end = info.comment.map(|(s, _)| s - start).unwrap_or(body.len());
+            info.end = start + body[..text_end].trim_end().len();
+            info.leading = std::mem::take(&mut leading);
         }
+        out.push(info);
         offset += line.len();
     }
     out
 }
 
-fn is_seq_marker(text: &str) -> bool {
-    text == "-" || text.starts_with("- ")
+/// Byte offset of a trailing comment's `#` in a line body: the first `#`
+/// outside quotes that follows whitespace. A quote only opens a quoted
+/// scalar at the start of a token, so `it's # note` still has a comment.
+fn find_comment(body: &str) -> Option<usize> {
+    let bytes = body.as_bytes();
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let token_start = i == 0 || matches!(bytes[i - 1], b' ' | b'\t' | b'[' | b'{' | b',');
+        match bytes[i] {
+            b'\\' if in_double => i += 1,
+            b'\'' if in_single && bytes.get(i + 1) == Some(&b'\'') => i += 1,
+            b'\'' if in_single => in_single = false,
+            b'"' if in_double => in_double = false,
+            b'\'' if !in_double && token_start => in_single = true,
+            b'"' if !in_single && token_start => in_double = true,
+            b'#' if !in_single && !in_double && i > 0 && matches!(bytes[i - 1], b' ' | b'\t') => {
+                return Some(i);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
 }
 
-/// Parse sibling entries at column `indent`, returning their node ids.
-fn parse_block(
-    raw: &str,
-    lines: &[SigLine<'_>],
-    cursor: &mut usize,
-    indent: usize,
-    parent_path: &[PathSeg],
-    parent: Option<usize>,
-    nodes: &mut Vec<Node>,
-) -> Vec<usize> {
-    let mut ids = Vec::new();
-    let mut seq_index = 0usize;
-    while *cursor < lines.len() {
-        let (line_indent, is_seq) = {
-            let l = &lines[*cursor];
-            (l.indent, is_seq_marker(l.text))
-        };
-        if line_indent < indent {
-            break;
-        }
-        if line_indent > indent {
-            // Malformed relative to this level; skip to stay panic-free.
-            *cursor += 1;
-            continue;
-        }
-        if is_seq {
-            let id = parse_seq_item(
-                raw,
-                lines,
-                cursor,
-                indent,
-                parent_path,
-                parent,
-                seq_index,
-                nodes,
-            );
-            ids.push(id);
-            seq_index += 1;
-        } else if let Some(id) =
-            parse_map_entry(raw, lines, cursor, indent, parent_path, parent, nodes)
-        {
-            ids.push(id);
+/// `text` with the tab after each `:` separator turned into a space. Only
+/// separators outside quotes and before a trailing comment count, so a quoted
+/// key such as `"a:\tb"` keeps its tab. Tabs leading the continuation lines of
+/// a multi-line quoted scalar, which are folded away, become spaces too.
+fn tab_separators(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let (mut in_single, mut in_double) = (false, false);
+    for line in text.split_inclusive('\n') {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        let end = if in_single || in_double {
+            i = line.len() - line.trim_start_matches([' ', '\t']).len();
+            out.extend(std::iter::repeat_n(' ', i));
+            line.len()
         } else {
-            *cursor += 1;
+            find_comment(line).unwrap_or(line.len())
+        };
+        let mut last = i;
+        while i < end {
+            let token_start = i == 0 || matches!(bytes[i - 1], b' ' | b'\t' | b'[' | b'{' | b',');
+            match bytes[i] {
+                b'\\' if in_double => i += 1,
+                b'\'' if in_single && bytes.get(i + 1) == Some(&b'\'') => i += 1,
+                b'\'' if in_single => in_single = false,
+                b'"' if in_double => in_double = false,
+                b'\'' if !in_double && token_start => in_single = true,
+                b'"' if !in_single && token_start => in_double = true,
+                b':' if !in_single && !in_double && bytes.get(i + 1) == Some(&b'\t') => {
+                    out.push_str(&line[last..=i]);
+                    out.push(' ');
+                    i += 1;
+                    last = i + 1;
+                }
+                _ => {}
+            }
+            i += 1;
         }
+        out.push_str(&line[last..]);
     }
-    ids
+    out
 }
 
-#[allow(clippy::too_many_arguments)]
-fn parse_seq_item(
-    raw: &str,
-    lines: &[SigLine<'_>],
-    cursor: &mut usize,
-    indent: usize,
-    parent_path: &[PathSeg],
-    parent: Option<usize>,
-    seq_index: usize,
-    nodes: &mut Vec<Node>,
-) -> usize {
-    let (content_start, rest) = {
-        let l = &lines[*cursor];
-        (l.content_start, &l.text[1..]) // after '-'
-    };
-    let spaces = rest.len() - rest.trim_start().len();
-    let rest_trimmed = rest.trim_start();
-    let item_col = indent + 1 + spaces;
-    let value_off = content_start + 1 + spaces;
-
-    let mut path = parent_path.to_vec();
-    path.push(PathSeg::Index(seq_index));
-    let id = push_node(nodes, parent, path.clone(), format!("[{seq_index}]"));
-
-    if rest_trimmed.is_empty() {
-        // `-` with a nested block on following lines.
-        *cursor += 1;
-        if let Some(ci) = lines.get(*cursor).map(|l| l.indent) {
-            if ci >= item_col {
-                let children = parse_block(raw, lines, cursor, ci, &path, Some(id), nodes);
-                finish_container(nodes, id, children);
-                return id;
-            }
-        }
-        nodes[id].kind = NodeKind::Scalar;
-        nodes[id].style = ScalarStyle::Unsupported;
-        return id;
-    }
-
-    if is_inline_mapping_start(rest_trimmed) {
-        // `- key: ...`: first key sits on the dash line at column `item_col`.
-        let mut children = Vec::new();
-        if let Some(cid) = parse_map_entry_line(
-            raw,
-            lines,
-            cursor,
-            item_col,
-            value_off,
-            rest_trimmed,
-            &path,
-            Some(id),
-            nodes,
-        ) {
-            children.push(cid);
-        }
-        let more = parse_block(raw, lines, cursor, item_col, &path, Some(id), nodes);
-        children.extend(more);
-        nodes[id].kind = NodeKind::Mapping;
-        nodes[id].children = children;
-        return id;
-    }
-
-    // `- scalar`.
-    let (span, style) = scalar_span(value_off, rest_trimmed);
-    nodes[id].kind = NodeKind::Scalar;
-    nodes[id].value_span = Some(span);
-    nodes[id].style = style;
-    *cursor += 1;
-    id
+/// Comment text without the `#` and the single space conventionally after it.
+fn comment_text(after_hash: &str) -> String {
+    after_hash
+        .strip_prefix(' ')
+        .unwrap_or(after_hash)
+        .trim_end()
+        .to_string()
 }
 
-fn parse_map_entry(
-    raw: &str,
-    lines: &[SigLine<'_>],
-    cursor: &mut usize,
-    indent: usize,
-    parent_path: &[PathSeg],
-    parent: Option<usize>,
-    nodes: &mut Vec<Node>,
-) -> Option<usize> {
-    let (content_start, text) = {
-        let l = &lines[*cursor];
-        (l.content_start, l.text)
-    };
-    parse_map_entry_line(
-        raw,
-        lines,
-        cursor,
-        indent,
-        content_start,
-        text,
-        parent_path,
-        parent,
-        nodes,
-    )
-}
-
-/// Parse a `key: ...` entry given its text and byte offset (works for both a
-/// real line and the inline first key of a `- key:` sequence item).
-#[allow(clippy::too_many_arguments)]
-fn parse_map_entry_line(
-    raw: &str,
-    lines: &[SigLine<'_>],
-    cursor: &mut usize,
-    indent: usize,
-    content_start: usize,
-    text: &str,
-    parent_path: &[PathSeg],
-    parent: Option<usize>,
-    nodes: &mut Vec<Node>,
-) -> Option<usize> {
-    let (key, after_colon, value_off) = split_key(text, content_start)?;
-    let mut path = parent_path.to_vec();
-    path.push(PathSeg::Key(key.clone()));
-    let id = push_node(nodes, parent, path.clone(), key);
-
-    let value = after_colon.trim();
-    if value.is_empty() {
-        *cursor += 1;
-        if let Some(ci) = lines.get(*cursor).map(|l| l.indent) {
-            let nested_seq =
-                ci == indent && lines.get(*cursor).is_some_and(|l| is_seq_marker(l.text));
-            if ci > indent || nested_seq {
-                let children = parse_block(raw, lines, cursor, ci, &path, Some(id), nodes);
-                finish_container(nodes, id, children);
-                return Some(id);
-            }
-        }
-        // Empty scalar (null) — navigable, not editable in place.
-        nodes[id].kind = NodeKind::Scalar;
-        nodes[id].style = ScalarStyle::Unsupported;
-        Some(id)
-    } else {
-        let (span, style) = scalar_span(value_off, &after_colon);
-        nodes[id].kind = NodeKind::Scalar;
-        nodes[id].value_span = Some(span);
-        nodes[id].style = style;
-        *cursor += 1;
-        Some(id)
+/// Collects parser events with the byte offset of their start marker.
+struct Collector {
+    events: Vec<(Event, usize)>,
+    /// Byte offset of each char of the parsed text (markers count chars).
+    char_bytes: Vec<usize>,
+    base: usize,
+}
+
+impl MarkedEventReceiver for Collector {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        let byte = self
+            .char_bytes
+            .get(mark.index())
+            .or(self.char_bytes.last())
+            .copied()
+            .unwrap_or(0);
+        self.events.push((ev, self.base + byte));
     }
 }
 
-fn finish_container(nodes: &mut [Node], id: usize, children: Vec<usize>) {
-    let is_seq = children
-        .iter()
-        .any(|&c| matches!(nodes[c].path.last(), Some(PathSeg::Index(_))));
-    nodes[id].kind = if is_seq {
-        NodeKind::Sequence
+/// Parse the first document of `raw` into events. On a syntax error the
+/// events before it are kept, so a broken file still shows what was read.
+fn collect_events(raw: &str) -> Vec<(Event, usize)> {
+    let base = if raw.starts_with(BOM) {
+        BOM.len_utf8()
     } else {
-        NodeKind::Mapping
+        0
+    };
+    // yaml-rust2 refuses a tab after `:` that other loaders accept; a space
+    // has the same length, so markers still line up with `raw`.
+    let text = tab_separators(&raw[base..]);
+    let text = text.as_str();
+    let mut collector = Collector {
+        events: Vec::new(),
+        char_bytes: text
+            .char_indices()
+            .map(|(b, _)| b)
+            .chain(std::iter::once(text.len()))
+            .collect(),
+        base,
     };
-    nodes[id].children = children;
+    let _ = Parser::new_from_str(text).load(&mut collector, false);
+    collector.events
+}
+
+/// Builds nodes from the event stream, one node per mapping entry or
+/// sequence item.
+struct Builder<'a> {
+    raw: &'a str,
+    events: Vec<(Event, usize)>,
+    pos: usize,
+    nodes: Vec<Node>,
+    /// Per node: byte offset where its entry starts (a key, or the item's
+    /// first token), and whether it sits inside a flow collection.
+    entries: Vec<(Option<usize>, bool)>,
 }
 
-fn push_node(
+impl Builder<'_> {
+    fn peek(&self) -> Option<&(Event, usize)> {
+        self.events.get(self.pos)
+    }
+
+    /// Node ids of the document's top-level entries.
+    fn roots(&mut self) -> Vec<usize> {
+        while let Some((Event::StreamStart | Event::DocumentStart, _)) = self.peek() {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some((Event::MappingStart(..), at)) => {
+                let flow = self.is_flow_mapping(*at);
+                self.pos += 1;
+                self.mapping_entries(None, &[], flow)
+            }
+            Some((Event::SequenceStart(..), at)) => {
+                let flow = self.raw[*at..].starts_with('[');
+                self.pos += 1;
+                self.sequence_items(None, &[], flow)
+            }
+            // A bare scalar document has nothing to navigate.
+            _ => Vec::new(),
+        }
+    }
+
+    /// A mapping is flow style when its marker sits on the `{` before its
+    /// first key; block mapping markers point into the first key instead.
+    fn is_flow_mapping(&self, at: usize) -> bool {
+        self.raw[at..].starts_with('{')
+            && self
+                .events
+                .get(self.pos + 1)
+                .is_some_and(|(_, next)| *next > at)
+    }
+
+    fn mapping_entries(
+        &mut self,
+        parent: Option<usize>,
+        path: &[PathSeg],
+        flow: bool,
+    ) -> Vec<usize> {
+        let mut ids = Vec::new();
+        while let Some((ev, at)) = self.peek().cloned() {
+            self.pos += 1;
+            let key = match ev {
+                Event::MappingEnd => break,
+                Event::Scalar(key, ..) => key,
+                // A complex key (`? [a, b]`): keep the value, not the key.
+                Event::MappingStart(..) | Event::SequenceStart(..) => {
+                    self.pos -= 1;
+                    self.skip();
+                    "?".to_string()
+                }
+                _ => "?".to_string(),
+            };
+            let mut child_path = path.to_vec();
+            child_path.push(PathSeg::Key(key.clone()));
+            match self.node(parent, child_path, key, Some(at), flow) {
+                Some(id) => ids.push(id),
+                None => break,
+            }
+        }
+        ids
+    }
+
+    fn sequence_items(
+        &mut self,
+        parent: Option<usize>,
+        path: &[PathSeg],
+        flow: bool,
+    ) -> Vec<usize> {
+        let mut ids = Vec::new();
+        loop {
+            match self.peek() {
+                None => break,
+                Some((Event::SequenceEnd, _)) => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(_) => {}
+            }
+            let i = ids.len();
+            let mut child_path = path.to_vec();
+            child_path.push(PathSeg::Index(i));
+            match self.node(parent, child_path, format!("[{i}]"), None, flow) {
+                Some(id) => ids.push(id),
+                None => break,
+            }
+        }
+        ids
+    }
+
+    /// Skip one complete node's events.
+    fn skip(&mut self) {
+        let mut depth = 0usize;
+        while let Some((ev, _)) = self.peek() {
+            match ev {
+                Event::MappingStart(..) | Event::SequenceStart(..) => depth += 1,
+                Event::MappingEnd | Event::SequenceEnd => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.pos += 1;
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Consume one node's events and push it (with its descendants).
+    /// `key_at` is the byte offset of its key, for mapping entries.
+    fn node(
+        &mut self,
+        parent: Option<usize>,
+        path: Vec<PathSeg>,
+        label: String,
+        key_at: Option<usize>,
+        in_flow: bool,
+    ) -> Option<usize> {
+        let (ev, at) = self.peek().cloned()?;
+        self.pos += 1;
+        let id = push_node(&mut self.nodes, parent, path.clone(), label);
+        self.entries.push((key_at, in_flow));
+        match ev {
+            Event::Scalar(value, style, anchor, tag) => {
+                self.scalar(id, &value, style, anchor != 0 || tag.is_some(), at, in_flow);
+                self.entries[id].0 = Some(key_at.unwrap_or(at));
+            }
+            Event::Alias(_) => {
+                let rest = &self.raw[at..];
+                let len = rest
+                    .find([' ', '\t', '\r', '\n', ',', ']', '}'])
+                    .unwrap_or(rest.len());
+                self.nodes[id].value_span = Some((at, at + len));
+                self.nodes[id].style = ScalarStyle::Unsupported;
+                self.entries[id].0 = Some(key_at.unwrap_or(at));
+            }
+            Event::MappingStart(..) => {
+                let flow = in_flow || self.is_flow_mapping(at);
+                let children = self.mapping_entries(Some(id), &path, flow);
+                self.finish_container(id, NodeKind::Mapping, children, key_at, flow.then_some(at));
+            }
+            Event::SequenceStart(..) => {
+                let flow = in_flow || self.raw[at..].starts_with('[');
+                let children = self.sequence_items(Some(id), &path, flow);
+                self.finish_container(id, NodeKind::Sequence, children, key_at, flow.then_some(at));
+            }
+            // Truncated stream: keep the node as an empty value.
+            _ => self.nodes[id].style = ScalarStyle::Unsupported,
+        }
+        Some(id)
+    }
+
+    /// A container's entry starts at its key, its flow opener, or else at its
+    /// first child (a `- key: value` item shares the key's line).
+    fn finish_container(
+        &mut self,
+        id: usize,
+        kind: NodeKind,
+        children: Vec<usize>,
+        key_at: Option<usize>,
+        flow_at: Option<usize>,
+    ) {
+        let first = children.first().and_then(|&c| self.entries[c].0);
+        self.entries[id].0 = key_at.or(flow_at).or(first);
+        self.nodes[id].kind = kind;
+        self.nodes[id].children = children;
+    }
+
+    /// Record a scalar's value span and whether it can be edited in place.
+    fn scalar(
+        &mut self,
+        id: usize,
+        value: &str,
+        style: TScalarStyle,
+        decorated: bool,
+        at: usize,
+        in_flow: bool,
+    ) {
+        let rest = &self.raw[at..];
+        let line = &rest[..rest.find(['\r', '\n']).unwrap_or(rest.len())];
+        let first_line = (at, at + line.trim_end().len());
+        let (span, style) = match style {
+            TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => {
+                let (end, quoted) = if style == TScalarStyle::SingleQuoted {
+                    (find_single_quote_end(rest), ScalarStyle::SingleQuoted)
+                } else {
+                    (find_double_quote_end(rest), ScalarStyle::DoubleQuoted)
+                };
+                match end {
+                    Some(e) if !rest[..e].contains('\n') => ((at, at + e), quoted),
+                    // Multi-line quoted scalars are not edited in place.
+                    _ => (first_line, ScalarStyle::Unsupported),
+                }
+            }
+            TScalarStyle::Plain => {
+                if value == "~" && !is_tilde_token(line) {
+                    // An empty value (`key:`): navigable, nothing to edit.
+                    self.nodes[id].style = ScalarStyle::Unsupported;
+                    return;
+                }
+                let end = if in_flow {
+                    line.find([',', ']', '}']).unwrap_or(line.len())
+                } else {
+                    find_comment(line).unwrap_or(line.len())
+                };
+                let text = line[..end].trim_end();
+                // A plain scalar folded over several lines reads differently
+                // from its first line.
+                let style = if text == value {
+                    ScalarStyle::Plain
+                } else {
+                    ScalarStyle::Unsupported
+                };
+                ((at, at + text.len()), style)
+            }
+            TScalarStyle::Literal | TScalarStyle::Folded => match self.block_header(at) {
+                Some(span) => (span, ScalarStyle::Unsupported),
+                None => {
+                    self.nodes[id].style = ScalarStyle::Unsupported;
+                    return;
+                }
+            },
+        };
+        self.nodes[id].value_span = Some(span);
+        self.nodes[id].style = if decorated || in_flow {
+            ScalarStyle::Unsupported
+        } else {
+            style
+        };
+    }
+
+    /// Span of the `|`/`>` indicator of a block scalar whose content starts at
+    /// `at`: the last token on the nearest content line above.
+    fn block_header(&self, at: usize) -> Option<(usize, usize)> {
+        let before = &self.raw[..at];
+        let header_end = before.trim_end().len();
+        let line_start = before[..header_end].rfind('\n').map_or(0, |i| i + 1);
+        let header = &self.raw[line_start..header_end];
+        let header = &header[..find_comment(header).unwrap_or(header.len())];
+        let header = header.trim_end();
+        let token_start = header.rfind([' ', '\t']).map_or(0, |i| i + 1);
+        header[token_start..]
+            .starts_with(['|', '>'])
+            .then_some((line_start + token_start, line_start + header.len()))
+    }
+
+    /// Attach comments: a line's leading comments go to the first node that
+    /// starts on it, the inline comment to the last (so `- key: v # c`
+    /// describes `key`). Nodes inside flow collections take neither.
+    fn attach_comments(&mut self, lines: &[LineInfo]) {
+        let line_of = |at: usize| lines.partition_point(|l| l.start <= at).checked_sub(1);
+        let mut seen = HashSet::new();
+        let mut inline = HashMap::new();
+        for id in 0..self.nodes.len() {
+            let (Some(at), false) = self.entries[id] else {
+                continue;
+            };
+            let Some(l) = line_of(at) else {
+                continue;
+            };
+            if seen.insert(l) {
+                self.nodes[id].leading_comments = lines[l].leading.clone();
+            }
+            inline.insert(l, id);
+        }
+        for (l, id) in inline {
+            let line = &lines[l];
+            let node = &mut self.nodes[id];
+            node.comment_span = line.comment;
+            node.inline_comment = line.comment.map(|(s, e)| comment_text(&self.raw[s + 1..e]));
+            node.line_end = line.end;
+        }
+    }
+}
+
+/// The style of `token` if it is a single, editable scalar on its own, which
+/// is what makes an in-place replacement safe.
+fn scalar_token_style(token: &str) -> Result<ScalarStyle, String> {
+    let invalid = || format!("not a single scalar value: {token}");
+    if token.is_empty() || token.contains(['\n', '\r']) {
+        return Err(invalid());
+    }
+    let probe = Document::parse(&format!("k: {token}"));
+    match probe.nodes() {
+        [node] if node.is_editable_scalar() && probe.value_source(0) == Some(token) => {
+            Ok(node.style)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Whether a plain token is a literal `~` rather than the parser's stand-in
+/// for an empty value.
+fn is_tilde_token(line: &str) -> bool {
+    line.strip_prefix('~')
+        .is_some_and(|after| after.is_empty() || after.starts_with([' ', '\t', ',', ']', '}']))
+        && !line[1..].trim_start().starts_with(':')
+}
+
+pub(super) fn push_node(
     nodes: &mut Vec<Node>,
     parent: Option<usize>,
     path: Vec<PathSeg>,
@@ -520,78 +1353,15 @@ fn push_node(
         kind: NodeKind::Scalar,
         value_span: None,
         style: ScalarStyle::Plain,
+        leading_comments: Vec::new(),
+        inline_comment: None,
+        comment_span: None,
+        line_end: 0,
+        entry_start: None,
     });
     id
 }
 
-/// Split `key: value` into (key, value-part-with-leading-space, byte offset of
-/// the value). Returns None if there is no top-level `:` separator.
-fn split_key(text: &str, content_start: usize) -> Option<(String, String, usize)> {
-    let bytes = text.as_bytes();
-    let mut in_single = false;
-    let mut in_double = false;
-    for (i, &b) in bytes.iter().enumerate() {
-        match b {
-            b'\'' if !in_double => in_single = !in_single,
-            b'"' if !in_single => in_double = !in_double,
-            b':' if !in_single && !in_double => {
-                let after = &text[i + 1..];
-                if after.is_empty() || after.starts_with(' ') {
-                    let key = text[..i].trim().trim_matches(['\'', '"']).to_string();
-                    return Some((key, after.to_string(), content_start + i + 1));
-                }
-            }
-            b'#' if !in_single && !in_double => break,
-            _ => {}
-        }
-    }
-    None
-}
-
-fn is_inline_mapping_start(s: &str) -> bool {
-    if s.starts_with(['{', '[', '"', '\'']) {
-        return false;
-    }
-    let bytes = s.as_bytes();
-    for (i, &b) in bytes.iter().enumerate() {
-        if b == b':' && (i + 1 == bytes.len() || bytes[i + 1] == b' ') {
-            return true;
-        }
-        if b == b'#' {
-            break;
-        }
-    }
-    false
-}
-
-/// Byte span and style of a scalar value, given the value part (with leading
-/// spaces) and the byte offset where that part starts.
-fn scalar_span(part_start: usize, part: &str) -> ((usize, usize), ScalarStyle) {
-    let leading = part.len() - part.trim_start().len();
-    let start = part_start + leading;
-    let body = part.trim_start();
-
-    if body.starts_with('"') {
-        if let Some(end_rel) = find_double_quote_end(body) {
-            return ((start, start + end_rel), ScalarStyle::DoubleQuoted);
-        }
-    } else if body.starts_with('\'') {
-        if let Some(end_rel) = find_single_quote_end(body) {
-            return ((start, start + end_rel), ScalarStyle::SingleQuoted);
-        }
-    } else if body.starts_with(['{', '[', '|', '>', '&', '*', '!']) {
-        return (
-            (start, start + body.trim_end().len()),
-            ScalarStyle::Unsupported,
-        );
-    }
-
-    // Plain scalar: ends before a trailing " #" comment.
-    let end_rel = body.find(" #").unwrap_or(body.len());
-    let value_len = body[..end_rel].trim_end().len();
-    ((start, start + value_len), ScalarStyle::Plain)
-}
-
 fn find_double_quote_end(s: &str) -> Option<usize> {
     let bytes = s.as_bytes();
     let mut i = 1;
@@ -645,6 +1415,29 @@ servers:
             .id
     }
 
+    #[test]
+    fn subtrees_copy_their_source() {
+        let doc = Document::parse(
+            "db:\n  port: 5432 # main\n  # the secret\n  password: \"![c2VjcmV0]\"\n  tls: true\nhosts:\n  - a\n  - name: b\n    port: 1\nflow: {x: 1}\n",
+        );
+        assert_eq!(
+            doc.subtree_yaml(nid(&doc, "db")).unwrap(),
+            "db:\n  port: 5432 # main\n  # the secret\n  password: \"![c2VjcmV0]\"\n  tls: true\n"
+        );
+        assert_eq!(
+            doc.subtree_yaml(nid(&doc, "hosts[1]")).unwrap(),
+            "name: b\nport: 1\n"
+        );
+        assert_eq!(doc.subtree_yaml(nid(&doc, "hosts[0]")).unwrap(), "a\n");
+        // Inside a flow collection, and in other formats, values are typed.
+        assert_eq!(doc.subtree_yaml(nid(&doc, "flow.x")).unwrap(), "x: 1\n");
+        let json = Document::parse_as(r#"{"db": {"port": 5432, "tls": "true"}}"#, DocFormat::Json);
+        assert_eq!(
+            json.subtree_yaml(nid(&json, "db")).unwrap(),
+            "db:\n  port: 5432\n  tls: 'true'\n"
+        );
+    }
+
     #[test]
     fn parses_nested_mappings() {
         let doc = Document::parse(SAMPLE);
@@ -773,4 +1566,208 @@ servers:
         assert_eq!(serialize_scalar_quoted("![CIPHER]"), "\"![CIPHER]\"");
         assert_eq!(serialize_scalar_quoted("a\"b"), "\"a\\\"b\"");
     }
+
+    #[test]
+    fn bom_and_crlf_are_outside_every_span() {
+        let src = "\u{feff}db:\r\n  host: localhost  \r\n  pass: \"x\"\t# note\r\n";
+        let doc = Document::parse(src);
+        assert_eq!(doc.line_ending(), LineEnding::CrLf);
+        assert_eq!(doc.node(nid(&doc, "db")).unwrap().label, "db");
+        assert_eq!(doc.value_source(nid(&doc, "db.host")), Some("localhost"));
+        assert_eq!(doc.value_source(nid(&doc, "db.pass")), Some("\"x\""));
+        let out = doc
+            .replace_scalar_source(nid(&doc, "db.host"), "\"h\"")
+            .unwrap();
+        assert_eq!(out, src.replace("localhost", "\"h\""));
+    }
+
+    #[test]
+    fn tab_separators_are_accepted() {
+        let doc = Document::parse("a:\tvalue\t# c\n");
+        assert_eq!(doc.value_source(nid(&doc, "a")), Some("value"));
+        let doc = Document::parse("list:\n  - k:\tv\n");
+        assert_eq!(doc.value_source(nid(&doc, "list[0].k")), Some("v"));
+        // A tab inside a quoted key is part of the key.
+        let doc = Document::parse("\"a:\tb\":\t1\n");
+        let id = doc.find_by_path(&[PathSeg::Key("a:\tb".into())]).unwrap();
+        assert_eq!(doc.value_source(id), Some("1"));
+    }
+
+    #[test]
+    fn tab_indentation_is_rejected() {
+        let err = Document::validate("a:\n\tb: 1\n").unwrap_err();
+        assert!(err.starts_with("line 2: tab"), "{err}");
+        // Tabs after the indentation are fine.
+        Document::validate("a: 1\t# c\nb:\t2\n").unwrap();
+        // So are tabs inside block and multi-line quoted scalars.
+        let src = "script: |\n  set -e\n  \techo hi\nb: \"x\n\ty\"\nc: >\n  a\n\n  \tb\n";
+        Document::validate(src).unwrap();
+        let doc = Document::parse(src);
+        assert_eq!(doc.value_source(nid(&doc, "c")), Some(">"));
+        let err = Document::validate(&format!("{src}d:\n\te: 1\n")).unwrap_err();
+        assert!(err.starts_with("line 11: tab"), "{err}");
+        for src in [
+            "s: |\n  x\n\tb: 1\n",
+            "a: \"x\"\n\tb: 1\n",
+            "a: 'x\n  y'\n\tb: 1\n",
+        ] {
+            assert!(Document::validate(src).is_err(), "{src:?}");
+        }
+    }
+
+    #[test]
+    fn line_ending_defaults_to_lf() {
+        assert_eq!(Document::parse("a: 1").line_ending(), LineEnding::Lf);
+        assert_eq!(Document::parse("a: 1\n").line_ending(), LineEnding::Lf);
+    }
+
+    #[test]
+    fn comments_attach_to_nodes() {
+        let src = "\
+# file header
+
+# rotated 2024-05
+# owner: payments
+db:
+  password: \"x\" # keep in vault
+  note: it's # plain with apostrophe
+list:
+  # first
+  - k: v # on key
+  - plain # on item
+";
+        let doc = Document::parse(src);
+        let node = |p: &str| doc.node(nid(&doc, p)).unwrap();
+        assert_eq!(
+            node("db").leading_comments,
+            vec!["rotated 2024-05", "owner: payments"]
+        );
+        assert_eq!(node("db").inline_comment, None);
+        assert_eq!(
+            node("db.password").inline_comment.as_deref(),
+            Some("keep in vault")
+        );
+        assert_eq!(
+            node("db.note").inline_comment.as_deref(),
+            Some("plain with apostrophe")
+        );
+        assert_eq!(node("list[0]").leading_comments, vec!["first"]);
+        assert_eq!(node("list[0]").inline_comment, None);
+        assert_eq!(node("list[0].k").inline_comment.as_deref(), Some("on key"));
+        assert_eq!(node("list[1]").inline_comment.as_deref(), Some("on item"));
+    }
+
+    #[test]
+    fn hash_inside_quotes_is_not_a_comment() {
+        let doc = Document::parse("a: \"x # y\"\nb: 'it''s # z'\n");
+        assert_eq!(doc.node(nid(&doc, "a")).unwrap().inline_comment, None);
+        assert_eq!(doc.node(nid(&doc, "b")).unwrap().inline_comment, None);
+    }
+
+    #[test]
+    fn inline_comment_add_replace_remove_keeps_value() {
+        let src = "db:\r\n  password: secret\r\n  host: h # old\r\n";
+        let doc = Document::parse(src);
+        let added = doc
+            .replace_inline_comment(nid(&doc, "db.password"), "owner: payments")
+            .unwrap();
+        assert_eq!(added, src.replace("secret", "secret # owner: payments"));
+        let replaced = doc
+            .replace_inline_comment(nid(&doc, "db.host"), "new")
+            .unwrap();
+        assert_eq!(replaced, src.replace("# old", "# new"));
+        let removed = doc
+            .replace_inline_comment(nid(&doc, "db.host"), "")
+            .unwrap();
+        assert_eq!(removed, src.replace(" # old", ""));
+        // A container's key line can carry a comment too.
+        let on_map = doc
+            .replace_inline_comment(nid(&doc, "db"), "creds")
+            .unwrap();
+        assert!(on_map.starts_with("db: # creds\r\n"));
+        assert!(doc.replace_inline_comment(nid(&doc, "db"), "a\nb").is_err());
+    }
+
+    #[test]
+    fn empty_values_are_not_editable_but_tilde_is() {
+        let doc = Document::parse("a:\nb: ~\nc:\n  - x\n");
+        let a = doc.node(nid(&doc, "a")).unwrap();
+        assert_eq!(a.value_span, None);
+        assert!(!a.is_editable_scalar());
+        assert_eq!(doc.value_source(nid(&doc, "b")), Some("~"));
+        assert_eq!(doc.node(nid(&doc, "c")).unwrap().kind, NodeKind::Sequence);
+    }
+
+    #[test]
+    fn flow_collections_are_navigable() {
+        let doc = Document::parse("f: {a: 1, b: [x, \"y\"]} # inline\n");
+        let f = doc.node(nid(&doc, "f")).unwrap();
+        assert_eq!(f.kind, NodeKind::Mapping);
+        assert_eq!(f.inline_comment.as_deref(), Some("inline"));
+        assert_eq!(doc.value_source(nid(&doc, "f.b[1]")), Some("\"y\""));
+        assert!(!doc.node(nid(&doc, "f.b[1]")).unwrap().is_editable_scalar());
+    }
+
+    #[test]
+    fn in_place_replacement_matches_a_full_reparse() {
+        let src = "db:\n  user: admin # who\n  password: secret\n  hosts: [a, b]\nport: 8081\n";
+        let mut doc = Document::parse(src);
+        doc.replace_scalar(nid(&doc, "db.user"), "\"![LONGER CIPHER]\"")
+            .unwrap();
+        doc.replace_scalar(nid(&doc, "port"), "1").unwrap();
+        let reparsed = Document::parse(doc.raw());
+        assert_eq!(doc.nodes(), reparsed.nodes());
+        assert_eq!(
+            doc.find_by_path(&[PathSeg::Key("port".into())]),
+            Some(nid(&doc, "port"))
+        );
+    }
+
+    #[test]
+    fn in_place_replacement_rejects_structural_tokens() {
+        let mut doc = Document::parse(SAMPLE);
+        let id = nid(&doc, "database.host");
+        for bad in ["", "a: b", "[x]", "x # c", "&a x", "|", "\"open", "a\nb"] {
+            assert!(doc.replace_scalar(id, bad).is_err(), "{bad:?}");
+        }
+        assert_eq!(doc.raw(), SAMPLE);
+        assert!(doc.replace_scalar(nid(&doc, "database"), "x").is_err());
+    }
+
+    #[test]
+    fn insert_key_follows_the_parents_layout() {
+        let path = |p: &str| query::parse_path(p).unwrap();
+        let src = "db:\n    user: admin  # who\n    pool:\n        size: 5\nlog: |\n  line\n";
+        let doc = Document::parse(src);
+        assert_eq!(
+            doc.insert_key(&path("db.password"), "\"X\"").unwrap(),
+            "db:\n    user: admin  # who\n    pool:\n        size: 5\n    password: \"X\"\nlog: |\n  line\n"
+        );
+        assert_eq!(
+            doc.insert_key(&path("db.tls.key"), "x").unwrap(),
+            src.replace("size: 5\n", "size: 5\n    tls:\n        key: x\n")
+        );
+        assert_eq!(
+            doc.insert_key(&path("api.token"), "x").unwrap(),
+            format!("{src}api:\n  token: x\n")
+        );
+
+        let items = "servers:\r\n  - host: a\r\n    port: 1\r\n  - host: b";
+        let doc = Document::parse(items);
+        assert_eq!(
+            doc.insert_key(&path("servers[1].user"), "u").unwrap(),
+            format!("{items}\r\n    user: u\r\n")
+        );
+        assert_eq!(
+            doc.insert_key(&path("servers[0].user"), "u").unwrap(),
+            items.replace("port: 1\r\n", "port: 1\r\n    user: u\r\n")
+        );
+
+        let doc = Document::parse("a: {b: 1}\nc: 2\n");
+        assert!(doc.insert_key(&path("a.x"), "1").is_err());
+        assert!(doc.insert_key(&path("c.x"), "1").is_err());
+        assert!(doc.insert_key(&path("c"), "1").is_err());
+        assert!(doc.insert_key(&path("d"), "a: b").is_err());
+        assert!(doc.insert_key(&path("e[0]"), "1").is_err());
+    }
 }
@@ -1,9 +1,11 @@
-//! A small cross-platform terminal file browser for picking a YAML file.
+//! A small cross-platform terminal file browser for picking a file to edit.
 //!
-//! Shows directories and `.yaml`/`.yml` files, lets the user enter/leave
-//! directories, and never panics on inaccessible directories.
+//! Shows directories and `.yaml`/`.yml`/`.properties`/`.json` files, lets the
+//! user enter/leave directories, and never panics on inaccessible directories.
 
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+
+use super::document::DocFormat;
 
 #[derive(Debug, Clone)]
 pub struct Entry {
@@ -66,7 +68,7 @@ impl FileBrowser {
                             is_dir: true,
                             is_parent: false,
                         });
-                    } else if is_yaml(&path) {
+                    } else if DocFormat::from_path(&path).is_some() {
                         files.push(Entry {
                             label: name,
                             path,
@@ -109,10 +111,3 @@ impl FileBrowser {
         }
     }
 }
-
-fn is_yaml(path: &Path) -> bool {
-    matches!(
-        path.extension().and_then(|e| e.to_str()),
-        Some("yaml") | Some("yml")
-    )
-}
@@ -0,0 +1,251 @@
+//! Undo history as a log of labelled operations.
+//!
+//! An [`Entry`] keeps only the span of text each change replaced (a
+//! [`Splice`]), so memory follows the size of the edits rather than the size
+//! of the file. A group collects every change made while it is open — a bulk
+//! run, a re-key — into one entry that is undone in one step. Entries before
+//! the cursor are applied; the ones after it can be redone.
+
+/// How many entries are kept; the oldest are dropped first.
+const LIMIT: usize = 100;
+
+/// One replaced span: `removed` at byte offset `at` became `inserted`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Splice {
+    at: usize,
+    removed: String,
+    inserted: String,
+}
+
+impl Splice {
+    /// The smallest span turning `before` into `after`, if they differ.
+    fn between(before: &str, after: &str) -> Option<Splice> {
+        if before == after {
+            return None;
+        }
+        let prefix: usize = before
+            .chars()
+            .zip(after.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        let room = before.len().min(after.len()) - prefix;
+        let mut suffix = 0;
+        for (a, b) in before.chars().rev().zip(after.chars().rev()) {
+            if a != b || suffix + a.len_utf8() > room {
+                break;
+            }
+            suffix += a.len_utf8();
+        }
+        Some(Splice {
+            at: prefix,
+            removed: before[prefix..before.len() - suffix].to_string(),
+            inserted: after[prefix..after.len() - suffix].to_string(),
+        })
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let end = self.at + self.removed.len();
+        [&text[..self.at], &self.inserted, &text[end..]].concat()
+    }
+
+    fn revert(&self, text: &str) -> String {
+        let end = self.at + self.inserted.len();
+        [&text[..self.at], &self.removed, &text[end..]].concat()
+    }
+}
+
+/// One undoable operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// What the operation did, e.g. `Encrypt db.password`.
+    pub label: String,
+    splices: Vec<Splice>,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<Entry>,
+    /// How many entries are applied.
+    cursor: usize,
+    group: Option<Vec<Splice>>,
+}
+
+impl History {
+    /// Record a change from `before` to `after`. Inside a group it joins the
+    /// group; otherwise it becomes its own entry and drops the redo tail.
+    pub fn record(&mut self, label: impl Into<String>, before: &str, after: &str) {
+        if let Some(splice) = Splice::between(before, after) {
+            self.add(label, splice);
+        }
+    }
+
+    /// Record a change whose span is already known, as returned by
+    /// [`Document::replace_scalar`](super::document::Document::replace_scalar):
+    /// `removed` at byte offset `at` became `inserted`. Unlike
+    /// [`record`](Self::record) this never looks at the rest of the text.
+    pub fn record_splice(
+        &mut self,
+        label: impl Into<String>,
+        (at, removed, inserted): (usize, String, String),
+    ) {
+        if removed != inserted {
+            self.add(
+                label,
+                Splice {
+                    at,
+                    removed,
+                    inserted,
+                },
+            );
+        }
+    }
+
+    fn add(&mut self, label: impl Into<String>, splice: Splice) {
+        match self.group.as_mut() {
+            Some(group) => group.push(splice),
+            None => self.push(Entry {
+                label: label.into(),
+                splices: vec![splice],
+            }),
+        }
+    }
+
+    fn push(&mut self, entry: Entry) {
+        self.entries.truncate(self.cursor);
+        self.entries.push(entry);
+        if self.entries.len() > LIMIT {
+            self.entries.remove(0);
+        }
+        self.cursor = self.entries.len();
+    }
+
+    /// Start collecting changes into one entry.
+    pub fn begin_group(&mut self) {
+        self.group.get_or_insert_with(Vec::new);
+    }
+
+    /// Close the group as one entry labelled from its number of changes.
+    /// A group that changed nothing leaves no entry.
+    pub fn end_group(&mut self, label: impl FnOnce(usize) -> String) {
+        if let Some(splices) = self.group.take().filter(|s| !s.is_empty()) {
+            let label = label(splices.len());
+            self.push(Entry { label, splices });
+        }
+    }
+
+    pub fn grouping(&self) -> bool {
+        self.group.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// All entries, oldest first.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// How many entries are applied; [`travel`](Self::travel) targets lie in
+    /// `0..=entries().len()`.
+    pub fn position(&self) -> usize {
+        self.cursor
+    }
+
+    /// Undo the last applied entry on `text`, returning the new text and the
+    /// entry's label.
+    pub fn undo(&mut self, text: &str) -> Option<(String, &str)> {
+        let target = self.cursor.checked_sub(1)?;
+        let text = self.travel(text, target)?;
+        Some((text, &self.entries[target].label))
+    }
+
+    /// Redo the next entry on `text`, returning the new text and its label.
+    pub fn redo(&mut self, text: &str) -> Option<(String, &str)> {
+        let label_at = self.cursor;
+        let text = self.travel(text, self.cursor + 1)?;
+        Some((text, &self.entries[label_at].label))
+    }
+
+    /// Undo or redo on `text` until `target` entries are applied.
+    pub fn travel(&mut self, text: &str, target: usize) -> Option<String> {
+        if target > self.entries.len() || target == self.cursor {
+            return None;
+        }
+        let mut text = text.to_string();
+        while self.cursor > target {
+            self.cursor -= 1;
+            for splice in self.entries[self.cursor].splices.iter().rev() {
+                text = splice.revert(&text);
+            }
+        }
+        while self.cursor < target {
+            for splice in &self.entries[self.cursor].splices {
+                text = splice.apply(&text);
+            }
+            self.cursor += 1;
+        }
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splices_hold_only_the_changed_span() {
+        let s = Splice::between("pw: héllo\n", "pw: \"![é]\"\n").unwrap();
+        assert_eq!(s.at, 4);
+        assert_eq!(
+            (s.removed.as_str(), s.inserted.as_str()),
+            ("héllo", "\"![é]\"")
+        );
+        assert_eq!(s.apply("pw: héllo\n"), "pw: \"![é]\"\n");
+        assert_eq!(s.revert("pw: \"![é]\"\n"), "pw: héllo\n");
+        let s = Splice::between("aaa", "aaaa").unwrap();
+        assert_eq!(s.revert(&s.apply("aaa")), "aaa");
+        assert!(Splice::between("x", "x").is_none());
+
+        // A span known up front is recorded as given.
+        let mut h = History::default();
+        h.record_splice("Encrypt pw", (4, "héllo".into(), "\"![é]\"".into()));
+        h.record_splice("Nothing", (0, "x".into(), "x".into()));
+        assert_eq!(h.entries().len(), 1);
+        assert_eq!(
+            h.undo("pw: \"![é]\"\n"),
+            Some(("pw: héllo\n".to_string(), "Encrypt pw"))
+        );
+    }
+
+    #[test]
+    fn groups_undo_as_one_step_and_travel_both_ways() {
+        let mut h = History::default();
+        h.record("Edit a", "a: 1\nb: 2\n", "a: 9\nb: 2\n");
+        h.begin_group();
+        h.record("", "a: 9\nb: 2\n", "a: X\nb: 2\n");
+        h.record("", "a: X\nb: 2\n", "a: X\nb: Y\n");
+        h.end_group(|n| format!("Bulk encrypt {n} values"));
+        h.begin_group();
+        h.end_group(|_| unreachable!());
+        let labels: Vec<&str> = h.entries().iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(labels, vec!["Edit a", "Bulk encrypt 2 values"]);
+
+        let (text, label) = h.undo("a: X\nb: Y\n").unwrap();
+        assert_eq!(
+            (text.as_str(), label),
+            ("a: 9\nb: 2\n", "Bulk encrypt 2 values")
+        );
+        let text = h.travel(&text, 0).unwrap();
+        assert_eq!(text, "a: 1\nb: 2\n");
+        assert!(h.undo(&text).is_none());
+        assert_eq!(h.travel(&text, 2).unwrap(), "a: X\nb: Y\n");
+
+        // A new change after undoing drops what could be redone.
+        h.travel("a: X\nb: Y\n", 1);
+        h.record("Edit b", "a: 9\nb: 2\n", "a: 9\nb: 3\n");
+        assert_eq!(h.entries().len(), 2);
+        assert!(h.redo("a: 9\nb: 3\n").is_none());
+    }
+}
@@ -16,16 +16,23 @@ fn ctrl(key: &KeyEvent, c: char) -> bool {
 fn perform_guard(g: Guard, state: &mut State, tx: &UnboundedSender<Action>) -> Result<()> {
     match g {
         Guard::Quit => send(tx, Action::Quit),
-        Guard::Open(path) => {
-            if let Err(e) = state.yaml.open_path(&path) {
-                state.yaml.report(e, true);
-            }
+        Guard::Close => {
+            state.yaml.close_buffer();
             Ok(())
         }
     }
 }
 
 pub fn handle_key(key: KeyEvent, state: &mut State, tx: &UnboundedSender<Action>) -> Result<()> {
+    // A save records the environment selected at the time.
+    state.yaml.save_env = state.selected_env().map(|e| e.name.clone());
+    let result = dispatch(key, state, tx);
+    // Any key that opened a file may have a project environment to select.
+    state.apply_linked_env();
+    result
+}
+
+fn dispatch(key: KeyEvent, state: &mut State, tx: &UnboundedSender<Action>) -> Result<()> {
     // 0. Unsaved-changes guard (Save / Discard / Cancel) takes precedence.
     if state.yaml.guard().is_some() {
         match key.code {
@@ -61,6 +68,186 @@ pub fn handle_key(key: KeyEvent, state: &mut State, tx: &UnboundedSender<Action>
         return Ok(());
     }
 
+    // 2b. Project panel.
+    if state.yaml.project_panel.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.close_project(),
+            KeyCode::Enter => state.yaml.project_activate(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.project_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.project_move(1),
+            KeyCode::Char('x') => state.yaml.project_cross_check(),
+            KeyCode::Char('n') => state.open_env_proposal(),
+            KeyCode::Char('c') => state.yaml.project_compare(),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    // 2c. Compare view.
+    if state.yaml.compare.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.close_compare(),
+            KeyCode::Enter => state.yaml.compare_activate(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.compare_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.compare_move(1),
+            KeyCode::Char('f') => state.yaml.compare_toggle_filter(),
+            KeyCode::Char('c') => state.yaml.compare_copy(),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    // 2d. Suspicious values.
+    if state.yaml.suspicious_panel.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.suspicious_panel = None,
+            KeyCode::Enter => state.yaml.suspicious_activate(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.suspicious_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.suspicious_move(1),
+            KeyCode::Char('E') => state.yaml_encrypt_suspicious(tx.clone()),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    // 2e. Merge with the file changed on disk.
+    if state.yaml.merge.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.merge_dismiss(),
+            KeyCode::Enter => state.yaml.merge_accept(),
+            KeyCode::Char(' ') => state.yaml.merge_toggle(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.merge_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.merge_move(1),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    // 2f. Undo history and the file's backups.
+    if state.yaml.history_view.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.history_view = None,
+            KeyCode::Enter => state.yaml.history_jump(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.history_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.history_move(1),
+            _ => {}
+        }
+        return Ok(());
+    }
+    if state.yaml.backup_view.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.backup_view = None,
+            KeyCode::Enter => state.yaml.backup_load(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.backup_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.backup_move(1),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    // 2g. Review of unsaved changes.
+    if state.yaml.review.is_some() {
+        if ctrl(&key, 's') {
+            state.yaml.close_review();
+            state.yaml.request_save();
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Esc => state.yaml.close_review(),
+            KeyCode::Enter => state.yaml.review_activate(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.review_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.review_move(1),
+            KeyCode::Char('r') => state.yaml.review_revert(),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    // 2h. Re-key (picking the target environment, then the report) and the
+    // verify report.
+    if state.yaml.rekey_picker.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.rekey_picker = None,
+            KeyCode::Enter => state.yaml_start_rekey(tx.clone()),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.rekey_picker_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.rekey_picker_move(1),
+            _ => {}
+        }
+        return Ok(());
+    }
+    if state.yaml.rekey_report.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.rekey_report = None,
+            KeyCode::Enter => state.yaml.rekey_report_activate(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.rekey_report_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.rekey_report_move(1),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    if state.yaml.verify_report.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.verify_report = None,
+            KeyCode::Enter => state.yaml.verify_report_activate(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.verify_report_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.verify_report_move(1),
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    // 2i. Go-to prompt and bookmarks.
+    if state.yaml.goto.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.goto = None,
+            KeyCode::Enter => state.yaml.goto_accept(),
+            KeyCode::Tab => state.yaml.goto_complete(),
+            KeyCode::Up => state.yaml.goto_move(-1),
+            KeyCode::Down => state.yaml.goto_move(1),
+            KeyCode::Backspace => goto_edit(state, |f| f.backspace()),
+            KeyCode::Left => goto_edit(state, |f| f.left()),
+            KeyCode::Right => goto_edit(state, |f| f.right()),
+            KeyCode::Char(c) => goto_edit(state, |f| f.insert(c)),
+            _ => {}
+        }
+        return Ok(());
+    }
+    if state.yaml.bookmark_view.is_some() {
+        match key.code {
+            KeyCode::Esc => state.yaml.bookmark_view = None,
+            KeyCode::Enter => state.yaml.bookmark_jump(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.bookmark_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.bookmark_move(1),
+            KeyCode::Char('x') => state.yaml.bookmark_delete(),
+            KeyCode::Char('a') => {
+                state.yaml.bookmark_view = None;
+                if let Err(e) = state.yaml.begin_bookmark() {
+                    state.yaml.report(e, true);
+                }
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    // 2j. Copy menu.
+    if let Some(menu) = &state.yaml.copy_menu {
+        match key.code {
+            KeyCode::Esc => state.yaml.copy_menu = None,
+            KeyCode::Enter => state.yaml.copy_selected(),
+            KeyCode::Up | KeyCode::Char('w') => state.yaml.copy_move(-1),
+            KeyCode::Down | KeyCode::Char('s') => state.yaml.copy_move(1),
+            KeyCode::Char(c) => {
+                if let Some(&item) = menu.items.iter().find(|i| i.key() == c) {
+                    state.yaml.copy(item);
+                }
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
     // 3. Scalar edit mode.
     if state.yaml.editing.is_some() {
         match key.code {
@@ -87,16 +274,11 @@ pub fn handle_key(key: KeyEvent, state: &mut State, tx: &UnboundedSender<Action>
         match key.code {
             KeyCode::Esc => state.yaml.clear_search(),
             KeyCode::Enter | KeyCode::Down | KeyCode::Up => state.yaml.confirm_search(),
-            KeyCode::Backspace => {
-                if let Some(f) = state.yaml.search_field() {
-                    f.backspace();
-                }
-            }
-            KeyCode::Char(c) => {
-                if let Some(f) = state.yaml.search_field() {
-                    f.insert(c);
-                }
-            }
+            KeyCode::Tab => state.yaml.toggle_search_mode(),
+            KeyCode::BackTab => state.yaml.cycle_search_filter(),
+            _ if ctrl(&key, 'f') => state.yaml.toggle_search_jump(),
+            KeyCode::Backspace => state.yaml.search_backspace(),
+            KeyCode::Char(c) => state.yaml.search_insert(c),
             _ => {}
         }
         return Ok(());
@@ -125,6 +307,14 @@ pub fn handle_key(key: KeyEvent, state: &mut State, tx: &UnboundedSender<Action>
         state.yaml.request_restore();
         return Ok(());
     }
+    if ctrl(&key, 'd') {
+        state.yaml.open_review();
+        return Ok(());
+    }
+    if ctrl(&key, 'w') {
+        state.yaml.request_close();
+        return Ok(());
+    }
     if ctrl(&key, 'z') {
         state.yaml.undo();
         return Ok(());
@@ -137,6 +327,9 @@ pub fn handle_key(key: KeyEvent, state: &mut State, tx: &UnboundedSender<Action>
         return send(tx, Action::Quit);
     }
 
+    // Conditions stay inside the arms: `guard_quit` changes state, and a key
+    // whose guard fails must not fall through to a later arm.
+    #[allow(clippy::collapsible_match)]
     match key.code {
         KeyCode::Char('q') => {
             if !state.yaml.guard_quit() {
@@ -170,16 +363,43 @@ pub fn handle_key(key: KeyEvent, state: &mut State, tx: &UnboundedSender<Action>
                 }
             }
         }
+        KeyCode::Char(' ') if state.yaml.focus == YamlFocus::Tree => state.yaml.toggle_mark(),
+        KeyCode::Char('M') => state.yaml.clear_marks(),
+        KeyCode::Char('u') => state.yaml.open_history(),
+        KeyCode::Char('B') => state.yaml.open_backups(),
+        KeyCode::Char('g') => state.yaml.open_goto(),
+        KeyCode::Char('\'') => state.yaml.open_bookmarks(),
+        KeyCode::Char('[') => state.yaml.switch_buffer(-1),
+        KeyCode::Char(']') => state.yaml.switch_buffer(1),
+        KeyCode::Char('y') => state.yaml.open_copy_menu(),
         KeyCode::Char('e') => state.yaml_begin_crypto(tx.clone(), Operation::Encrypt),
         KeyCode::Char('d') => state.yaml_begin_crypto(tx.clone(), Operation::Decrypt),
         KeyCode::Char('E') => state.yaml_start_bulk(tx.clone(), Operation::Encrypt),
         KeyCode::Char('D') => state.yaml_start_bulk(tx.clone(), Operation::Decrypt),
+        KeyCode::Char('K') => state.yaml_open_rekey(),
+        KeyCode::Char('i') => state.begin_detect(tx.clone()),
+        KeyCode::Char('v') => state.yaml_start_verify(tx.clone(), false),
+        KeyCode::Char('V') => state.yaml_start_verify(tx.clone(), true),
         KeyCode::Char('/') => {
             if state.yaml.focus == YamlFocus::Tree {
                 state.yaml.start_search();
             }
         }
+        KeyCode::Char('n') => state.yaml.search_step(1),
+        KeyCode::Char('N') => state.yaml.search_step(-1),
+        KeyCode::Char('#') => {
+            if state.yaml.focus == YamlFocus::Tree {
+                if let Err(e) = state.yaml.begin_comment_edit() {
+                    state.yaml.report(e, true);
+                }
+            }
+        }
         KeyCode::Char('r') => state.yaml.reveal = !state.yaml.reveal,
+        KeyCode::Char('o') => state.yaml_toggle_peek(tx.clone(), false),
+        KeyCode::Char('O') => state.yaml_toggle_peek(tx.clone(), true),
+        KeyCode::Char('p') => state.yaml.show_project(),
+        KeyCode::Char('!') => state.yaml.open_suspicious(),
+        KeyCode::Char('c') => state.yaml.open_compare_dialog(),
         // Add a new environment without leaving the YAML screen. The form is
         // modal and its keys are handled at the app level.
         KeyCode::Char('a') => state.open_add_form(),
@@ -243,6 +463,12 @@ fn edit(state: &mut State, f: impl FnOnce(&mut crate::text_field::TextField)) {
     }
 }
 
+fn goto_edit(state: &mut State, f: impl FnOnce(&mut crate::text_field::TextField)) {
+    if let Some(field) = state.yaml.goto_field() {
+        f(field);
+    }
+}
+
 fn send(tx: &UnboundedSender<Action>, action: Action) -> Result<()> {
     let _ = tx.send(action);
     Ok(())
@@ -0,0 +1,381 @@
+//! JSON backend for [`super::document::Document`].
+//!
+//! A small recursive-descent scanner turns objects and arrays into the same
+//! mapping/sequence nodes the YAML tree uses, and records the byte span of
+//! every scalar token — a string's span includes its quotes — so an edit
+//! replaces exactly that token and the file's formatting is never
+//! regenerated. Well-formedness is checked separately with serde_json.
+
+use super::document::{push_node, Document, Node, NodeKind, PathSeg, ScalarStyle};
+
+struct Scanner<'a> {
+    raw: &'a str,
+    bytes: &'a [u8],
+    i: usize,
+    nodes: Vec<Node>,
+}
+
+impl Scanner<'_> {
+    fn skip_ws(&mut self) {
+        while matches!(self.bytes.get(self.i), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+            self.i += 1;
+        }
+    }
+
+    /// Consume `b` (after whitespace) if it is next.
+    fn eat(&mut self, b: u8) -> bool {
+        self.skip_ws();
+        if self.bytes.get(self.i) == Some(&b) {
+            self.i += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The span of the string token starting at the current `"`.
+    fn string(&mut self) -> Option<(usize, usize)> {
+        let start = self.i;
+        let end = start + string_len(&self.raw[start..])?;
+        self.i = end;
+        Some((start, end))
+    }
+
+    /// The span of a number, `true`, `false` or `null` token.
+    fn bare(&mut self) -> Option<(usize, usize)> {
+        let start = self.i;
+        while self
+            .bytes
+            .get(self.i)
+            .is_some_and(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.'))
+        {
+            self.i += 1;
+        }
+        (self.i > start).then_some((start, self.i))
+    }
+
+    /// Children of the container starting at the current `{` or `[`.
+    fn children(&mut self, parent: Option<usize>, path: &[PathSeg]) -> Option<Vec<usize>> {
+        let object = self.bytes.get(self.i) == Some(&b'{');
+        let close = if object { b'}' } else { b']' };
+        self.i += 1;
+        let mut children = Vec::new();
+        if self.eat(close) {
+            return Some(children);
+        }
+        loop {
+            self.skip_ws();
+            let seg = if object {
+                if self.bytes.get(self.i) != Some(&b'"') {
+                    return None;
+                }
+                let (s, e) = self.string()?;
+                if !self.eat(b':') {
+                    return None;
+                }
+                PathSeg::Key(unquote(&self.raw[s..e]))
+            } else {
+                PathSeg::Index(children.len())
+            };
+            let label = match &seg {
+                PathSeg::Key(k) => k.clone(),
+                PathSeg::Index(i) => format!("[{i}]"),
+            };
+            let mut child_path = path.to_vec();
+            child_path.push(seg);
+            children.push(self.value(parent, child_path, label)?);
+            if self.eat(b',') {
+                continue;
+            }
+            return self.eat(close).then_some(children);
+        }
+    }
+
+    /// Parse the value at the cursor into a node at `path`.
+    fn value(&mut self, parent: Option<usize>, path: Vec<PathSeg>, label: String) -> Option<usize> {
+        self.skip_ws();
+        let id = push_node(&mut self.nodes, parent, path.clone(), label);
+        match self.bytes.get(self.i)? {
+            b'{' | b'[' => {
+                let kind = if self.bytes[self.i] == b'{' {
+                    NodeKind::Mapping
+                } else {
+                    NodeKind::Sequence
+                };
+                let children = self.children(Some(id), &path)?;
+                self.nodes[id].kind = kind;
+                self.nodes[id].children = children;
+            }
+            b'"' => {
+                let span = self.string()?;
+                self.nodes[id].value_span = Some(span);
+                self.nodes[id].style = ScalarStyle::DoubleQuoted;
+            }
+            _ => {
+                let span = self.bare()?;
+                self.nodes[id].value_span = Some(span);
+            }
+        }
+        self.nodes[id].line_end = self.i;
+        Some(id)
+    }
+}
+
+/// Length of the JSON string token at the start of `s` (which begins with
+/// `"`), including both quotes.
+fn string_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// The string a quoted JSON token stands for; the raw text if it is invalid.
+fn unquote(token: &str) -> String {
+    serde_json::from_str(token).unwrap_or_else(|_| token.to_string())
+}
+
+/// Parse `raw` into a node tree. The members (or items) of the top-level
+/// object (or array) are the roots; a top-level scalar has no nodes, and
+/// malformed input (never opened, since [`validate`] runs first) yields an
+/// empty tree.
+pub(super) fn parse(raw: &str) -> (Vec<Node>, Vec<usize>) {
+    let mut scanner = Scanner {
+        raw,
+        bytes: raw.as_bytes(),
+        i: if raw.starts_with('\u{feff}') { 3 } else { 0 },
+        nodes: Vec::new(),
+    };
+    scanner.skip_ws();
+    if !matches!(scanner.bytes.get(scanner.i), Some(b'{' | b'[')) {
+        return (Vec::new(), Vec::new());
+    }
+    match scanner.children(None, &[]) {
+        Some(roots) => (scanner.nodes, roots),
+        None => (Vec::new(), Vec::new()),
+    }
+}
+
+/// Check that `text` is well-formed JSON.
+pub(super) fn validate(text: &str) -> Result<(), String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    serde_json::from_str::<serde_json::Value>(text)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// The style of `token` if it is a single-line JSON scalar (string, number,
+/// `true`, `false` or `null`), which is all an in-place edit may write.
+pub(super) fn scalar_token_style(token: &str) -> Result<ScalarStyle, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(token).map_err(|_| format!("not a single JSON value: {token}"))?;
+    if token.contains(['\n', '\r'])
+        || token.trim() != token
+        || value.is_object()
+        || value.is_array()
+    {
+        return Err(format!("not a single JSON scalar: {token}"));
+    }
+    Ok(if value.is_string() {
+        ScalarStyle::DoubleQuoted
+    } else {
+        ScalarStyle::Plain
+    })
+}
+
+/// The logical value of a scalar token: a string unquoted, anything else as
+/// written.
+pub fn logical_value(token: &str) -> String {
+    if token.starts_with('"') {
+        unquote(token)
+    } else {
+        token.to_string()
+    }
+}
+
+/// Encode `value` as a JSON string token.
+pub fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("\"{value}\""))
+}
+
+/// Add `"key": token` after the parent object's last member, creating
+/// missing parent objects, in that object's layout: one member per line when
+/// it spans several lines, else inline.
+pub(super) fn insert(doc: &Document, path: &[PathSeg], token: &str) -> Result<String, String> {
+    scalar_token_style(token)?;
+    let (parent, keys) = doc.split_missing(path)?;
+    let raw = doc.raw();
+    let nodes = doc.nodes();
+    let children = match parent {
+        Some(id) => &nodes[id].children,
+        None => doc.roots(),
+    };
+    // The object's closing brace.
+    let close = match parent {
+        Some(id) => nodes[id].line_end.saturating_sub(1),
+        None => raw.trim_end().len().saturating_sub(1),
+    };
+    if raw.as_bytes().get(close) != Some(&b'}') {
+        return Err("entries can only be added to objects".to_string());
+    }
+    let indent_of = |at: usize| {
+        let start = raw[..at].rfind('\n').map_or(0, |i| i + 1);
+        raw[start..at].len() - raw[start..at].trim_start().len()
+    };
+    let (insert_at, multiline) = match children.last() {
+        Some(&last) => {
+            let end = nodes[last].line_end;
+            (end, raw[end..close].contains('\n'))
+        }
+        None => (close, false),
+    };
+    let close_indent = indent_of(close);
+    let indent = children
+        .iter()
+        .find_map(|&c| nodes[c].value_span)
+        .map_or(close_indent + 2, |(s, _)| indent_of(s));
+    let step = indent.saturating_sub(close_indent).max(1);
+    let eol = doc.line_ending().as_str();
+    let mut entry = String::new();
+    if !children.is_empty() {
+        entry.push(',');
+        if multiline {
+            entry.push_str(eol);
+            entry.push_str(&" ".repeat(indent));
+        } else {
+            entry.push(' ');
+        }
+    }
+    // `"a": {"b": token}`, spread over lines to match a multi-line parent.
+    let mut closers = Vec::new();
+    for (depth, key) in keys.iter().enumerate() {
+        entry.push_str(&quote(key));
+        entry.push_str(": ");
+        if depth + 1 == keys.len() {
+            entry.push_str(token);
+        } else if multiline {
+            let inner = indent + (depth + 1) * step;
+            entry.push('{');
+            entry.push_str(eol);
+            entry.push_str(&" ".repeat(inner));
+            closers.push(format!("{eol}{}}}", " ".repeat(inner - step)));
+        } else {
+            entry.push('{');
+            closers.push("}".to_string());
+        }
+    }
+    closers.reverse();
+    entry.extend(closers);
+    let mut text = raw.to_string();
+    text.insert_str(insert_at, &entry);
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::document::{DocFormat, Document};
+    use super::*;
+
+    const SAMPLE: &str = "{\n  \"db\": {\"user\": \"admin\", \"password\": \"s3cr\\u0065t\"},\n  \"ports\": [8080, 8443],\n  \"debug\": false,\n  \"note\": null\n}\n";
+
+    #[test]
+    fn tree_follows_objects_and_arrays() {
+        let doc = Document::parse_as(SAMPLE, DocFormat::Json);
+        let paths: Vec<String> = doc
+            .nodes()
+            .iter()
+            .map(|n| super::super::document::path_to_string(&n.path))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "db",
+                "db.user",
+                "db.password",
+                "ports",
+                "ports[0]",
+                "ports[1]",
+                "debug",
+                "note"
+            ]
+        );
+        let pw = doc
+            .find_by_path(&[PathSeg::Key("db".into()), PathSeg::Key("password".into())])
+            .unwrap();
+        assert_eq!(doc.logical_value(pw).unwrap(), "s3cret");
+        assert_eq!(doc.nodes()[pw].style, ScalarStyle::DoubleQuoted);
+        assert_eq!(doc.nodes()[5].style, ScalarStyle::Plain);
+        assert_eq!(doc.roots().len(), 4);
+    }
+
+    #[test]
+    fn replacing_a_string_keeps_the_layout() {
+        let mut doc = Document::parse_as(SAMPLE, DocFormat::Json);
+        let pw = doc
+            .find_by_path(&[PathSeg::Key("db".into()), PathSeg::Key("password".into())])
+            .unwrap();
+        let token = doc.encode_value("![CI\"PHER]");
+        doc.replace_scalar(pw, &token).unwrap();
+        assert_eq!(
+            doc.raw(),
+            SAMPLE.replace("\"s3cr\\u0065t\"", "\"![CI\\\"PHER]\"")
+        );
+        let reparsed = Document::parse_as(doc.raw(), DocFormat::Json);
+        assert_eq!(doc.nodes(), reparsed.nodes());
+        let port = doc.find_by_path(&[PathSeg::Key("ports".into()), PathSeg::Index(0)]);
+        assert!(doc.replace_scalar(port.unwrap(), "{}").is_err());
+        assert!(doc.replace_scalar(port.unwrap(), "1, 2").is_err());
+        // A longer token moves the closing brackets of its parents too.
+        doc.replace_scalar(port.unwrap(), "65535").unwrap();
+        let reparsed = Document::parse_as(doc.raw(), DocFormat::Json);
+        assert_eq!(doc.nodes(), reparsed.nodes());
+    }
+
+    #[test]
+    fn malformed_input_never_panics() {
+        for src in [
+            "",
+            "1",
+            "{",
+            "{\"a\": [1, {\"b\":",
+            "[\"unterminated",
+            "{\"a\" 1}",
+            "\u{feff}[]",
+        ] {
+            let doc = Document::parse_as(src, DocFormat::Json);
+            assert!(doc.nodes().len() <= 2, "{src:?}");
+        }
+        assert!(validate("{\"a\": 1,}").is_err());
+        assert!(validate("\u{feff}{\"a\": 1}").is_ok());
+    }
+
+    #[test]
+    fn inserted_members_match_the_objects_layout() {
+        let doc = Document::parse_as(SAMPLE, DocFormat::Json);
+        let path = |p: &str| super::super::query::parse_path(p).unwrap();
+        assert_eq!(
+            doc.insert_key(&path("db.host"), "\"x\"").unwrap(),
+            SAMPLE.replace("\"s3cr\\u0065t\"}", "\"s3cr\\u0065t\", \"host\": \"x\"}")
+        );
+        assert_eq!(
+            doc.insert_key(&path("api.auth.token"), "1").unwrap(),
+            SAMPLE.replace(
+                "\"note\": null\n",
+                "\"note\": null,\n  \"api\": {\n    \"auth\": {\n      \"token\": 1\n    }\n  }\n"
+            )
+        );
+        let empty = Document::parse_as("{\"a\": {}}", DocFormat::Json);
+        assert_eq!(
+            empty.insert_key(&path("a.b"), "true").unwrap(),
+            "{\"a\": {\"b\": true}}"
+        );
+        assert!(doc.insert_key(&path("ports.x"), "1").is_err());
+        assert!(doc.insert_key(&path("debug.x"), "1").is_err());
+    }
+}
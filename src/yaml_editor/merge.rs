@@ -0,0 +1,202 @@
+//! Three-way merge of the working document with a file changed on disk.
+//!
+//! The base is the text the file had when it was opened or last saved. Each
+//! value changed in the editor since then (by logical path) is reapplied onto
+//! the disk text where the disk still has the base value. A path changed on
+//! both sides, or present on one side only, is a conflict and listed for the
+//! user to decide. Comment and layout edits are not carried over.
+
+use super::document::{self, Document, PathSeg};
+use super::review::{quote, scalar_values};
+
+/// What became of one changed path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The disk still has the base value; ours applies cleanly.
+    Clean,
+    /// Changed here and, differently, on disk.
+    BothChanged,
+    /// Changed here, removed on disk.
+    RemovedOnDisk,
+    /// Added here; the disk has no such key.
+    AddedHere,
+    /// Removed here; the disk still has it.
+    RemovedHere,
+    /// The disk's value is not editable in place (flow or block style).
+    NotEditable,
+}
+
+/// One path where the working document differs from the base.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeRow {
+    pub path: Vec<PathSeg>,
+    pub outcome: Outcome,
+    /// The logical values in the working document and on disk.
+    pub mine: Option<String>,
+    pub disk: Option<String>,
+    /// Whether the merge writes our value; the disk value stays otherwise.
+    pub keep_mine: bool,
+}
+
+impl MergeRow {
+    /// Whether the user can choose between our value and the disk's.
+    pub fn choosable(&self) -> bool {
+        matches!(self.outcome, Outcome::Clean | Outcome::BothChanged)
+    }
+
+    pub fn is_conflict(&self) -> bool {
+        self.outcome != Outcome::Clean
+    }
+
+    /// `changed on disk too: yours "a", disk "b"` and the like. Ciphertext
+    /// is never shown.
+    pub fn summary(&self) -> String {
+        let shown = |v: &Option<String>| match v {
+            Some(v) if document::is_wrapped(v) => "encrypted".to_string(),
+            Some(v) => quote(v),
+            None => "none".to_string(),
+        };
+        match self.outcome {
+            Outcome::Clean => format!("yours applies: {}", shown(&self.mine)),
+            Outcome::BothChanged => format!(
+                "changed on disk too: yours {}, disk {}",
+                shown(&self.mine),
+                shown(&self.disk)
+            ),
+            Outcome::RemovedOnDisk => {
+                format!("removed on disk; yours {} is dropped", shown(&self.mine))
+            }
+            Outcome::AddedHere => {
+                format!("added here only; {} is dropped", shown(&self.mine))
+            }
+            Outcome::RemovedHere => {
+                format!("removed here; the disk keeps {}", shown(&self.disk))
+            }
+            Outcome::NotEditable => format!(
+                "disk value {} cannot be replaced in place",
+                shown(&self.disk)
+            ),
+        }
+    }
+}
+
+/// Every path `working` changed since `base`, with what merging it onto
+/// `disk` would do. Paths the disk already agrees on are left out.
+pub fn plan(base: &str, working: &Document, disk: &str) -> Vec<MergeRow> {
+    let format = working.format();
+    let base_doc = Document::parse_as(base, format);
+    let disk_doc = Document::parse_as(disk, format);
+    let (base_vals, work_vals, disk_vals) = (
+        scalar_values(&base_doc),
+        scalar_values(working),
+        scalar_values(&disk_doc),
+    );
+    let editable_on_disk = |path: &[PathSeg]| {
+        disk_doc
+            .find_by_path(path)
+            .and_then(|id| disk_doc.node(id))
+            .is_some_and(|n| n.is_editable_scalar())
+    };
+    // Paths in the working document's order, then the ones removed here.
+    let paths = working
+        .nodes()
+        .iter()
+        .chain(base_doc.nodes())
+        .map(|n| &n.path)
+        .filter(|p| work_vals.get(*p) != base_vals.get(*p));
+    let mut rows: Vec<MergeRow> = Vec::new();
+    for path in paths {
+        if rows.iter().any(|r| &r.path == path) {
+            continue;
+        }
+        let (base, mine, disk) = (
+            base_vals.get(path),
+            work_vals.get(path),
+            disk_vals.get(path),
+        );
+        if mine == disk {
+            continue;
+        }
+        let outcome = match (mine, disk) {
+            (None, _) => Outcome::RemovedHere,
+            (Some(_), None) if base.is_some() => Outcome::RemovedOnDisk,
+            (Some(_), None) => Outcome::AddedHere,
+            (Some(_), Some(_)) if !editable_on_disk(path) => Outcome::NotEditable,
+            (Some(_), Some(d)) if Some(d) == base => Outcome::Clean,
+            (Some(_), Some(_)) => Outcome::BothChanged,
+        };
+        rows.push(MergeRow {
+            path: path.clone(),
+            outcome,
+            mine: mine.cloned(),
+            disk: disk.cloned(),
+            keep_mine: outcome == Outcome::Clean,
+        });
+    }
+    rows
+}
+
+/// The disk text with our value token written at every row that keeps it.
+pub fn apply(working: &Document, disk: &str, rows: &[MergeRow]) -> Result<String, String> {
+    let mut merged = Document::parse_as(disk, working.format());
+    for row in rows.iter().filter(|r| r.keep_mine && r.choosable()) {
+        let name = document::path_to_string(&row.path);
+        let token = working
+            .find_by_path(&row.path)
+            .and_then(|id| working.value_source(id))
+            .ok_or_else(|| format!("{name}: no longer in the editor"))?
+            .to_string();
+        let id = merged
+            .find_by_path(&row.path)
+            .ok_or_else(|| format!("{name}: not in the file on disk"))?;
+        merged
+            .replace_scalar(id, &token)
+            .map_err(|e| format!("{name}: {e}"))?;
+    }
+    Ok(merged.raw().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "db:\n  user: admin\n  password: hunter2\n  port: 5432\nmode: a\nold: 1\n";
+
+    #[test]
+    fn reapplies_our_changes_and_lists_conflicts() {
+        let working = Document::parse(
+            "db:\n  user: root\n  password: \"![c2VjcmV0]\"\n  port: 6000\nmode: b\nnew: 2\n",
+        );
+        // On disk: a comment, another port, `mode` removed.
+        let disk = "# tuned\ndb:\n  user: admin\n  password: hunter2\n  port: 7000\nold: 1\n";
+        let rows = plan(BASE, &working, disk);
+        let outcomes: Vec<(String, Outcome)> = rows
+            .iter()
+            .map(|r| (document::path_to_string(&r.path), r.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("db.user".to_string(), Outcome::Clean),
+                ("db.password".to_string(), Outcome::Clean),
+                ("db.port".to_string(), Outcome::BothChanged),
+                ("mode".to_string(), Outcome::RemovedOnDisk),
+                ("new".to_string(), Outcome::AddedHere),
+                ("old".to_string(), Outcome::RemovedHere),
+            ]
+        );
+        assert_eq!(
+            rows[2].summary(),
+            "changed on disk too: yours \"6000\", disk \"7000\""
+        );
+        assert_eq!(rows[1].summary(), "yours applies: encrypted");
+
+        assert_eq!(
+            apply(&working, disk, &rows).unwrap(),
+            "# tuned\ndb:\n  user: root\n  password: \"![c2VjcmV0]\"\n  port: 7000\nold: 1\n"
+        );
+        let mut rows = rows;
+        rows[2].keep_mine = true;
+        assert!(apply(&working, disk, &rows).unwrap().contains("port: 6000"));
+    }
+}
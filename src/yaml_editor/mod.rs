@@ -1,6 +1,19 @@
-//! Source-preserving YAML editing for the YAML screen.
+//! Source-preserving editing of YAML, `.properties` and JSON files for the
+//! YAML screen.
 
+pub mod backup;
+pub mod bookmarks;
+pub mod compare;
 pub mod document;
 pub mod file_browser;
+pub mod history;
 pub mod input;
+pub mod json;
+pub mod merge;
+pub mod per_file;
+pub mod properties;
+pub mod query;
+pub mod rekey;
+pub mod review;
 pub mod state;
+pub mod suspicious;
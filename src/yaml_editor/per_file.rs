@@ -0,0 +1,127 @@
+//! Values kept per file across sessions, such as bookmarks or the environment
+//! a config file is linked to.
+//!
+//! A [`PerFile`] map is stored as YAML in the lazyprop home, keyed by each
+//! file's canonical path, and saved after every change. A store that cannot
+//! be read is reported and left alone: changes then last for the session only.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[derive(Debug, Clone)]
+pub struct PerFile<V> {
+    /// Where the map is saved; `None` keeps it for the session only.
+    store: Option<PathBuf>,
+    /// Why the saved map could not be read. The file is then left alone.
+    error: Option<String>,
+    by_file: BTreeMap<String, V>,
+}
+
+impl<V> Default for PerFile<V> {
+    fn default() -> Self {
+        Self {
+            store: None,
+            error: None,
+            by_file: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V: Serialize + DeserializeOwned> PerFile<V> {
+    /// Read the map saved at `store`; a missing file starts empty.
+    pub fn load(store: PathBuf) -> Self {
+        let read = match std::fs::read_to_string(&store) {
+            Ok(text) => serde_yaml::from_str(&text).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.to_string()),
+        };
+        let (by_file, error) = match read {
+            Ok(by_file) => (by_file, None),
+            Err(e) => (
+                BTreeMap::new(),
+                Some(format!("Could not read {}: {e}", store.display())),
+            ),
+        };
+        Self {
+            store: Some(store),
+            error,
+            by_file,
+        }
+    }
+
+    /// Why the saved map could not be read, if it couldn't.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn get(&self, file: &Path) -> Option<&V> {
+        self.by_file.get(&key(file))
+    }
+
+    /// Set `file`'s value and save.
+    pub fn insert(&mut self, file: &Path, value: V) -> Result<(), String> {
+        self.by_file.insert(key(file), value);
+        self.save()
+    }
+
+    /// Drop `file`'s value and save.
+    pub fn remove(&mut self, file: &Path) -> Result<(), String> {
+        self.by_file.remove(&key(file));
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        if let Some(e) = &self.error {
+            return Err(format!("{e}; not overwriting it"));
+        }
+        if let Some(dir) = store.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let text = serde_yaml::to_string(&self.by_file).map_err(|e| e.to_string())?;
+        super::state::atomic_write(store, &text)
+    }
+}
+
+fn key(file: &Path) -> String {
+    std::fs::canonicalize(file)
+        .unwrap_or_else(|_| file.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mule::test_support::TempDir;
+
+    #[test]
+    fn values_survive_a_reload_and_a_broken_store_is_never_overwritten() {
+        let dir = TempDir::new();
+        let store = dir.0.join("home/links.yaml");
+        let (a, b) = (Path::new("/configs/a.yaml"), Path::new("/configs/b.yaml"));
+        let mut map = PerFile::load(store.clone());
+        map.insert(a, "dev".to_string()).unwrap();
+        map.insert(b, "prod".to_string()).unwrap();
+        map.remove(b).unwrap();
+        let map = PerFile::<String>::load(store.clone());
+        assert_eq!(map.get(a).map(String::as_str), Some("dev"));
+        assert_eq!(map.get(b), None);
+
+        // Session-only maps never touch the disk.
+        let mut session = PerFile::default();
+        session.insert(a, "x".to_string()).unwrap();
+        assert_eq!(session.get(a).map(String::as_str), Some("x"));
+
+        std::fs::write(&store, "[broken").unwrap();
+        let mut map = PerFile::<String>::load(store.clone());
+        assert!(map.error().is_some());
+        assert!(map.insert(a, "other".to_string()).is_err());
+        assert_eq!(std::fs::read_to_string(&store).unwrap(), "[broken");
+    }
+}
@@ -0,0 +1,337 @@
+//! Java `.properties` backend for [`super::document::Document`].
+//!
+//! Entries become a flat list of scalar nodes, one per key, each remembering
+//! the byte span of its raw value — continuation lines and escapes included —
+//! so an edit replaces exactly that value and nothing else. Follows
+//! `java.util.Properties::load`: `=`, `:` or whitespace separators, `#`/`!`
+//! comment lines, `\` line continuations and `\t \n \r \f \uXXXX` escapes.
+
+use super::document::{push_node, Document, Node, PathSeg, ScalarStyle};
+
+/// Whitespace as `Properties::load` defines it.
+fn is_ws(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\x0c')
+}
+
+/// Length of the line break at `i`, if there is one.
+fn newline_at(bytes: &[u8], i: usize) -> Option<usize> {
+    match bytes.get(i) {
+        Some(b'\n') => Some(1),
+        Some(b'\r') if bytes.get(i + 1) == Some(&b'\n') => Some(2),
+        Some(b'\r') => Some(1),
+        _ => None,
+    }
+}
+
+/// Skip whitespace starting at `i`.
+fn skip_ws(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && is_ws(bytes[i]) {
+        i += 1;
+    }
+    i
+}
+
+/// Scan from `i` to the end of the logical line (or, with `stop_at_sep`, to
+/// the first unescaped key separator), stepping over escapes and `\`
+/// continuations. Returns the stop position.
+fn scan(bytes: &[u8], mut i: usize, stop_at_sep: bool) -> usize {
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => match newline_at(bytes, i + 1) {
+                Some(n) => i = skip_ws(bytes, i + 1 + n),
+                None => i += 2,
+            },
+            b'\n' | b'\r' => break,
+            b'=' | b':' if stop_at_sep => break,
+            b if stop_at_sep && is_ws(b) => break,
+            _ => i += 1,
+        }
+    }
+    i.min(bytes.len())
+}
+
+/// Parse `raw` into one scalar node per entry. Leading comment lines attach to
+/// the entry below them, as in YAML.
+pub(super) fn parse(raw: &str) -> (Vec<Node>, Vec<usize>) {
+    let bytes = raw.as_bytes();
+    let mut nodes = Vec::new();
+    let mut roots = Vec::new();
+    let mut leading = Vec::new();
+    let mut i = if raw.starts_with('\u{feff}') { 3 } else { 0 };
+    while i < bytes.len() {
+        let start = skip_ws(bytes, i);
+        let line_end = start + raw[start..].find(['\n', '\r']).unwrap_or(raw.len() - start);
+        match bytes.get(start) {
+            None | Some(b'\n' | b'\r') => leading.clear(),
+            Some(b'#' | b'!') => {
+                let text = &raw[start + 1..line_end];
+                leading.push(
+                    text.strip_prefix(' ')
+                        .unwrap_or(text)
+                        .trim_end()
+                        .to_string(),
+                );
+            }
+            Some(_) => {
+                let key_end = scan(bytes, start, true);
+                let mut v = skip_ws(bytes, key_end);
+                if matches!(bytes.get(v), Some(b'=' | b':')) {
+                    v = skip_ws(bytes, v + 1);
+                }
+                let value_start = v;
+                let value_end = scan(bytes, value_start, false);
+                let key = unescape(&raw[start..key_end]);
+                let id = push_node(&mut nodes, None, vec![PathSeg::Key(key.clone())], key);
+                let node = &mut nodes[id];
+                node.value_span = Some((value_start, value_end));
+                node.style = ScalarStyle::Plain;
+                node.leading_comments = std::mem::take(&mut leading);
+                node.line_end = value_end;
+                roots.push(id);
+                i = value_end + newline_at(bytes, value_end).unwrap_or(0);
+                continue;
+            }
+        }
+        i = line_end + newline_at(bytes, line_end).unwrap_or(0);
+    }
+    (nodes, roots)
+}
+
+/// Check for what `Properties::load` rejects: a malformed `\uXXXX` escape.
+pub(super) fn validate(text: &str) -> Result<(), String> {
+    let bytes = text.as_bytes();
+    let mut line = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => line += 1,
+            b'\\' if bytes.get(i + 1) == Some(&b'u') => {
+                let hex = text.get(i + 2..i + 6).unwrap_or("");
+                if hex.len() != 4 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(format!("line {line}: malformed \\uxxxx escape"));
+                }
+                i += 6;
+                continue;
+            }
+            b'\\' => i += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Whether `token` can replace a value in place: it must stay on one line,
+/// not start with whitespace (which `load` would strip) and not end in a
+/// continuation backslash.
+pub(super) fn check_token(token: &str) -> Result<(), String> {
+    let trailing_backslashes = token.len() - token.trim_end_matches('\\').len();
+    if token.contains(['\n', '\r'])
+        || token.starts_with([' ', '\t', '\x0c'])
+        || trailing_backslashes % 2 == 1
+    {
+        return Err(format!("not a single property value: {token}"));
+    }
+    validate(token)
+}
+
+/// The logical value of a raw key or value: escapes decoded, continuations
+/// joined.
+pub fn unescape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\x0c'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(ch) => out.push(ch),
+                    None => out.push_str(&hex),
+                }
+            }
+            Some(nl @ ('\n' | '\r')) => {
+                if nl == '\r' && chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                while chars
+                    .peek()
+                    .is_some_and(|c| matches!(c, ' ' | '\t' | '\x0c'))
+                {
+                    chars.next();
+                }
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Encode `value` as a raw property value that reads back as `value`.
+pub fn escape_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\x0c' => out.push_str("\\f"),
+            ' ' if i == 0 => out.push_str("\\ "),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Add `key=token` after the last entry sharing the longest dotted prefix
+/// with `key` (so `db.port` lands beside `db.user`), else at the end.
+pub(super) fn insert(doc: &Document, path: &[PathSeg], token: &str) -> Result<String, String> {
+    let [PathSeg::Key(key)] = path else {
+        return Err("a property key is a single name".to_string());
+    };
+    check_token(token)?;
+    let raw = doc.raw();
+    let shared = |other: &str| {
+        key.split('.')
+            .zip(other.split('.'))
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+    let insert_at = doc
+        .nodes()
+        .iter()
+        .map(|n| (shared(&n.label), n.line_end))
+        .filter(|&(shared, _)| shared > 0)
+        .max_by_key(|&(shared, _)| shared)
+        .map_or(raw.len(), |(_, end)| {
+            end + newline_at(raw.as_bytes(), end).unwrap_or(0)
+        });
+    let eol = doc.line_ending().as_str();
+    let mut entry = String::new();
+    if insert_at > 0 && !raw[..insert_at].ends_with(['\n', '\r']) {
+        entry.push_str(eol);
+    }
+    entry.push_str(&escape_key(key));
+    entry.push('=');
+    entry.push_str(token);
+    entry.push_str(eol);
+    let mut text = raw.to_string();
+    text.insert_str(insert_at, &entry);
+    Ok(text)
+}
+
+/// Encode `key` so `load` reads it back whole: separators and spaces
+/// escaped, and a leading `#` or `!` kept from starting a comment.
+fn escape_key(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    for (i, c) in key.chars().enumerate() {
+        match c {
+            '=' | ':' | ' ' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '#' | '!' if i == 0 => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push_str(&escape_value(&c.to_string())),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::document::{DocFormat, Document};
+    use super::*;
+
+    const SAMPLE: &str = "\
+# Database
+db.user = admin
+db.password:secret
+db.url jdbc:x\\
+    ://host
+empty=
+! legacy
+path=C:\\\\temp\\u0041
+";
+
+    fn value(doc: &Document, key: &str) -> String {
+        let id = doc
+            .find_by_path(&[PathSeg::Key(key.into())])
+            .unwrap_or_else(|| panic!("{key} missing"));
+        doc.logical_value(id).unwrap()
+    }
+
+    #[test]
+    fn parses_separators_continuations_and_escapes() {
+        let doc = Document::parse_as(SAMPLE, DocFormat::Properties);
+        let labels: Vec<&str> = doc.nodes().iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["db.user", "db.password", "db.url", "empty", "path"]
+        );
+        assert_eq!(value(&doc, "db.user"), "admin");
+        assert_eq!(value(&doc, "db.password"), "secret");
+        assert_eq!(value(&doc, "db.url"), "jdbc:x://host");
+        assert_eq!(value(&doc, "empty"), "");
+        assert_eq!(value(&doc, "path"), "C:\\tempA");
+        assert_eq!(doc.nodes()[0].leading_comments, vec!["Database"]);
+        assert_eq!(doc.nodes()[4].leading_comments, vec!["legacy"]);
+    }
+
+    #[test]
+    fn replacing_a_value_changes_only_its_bytes() {
+        let src = SAMPLE.replace('\n', "\r\n");
+        let mut doc = Document::parse_as(&src, DocFormat::Properties);
+        let id = doc.find_by_path(&[PathSeg::Key("db.url".into())]).unwrap();
+        let token = doc.encode_value("![CIPHER]");
+        doc.replace_scalar(id, &token).unwrap();
+        assert_eq!(
+            doc.raw(),
+            src.replace("jdbc:x\\\r\n    ://host", "![CIPHER]")
+        );
+        let reparsed = Document::parse_as(doc.raw(), DocFormat::Properties);
+        assert_eq!(doc.nodes(), reparsed.nodes());
+    }
+
+    #[test]
+    fn escape_roundtrips() {
+        for v in ["", " lead", "a\\b", "line\nbreak\ttab", "ünï", "x=y:z #!"] {
+            assert_eq!(unescape(&escape_value(v)), v, "{v:?}");
+            check_token(&escape_value(v)).unwrap();
+        }
+        assert!(check_token("ends\\").is_err());
+        assert!(check_token(" lead").is_err());
+        assert!(validate("a=\\u12").is_err());
+        assert!(validate("a=\\\\u12").is_ok());
+    }
+
+    #[test]
+    fn inserted_keys_join_their_group() {
+        let src = "db.user=admin\r\ndb.url=x\r\n\r\nlog.level=info";
+        let doc = Document::parse_as(src, DocFormat::Properties);
+        let key = |k: &str| [PathSeg::Key(k.into())];
+        assert_eq!(
+            doc.insert_key(&key("db.password"), "CHANGE_ME").unwrap(),
+            src.replace("db.url=x\r\n", "db.url=x\r\ndb.password=CHANGE_ME\r\n")
+        );
+        assert_eq!(
+            doc.insert_key(&key("api key"), "v").unwrap(),
+            format!("{src}\r\napi\\ key=v\r\n")
+        );
+        let inserted = doc.insert_key(&key("#a=b:c"), "v").unwrap();
+        let reparsed = Document::parse_as(&inserted, DocFormat::Properties);
+        assert!(reparsed.find_by_path(&key("#a=b:c")).is_some());
+        assert!(doc.insert_key(&key("db.user"), "v").is_err());
+    }
+}
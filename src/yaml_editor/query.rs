@@ -0,0 +1,379 @@
+//! A small path language for addressing and selecting document nodes.
+//!
+//! Literal paths use exactly the syntax [`path_to_string`] renders — dotted
+//! keys and bracketed indexes (`servers[0].host`) — so a rendered path always
+//! parses back to the same segments. Queries add patterns on top:
+//!
+//! * `*` matches any single key or index (`db.*.password`);
+//! * `**` matches any number of segments, including none (`**.password`);
+//! * `[a:b]` matches indexes `a..b` with either bound optional, `[*]` any index;
+//! * `/re/` matches a key by regular expression, anchored to the whole key
+//!   (`db./pass(word)?/`).
+//!
+//! Keys that would otherwise be ambiguous — containing `.`, `[`, `]`, `"`, `\`,
+//! a leading `*` or `/`, or surrounding whitespace — are written double-quoted
+//! with `\"` / `\\` escapes (`"a.b".c`).
+//!
+//! [`path_to_string`]: super::document::path_to_string
+
+use regex::Regex;
+
+use super::document::PathSeg;
+
+/// One segment of a [`PathQuery`].
+#[derive(Debug, Clone)]
+pub enum QuerySeg {
+    /// An exact key.
+    Key(String),
+    /// An exact sequence index.
+    Index(usize),
+    /// `*`: any single key or index.
+    Any,
+    /// `[a:b]` / `[*]`: an index in `start..end` (both bounds optional).
+    Range(Option<usize>, Option<usize>),
+    /// `/re/`: a key matching the (anchored) regular expression.
+    KeyRegex(Regex),
+    /// `**`: zero or more segments.
+    Recursive,
+}
+
+impl QuerySeg {
+    fn matches(&self, seg: &PathSeg) -> bool {
+        match (self, seg) {
+            (QuerySeg::Key(k), PathSeg::Key(p)) => k == p,
+            (QuerySeg::Index(i), PathSeg::Index(p)) => i == p,
+            (QuerySeg::Any, _) => true,
+            (QuerySeg::Range(lo, hi), PathSeg::Index(p)) => {
+                lo.is_none_or(|lo| *p >= lo) && hi.is_none_or(|hi| *p < hi)
+            }
+            (QuerySeg::KeyRegex(re), PathSeg::Key(p)) => re.is_match(p),
+            _ => false,
+        }
+    }
+}
+
+/// A parsed path pattern. See the module docs for the syntax.
+#[derive(Debug, Clone)]
+pub struct PathQuery {
+    segs: Vec<QuerySeg>,
+}
+
+impl PathQuery {
+    pub fn parse(input: &str) -> Result<PathQuery, String> {
+        Ok(PathQuery {
+            segs: parse_segments(input, true)?,
+        })
+    }
+
+    pub fn segments(&self) -> &[QuerySeg] {
+        &self.segs
+    }
+
+    /// Whether the query contains no patterns, i.e. addresses a single path.
+    pub fn is_literal(&self) -> bool {
+        self.segs
+            .iter()
+            .all(|s| matches!(s, QuerySeg::Key(_) | QuerySeg::Index(_)))
+    }
+
+    /// Whether `path` is selected by this query.
+    pub fn matches(&self, path: &[PathSeg]) -> bool {
+        match_from(&self.segs, path)
+    }
+}
+
+fn match_from(pattern: &[QuerySeg], path: &[PathSeg]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((QuerySeg::Recursive, rest)) => (0..=path.len()).any(|i| match_from(rest, &path[i..])),
+        Some((seg, rest)) => match path.split_first() {
+            Some((head, tail)) => seg.matches(head) && match_from(rest, tail),
+            None => false,
+        },
+    }
+}
+
+/// Parse a literal path (no patterns) back into segments. The inverse of
+/// [`path_to_string`](super::document::path_to_string).
+pub fn parse_path(input: &str) -> Result<Vec<PathSeg>, String> {
+    parse_segments(input, false)?
+        .into_iter()
+        .map(|s| match s {
+            QuerySeg::Key(k) => Ok(PathSeg::Key(k)),
+            QuerySeg::Index(i) => Ok(PathSeg::Index(i)),
+            _ => Err("patterns are not allowed in a path".to_string()),
+        })
+        .collect()
+}
+
+/// Whether a key must be double-quoted to render unambiguously.
+pub fn key_needs_quoting(key: &str) -> bool {
+    key.is_empty()
+        || key != key.trim()
+        || key.starts_with(['*', '/'])
+        || key.contains(['.', '[', ']', '"', '\\'])
+}
+
+/// Render a key for a path string, quoting it only when required.
+pub fn quote_key(key: &str) -> String {
+    if !key_needs_quoting(key) {
+        return key.to_string();
+    }
+    let mut out = String::with_capacity(key.len() + 2);
+    out.push('"');
+    for c in key.chars() {
+        if matches!(c, '"' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+fn parse_segments(input: &str, patterns: bool) -> Result<Vec<QuerySeg>, String> {
+    let chars: Vec<char> = input.trim().chars().collect();
+    let mut segs = Vec::new();
+    let mut i = 0;
+    if chars.is_empty() {
+        return Err("empty path".to_string());
+    }
+    // A path may start with an index (a root-level sequence).
+    let mut expect_key = chars[0] != '[';
+    while i < chars.len() {
+        if expect_key {
+            let (seg, next) = parse_key(&chars, i, patterns)?;
+            segs.push(seg);
+            i = next;
+        }
+        while i < chars.len() && chars[i] == '[' {
+            let (seg, next) = parse_index(&chars, i, patterns)?;
+            segs.push(seg);
+            i = next;
+        }
+        if i < chars.len() {
+            if chars[i] != '.' {
+                return Err(format!("unexpected '{}' at position {}", chars[i], i + 1));
+            }
+            i += 1;
+            if i == chars.len() {
+                return Err("path ends with '.'".to_string());
+            }
+        }
+        expect_key = true;
+    }
+    Ok(segs)
+}
+
+/// Parse a key segment starting at `i`; returns it and the index after it.
+fn parse_key(chars: &[char], i: usize, patterns: bool) -> Result<(QuerySeg, usize), String> {
+    match chars[i] {
+        '"' => {
+            let (text, next) = delimited(chars, i, '"')?;
+            Ok((QuerySeg::Key(text), next))
+        }
+        '/' if patterns => {
+            let (text, next) = delimited(chars, i, '/')?;
+            let re = Regex::new(&format!("^(?:{text})$"))
+                .map_err(|e| format!("invalid key regex /{text}/: {e}"))?;
+            Ok((QuerySeg::KeyRegex(re), next))
+        }
+        '*' if patterns => {
+            if chars.get(i + 1) == Some(&'*') {
+                Ok((QuerySeg::Recursive, i + 2))
+            } else {
+                Ok((QuerySeg::Any, i + 1))
+            }
+        }
+        _ => {
+            let mut end = i;
+            while end < chars.len() && !matches!(chars[end], '.' | '[') {
+                if matches!(chars[end], ']' | '"' | '\\') {
+                    return Err(format!(
+                        "unexpected '{}' in key (quote keys containing it)",
+                        chars[end]
+                    ));
+                }
+                end += 1;
+            }
+            if end == i {
+                return Err(format!("empty key at position {}", i + 1));
+            }
+            let key: String = chars[i..end].iter().collect();
+            if !patterns && key_needs_quoting(&key) {
+                return Err(format!("key '{key}' must be quoted"));
+            }
+            Ok((QuerySeg::Key(key), end))
+        }
+    }
+}
+
+/// Read text between `delim`s starting at `i` (which holds the opening
+/// delimiter), honouring backslash escapes of the delimiter and `\\`.
+fn delimited(chars: &[char], i: usize, delim: char) -> Result<(String, usize), String> {
+    let mut out = String::new();
+    let mut j = i + 1;
+    while j < chars.len() {
+        match chars[j] {
+            '\\' if matches!(chars.get(j + 1), Some(&c) if c == delim || c == '\\') => {
+                // In a regex keep `\\` intact; only the delimiter is unescaped.
+                if delim == '/' && chars[j + 1] == '\\' {
+                    out.push('\\');
+                }
+                out.push(chars[j + 1]);
+                j += 2;
+            }
+            c if c == delim => return Ok((out, j + 1)),
+            c => {
+                out.push(c);
+                j += 1;
+            }
+        }
+    }
+    Err(format!(
+        "unterminated '{delim}' starting at position {}",
+        i + 1
+    ))
+}
+
+/// Parse a `[...]` segment starting at `i`; returns it and the index after it.
+fn parse_index(chars: &[char], i: usize, patterns: bool) -> Result<(QuerySeg, usize), String> {
+    let close = chars[i..]
+        .iter()
+        .position(|&c| c == ']')
+        .map(|p| i + p)
+        .ok_or_else(|| format!("unterminated '[' at position {}", i + 1))?;
+    let body: String = chars[i + 1..close].iter().collect();
+    let body = body.trim();
+    let number = |s: &str| -> Result<Option<usize>, String> {
+        let s = s.trim();
+        if s.is_empty() {
+            Ok(None)
+        } else {
+            s.parse()
+                .map(Some)
+                .map_err(|_| format!("invalid index '{s}'"))
+        }
+    };
+    let seg = if patterns && body == "*" {
+        QuerySeg::Range(None, None)
+    } else if let Some((lo, hi)) = body.split_once(':').filter(|_| patterns) {
+        QuerySeg::Range(number(lo)?, number(hi)?)
+    } else {
+        QuerySeg::Index(number(body)?.ok_or("empty index '[]'")?)
+    };
+    Ok((seg, close + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::yaml_editor::document::{path_to_string, Document};
+
+    fn key(k: &str) -> PathSeg {
+        PathSeg::Key(k.to_string())
+    }
+
+    const SAMPLE: &str = "\
+db:
+  main:
+    user: admin
+    password: one
+  replica:
+    password: two
+    passphrase: three
+servers:
+  - host: a
+  - host: b
+  - host: c
+password: top
+";
+
+    fn select(doc: &Document, q: &str) -> Vec<String> {
+        doc.select(q)
+            .unwrap()
+            .into_iter()
+            .map(|id| path_to_string(&doc.nodes()[id].path))
+            .collect()
+    }
+
+    #[test]
+    fn literal_paths_round_trip() {
+        for p in [
+            vec![key("servers"), PathSeg::Index(0), key("host")],
+            vec![PathSeg::Index(2), key("name")],
+            vec![key("a.b"), key("c[0]"), key("say \"hi\"")],
+            vec![key("*"), key("/x/"), key(" padded "), key("back\\slash")],
+            vec![key("")],
+        ] {
+            let s = path_to_string(&p);
+            assert_eq!(parse_path(&s).unwrap(), p, "round trip of {s}");
+        }
+        assert_eq!(path_to_string(&[key("a.b"), key("c")]), "\"a.b\".c");
+    }
+
+    #[test]
+    fn literal_path_rejects_patterns_and_garbage() {
+        assert!(parse_path("db.*.password").is_err());
+        assert!(parse_path("db..password").is_err());
+        assert!(parse_path("db.").is_err());
+        assert!(parse_path("servers[x]").is_err());
+        assert!(parse_path("\"open").is_err());
+        assert!(parse_path("").is_err());
+    }
+
+    #[test]
+    fn wildcard_matches_one_level() {
+        let doc = Document::parse(SAMPLE);
+        assert_eq!(
+            select(&doc, "db.*.password"),
+            vec!["db.main.password", "db.replica.password"]
+        );
+    }
+
+    #[test]
+    fn recursive_descent_matches_any_depth() {
+        let doc = Document::parse(SAMPLE);
+        assert_eq!(
+            select(&doc, "**.password"),
+            vec!["db.main.password", "db.replica.password", "password"]
+        );
+    }
+
+    #[test]
+    fn index_ranges() {
+        let doc = Document::parse(SAMPLE);
+        assert_eq!(
+            select(&doc, "servers[1:].host"),
+            vec!["servers[1].host", "servers[2].host"]
+        );
+        assert_eq!(select(&doc, "servers[:1].host"), vec!["servers[0].host"]);
+        assert_eq!(select(&doc, "servers[*]").len(), 3);
+    }
+
+    #[test]
+    fn key_regex_is_anchored() {
+        let doc = Document::parse(SAMPLE);
+        assert_eq!(
+            select(&doc, "db.replica./pass.*/"),
+            vec!["db.replica.password", "db.replica.passphrase"]
+        );
+        // Anchored: `word` alone does not match `password`.
+        assert!(select(&doc, "**./word/").is_empty());
+        assert!(PathQuery::parse("a./[/").is_err());
+    }
+
+    #[test]
+    fn quoted_keys_select_literal_dots() {
+        let doc = Document::parse("\"a.b\":\n  c: 1\na:\n  b:\n    c: 2\n");
+        assert_eq!(select(&doc, "\"a.b\".c"), vec!["\"a.b\".c"]);
+        assert_eq!(select(&doc, "a.b.c"), vec!["a.b.c"]);
+    }
+
+    #[test]
+    fn literal_query_detection() {
+        assert!(PathQuery::parse("servers[0].host").unwrap().is_literal());
+        assert!(!PathQuery::parse("servers[*].host").unwrap().is_literal());
+        assert!(!PathQuery::parse("**").unwrap().is_literal());
+    }
+}
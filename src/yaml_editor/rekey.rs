@@ -0,0 +1,283 @@
+//! Re-keying a file: every `![...]` value is decrypted with one environment
+//! and encrypted with another.
+//!
+//! Each value makes the round trip in a single call ([`rekey_value`]), so
+//! the plaintext only ever lives in that call's memory and is never written
+//! to the document. The YAML screen drives [`plan`] one value at a time off
+//! the UI thread; the `rekey` subcommand runs [`rekey_document`] in one go.
+
+use std::path::Path;
+
+use super::backup::Store;
+use super::document::{self, DocFormat, Document, NodeKind, PathSeg};
+use super::state::atomic_write;
+use crate::dencrypt;
+use crate::environment::{Environment, Environments};
+
+/// A value's path and why it failed or was skipped.
+pub type PathNote = (Vec<PathSeg>, String);
+
+/// What a re-key did to each value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub rotated: Vec<Vec<PathSeg>>,
+    /// Values that could not be re-keyed, with why.
+    pub failed: Vec<PathNote>,
+    /// Values left alone, with why (plaintext, not editable in place, …).
+    pub skipped: Vec<PathNote>,
+}
+
+impl Summary {
+    /// `3 rotated, 1 failed, 2 skipped`.
+    pub fn counts(&self) -> String {
+        format!(
+            "{} rotated, {} failed, {} skipped",
+            self.rotated.len(),
+            self.failed.len(),
+            self.skipped.len()
+        )
+    }
+}
+
+/// Split the scalars of `doc` into the encrypted values to re-key and the
+/// ones that are skipped. Empty values are not listed at all.
+pub fn plan(doc: &Document) -> (Vec<Vec<PathSeg>>, Vec<PathNote>) {
+    let (mut targets, mut skipped) = (Vec::new(), Vec::new());
+    for node in doc.nodes() {
+        if node.kind != NodeKind::Scalar {
+            continue;
+        }
+        let value = doc.logical_value(node.id).unwrap_or_default();
+        if value.trim().is_empty() {
+            continue;
+        }
+        if !document::is_wrapped(&value) {
+            skipped.push((node.path.clone(), "not encrypted".to_string()));
+        } else if !node.is_editable_scalar() {
+            skipped.push((node.path.clone(), "cannot be modified in place".to_string()));
+        } else {
+            targets.push(node.path.clone());
+        }
+    }
+    (targets, skipped)
+}
+
+/// Decrypt the wrapped value `wrapped` with `from` and encrypt the result with
+/// `to`, returning the new `![...]` value. Errors never include the value.
+pub fn rekey_value(
+    wrapped: &str,
+    from: &Environment,
+    to: &Environment,
+    jar: &Path,
+) -> Result<String, String> {
+    let plain = dencrypt::decrypt(&document::unwrap_cipher(wrapped), from, jar)
+        .map_err(|e| format!("decrypt with {} failed: {}", from.name, first_line(&e)))?;
+    let cipher = dencrypt::encrypt(&plain, to, jar)
+        .map_err(|e| format!("encrypt with {} failed: {}", to.name, first_line(&e)))?;
+    Ok(document::wrap_cipher(&cipher))
+}
+
+/// Check that `from` and `to` can re-key a file.
+pub fn check_envs(from: &Environment, to: &Environment) -> Result<(), String> {
+    if from.name == to.name {
+        return Err("Pick a different environment to re-key to".to_string());
+    }
+    for env in [from, to] {
+        if !env.algorithm.supports_modes() {
+            return Err(format!("{:?} is not supported by the tool.", env.algorithm));
+        }
+    }
+    Ok(())
+}
+
+/// Re-key every encrypted value in `doc` with `rekey` (normally a
+/// [`rekey_value`] closure), returning the new text and what happened to each
+/// value.
+pub fn rekey_document(
+    doc: &Document,
+    mut rekey: impl FnMut(&str) -> Result<String, String>,
+) -> (String, Summary) {
+    let (targets, skipped) = plan(doc);
+    let mut summary = Summary {
+        skipped,
+        ..Summary::default()
+    };
+    let mut doc = doc.clone();
+    for path in targets {
+        let Some(id) = doc.find_by_path(&path) else {
+            continue;
+        };
+        let value = doc.logical_value(id).unwrap_or_default();
+        let applied = rekey(&value).and_then(|new| {
+            let token = doc.encode_value(&new);
+            doc.replace_scalar(id, &token)
+        });
+        match applied {
+            Ok(_) => summary.rotated.push(path),
+            Err(e) => summary.failed.push((path, e)),
+        }
+    }
+    (doc.raw().to_string(), summary)
+}
+
+/// Read and validate the config file at `path` for re-keying.
+pub fn load(path: &Path) -> Result<Document, String> {
+    let format = DocFormat::from_path(path).ok_or("Not a .yaml/.yml/.properties/.json file")?;
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Document::validate_as(&content, format)
+        .map_err(|e| format!("Invalid {}: {e}", format.label()))?;
+    Ok(Document::parse_as(&content, format))
+}
+
+/// The `rekey` subcommand: re-key `file` from the environment named `from`
+/// to the one named `to`, writing it back unless `dry_run`. The text before
+/// and after is kept in `backups` first; the file is left alone if that fails.
+pub fn run_command(
+    envs: &Environments,
+    jar: &Path,
+    from: &str,
+    to: &str,
+    file: &Path,
+    dry_run: bool,
+    backups: Option<&Store>,
+) -> Result<Summary, String> {
+    let find = |name: &str| {
+        envs.environments
+            .iter()
+            .find(|e| e.name == name)
+            .ok_or_else(|| format!("No environment named {name}"))
+    };
+    let (from, to) = (find(from)?, find(to)?);
+    check_envs(from, to)?;
+    let doc = load(file)?;
+    let (text, summary) = rekey_document(&doc, |value| rekey_value(value, from, to, jar));
+    if !dry_run && !summary.rotated.is_empty() {
+        write_back(file, doc.raw(), &text, &to.name, backups)?;
+    }
+    Ok(summary)
+}
+
+/// Write the re-keyed `text` over `file`, which held `previous`, after
+/// keeping both in `backups`.
+fn write_back(
+    file: &Path,
+    previous: &str,
+    text: &str,
+    env: &str,
+    backups: Option<&Store>,
+) -> Result<(), String> {
+    if let Some(store) = backups {
+        store
+            .record(file, Some(previous), text, Some(env))
+            .map_err(|e| format!("Backup failed, {} not written: {e}", file.display()))?;
+    }
+    atomic_write(file, text)
+}
+
+/// One line per value (`rotated  db.password`), then the counts.
+pub fn format_summary(summary: &Summary) -> String {
+    let mut out = String::new();
+    for path in &summary.rotated {
+        out.push_str(&format!("rotated  {}\n", document::path_to_string(path)));
+    }
+    for (path, reason) in &summary.failed {
+        out.push_str(&format!(
+            "failed   {} — {reason}\n",
+            document::path_to_string(path)
+        ));
+    }
+    for (path, reason) in &summary.skipped {
+        out.push_str(&format!(
+            "skipped  {} — {reason}\n",
+            document::path_to_string(path)
+        ));
+    }
+    out.push_str(&summary.counts());
+    out.push('\n');
+    out
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+db:
+  user: admin
+  password: \"![old-pw]\"
+  empty: \"\"
+api:
+  key: '![old-key]'
+  token: \"![bad]\"
+";
+
+    #[test]
+    fn rekeys_encrypted_values_and_reports_the_rest() {
+        let doc = Document::parse(SAMPLE);
+        let (text, summary) = rekey_document(&doc, |value| {
+            let cipher = document::unwrap_cipher(value);
+            if cipher == "bad" {
+                return Err("decrypt with dev failed: bad padding".to_string());
+            }
+            Ok(document::wrap_cipher(&cipher.replace("old", "new")))
+        });
+        assert_eq!(
+            text,
+            "db:\n  user: admin\n  password: \"![new-pw]\"\n  empty: \"\"\napi:\n  key: \"![new-key]\"\n  token: \"![bad]\"\n"
+        );
+        let key = |keys: &[&str]| -> Vec<PathSeg> {
+            keys.iter().map(|k| PathSeg::Key(k.to_string())).collect()
+        };
+        assert_eq!(
+            summary.rotated,
+            vec![key(&["db", "password"]), key(&["api", "key"])]
+        );
+        assert_eq!(
+            summary.failed,
+            vec![(
+                key(&["api", "token"]),
+                "decrypt with dev failed: bad padding".to_string()
+            )]
+        );
+        assert_eq!(
+            summary.skipped,
+            vec![(key(&["db", "user"]), "not encrypted".to_string())]
+        );
+        assert_eq!(summary.counts(), "2 rotated, 1 failed, 1 skipped");
+        assert!(format_summary(&summary)
+            .starts_with("rotated  db.password\nrotated  api.key\nfailed   api.token — "));
+    }
+
+    #[test]
+    fn writes_are_backed_up_with_the_previous_text() {
+        let dir = crate::mule::test_support::TempDir::new();
+        dir.write("app.yaml", "pw: \"![old]\"\n");
+        let file = dir.0.join("app.yaml");
+        let store = Store::new(dir.0.join("backups"), 10);
+        write_back(
+            &file,
+            "pw: \"![old]\"\n",
+            "pw: \"![new]\"\n",
+            "prod",
+            Some(&store),
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "pw: \"![new]\"\n");
+        let backups: Vec<(String, Option<String>)> = store
+            .list(&file)
+            .iter()
+            .map(|b| (b.read().unwrap(), b.env.clone()))
+            .collect();
+        assert_eq!(
+            backups,
+            vec![
+                ("pw: \"![new]\"\n".to_string(), Some("prod".to_string())),
+                ("pw: \"![old]\"\n".to_string(), None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Reviewing unsaved changes before they are written.
+//!
+//! The saved text is diffed against the working document line by line, and
+//! each changed block of lines becomes a [`Hunk`]. Value changes are found by
+//! logical path and attached to the hunk holding their line, so a hunk reads
+//! `db.password: plaintext → encrypted` instead of showing ciphertext. A hunk
+//! can be reverted on its own by putting its saved lines back ([`revert`]).
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use similar::{DiffTag, TextDiff};
+
+use super::document::{self, Document, Node, NodeKind, PathSeg};
+
+/// How a value changed between the saved file and the working document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// Plaintext that is now `![...]`.
+    Encrypted,
+    /// `![...]` that is now plaintext.
+    Decrypted,
+    /// `![...]` with a different ciphertext.
+    Reencrypted,
+    /// Plaintext edited.
+    Edited,
+}
+
+/// A value change at one path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathChange {
+    pub path: Vec<PathSeg>,
+    pub kind: ChangeKind,
+    /// The saved and working logical values.
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl PathChange {
+    /// The change from `before` to `after` at `path`, if they differ.
+    pub fn between(
+        path: &[PathSeg],
+        before: Option<&String>,
+        after: Option<&String>,
+    ) -> Option<PathChange> {
+        let kind = match (before, after) {
+            (b, a) if b == a => return None,
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+            (Some(b), Some(a)) => match (document::is_wrapped(b), document::is_wrapped(a)) {
+                (false, true) => ChangeKind::Encrypted,
+                (true, false) => ChangeKind::Decrypted,
+                (true, true) => ChangeKind::Reencrypted,
+                (false, false) => ChangeKind::Edited,
+            },
+        };
+        Some(PathChange {
+            path: path.to_vec(),
+            kind,
+            before: before.cloned(),
+            after: after.cloned(),
+        })
+    }
+
+    /// `plaintext → encrypted`, `"8080" → "9090"`, … Ciphertext is never
+    /// shown.
+    pub fn summary(&self) -> String {
+        let shown = |v: &Option<String>| match v {
+            Some(v) if document::is_wrapped(v) => "encrypted".to_string(),
+            Some(v) => quote(v),
+            None => String::new(),
+        };
+        match self.kind {
+            ChangeKind::Added => format!("added, {}", shown(&self.after)),
+            ChangeKind::Removed => format!("removed, was {}", shown(&self.before)),
+            ChangeKind::Encrypted => "plaintext → encrypted".to_string(),
+            ChangeKind::Decrypted => "encrypted → plaintext".to_string(),
+            ChangeKind::Reencrypted => "encrypted → re-encrypted".to_string(),
+            ChangeKind::Edited => format!("{} → {}", shown(&self.before), shown(&self.after)),
+        }
+    }
+}
+
+/// A block of changed lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// Line range in the saved text.
+    pub saved: Range<usize>,
+    /// Line range in the working text.
+    pub working: Range<usize>,
+    /// The saved lines replaced, without line endings, ciphertext masked.
+    pub removed: Vec<String>,
+    /// The working lines in their place, likewise.
+    pub added: Vec<String>,
+    /// The value changes on these lines.
+    pub changes: Vec<PathChange>,
+}
+
+impl Hunk {
+    /// The path changes, or a note for a hunk that only touches layout or
+    /// comments.
+    pub fn summaries(&self) -> Vec<String> {
+        if self.changes.is_empty() {
+            return vec!["comments or layout only".to_string()];
+        }
+        self.changes
+            .iter()
+            .map(|c| format!("{}: {}", document::path_to_string(&c.path), c.summary()))
+            .collect()
+    }
+}
+
+/// Every hunk between `saved` and `working`, in file order.
+pub fn review(saved: &str, working: &Document) -> Vec<Hunk> {
+    let base = Document::parse_as(saved, working.format());
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let diff = TextDiff::from_lines(saved, working.raw());
+    let (old_lines, new_lines) = (lines(saved), lines(working.raw()));
+    for op in diff.ops() {
+        if op.tag() == DiffTag::Equal {
+            continue;
+        }
+        let (old, new) = (op.old_range(), op.new_range());
+        // A delete followed by an insert is one hunk.
+        if let Some(last) = hunks
+            .last_mut()
+            .filter(|h| h.saved.end == old.start && h.working.end == new.start)
+        {
+            last.saved.end = old.end;
+            last.working.end = new.end;
+        } else {
+            hunks.push(Hunk {
+                saved: old,
+                working: new,
+                removed: Vec::new(),
+                added: Vec::new(),
+                changes: Vec::new(),
+            });
+        }
+    }
+    for hunk in &mut hunks {
+        hunk.removed = old_lines[hunk.saved.clone()]
+            .iter()
+            .map(|l| mask(l))
+            .collect();
+        hunk.added = new_lines[hunk.working.clone()]
+            .iter()
+            .map(|l| mask(l))
+            .collect();
+    }
+
+    let (base_starts, work_starts) = (line_starts(saved), line_starts(working.raw()));
+    let base_values = scalar_values(&base);
+    let work_values = scalar_values(working);
+    let mut place = |change: PathChange, lines: Range<usize>, saved_side: bool| {
+        let hunk = hunks.iter_mut().find(|h| {
+            let range = if saved_side { &h.saved } else { &h.working };
+            range.start < lines.end && lines.start < range.end
+        });
+        if let Some(hunk) = hunk {
+            hunk.changes.push(change);
+        }
+    };
+    for node in working.nodes() {
+        let Some(after) = work_values.get(&node.path) else {
+            continue;
+        };
+        let Some(change) =
+            PathChange::between(&node.path, base_values.get(&node.path), Some(after))
+        else {
+            continue;
+        };
+        place(change, node_lines(&work_starts, node), false);
+    }
+    for node in base.nodes() {
+        if let (Some(before), None) = (base_values.get(&node.path), work_values.get(&node.path)) {
+            if let Some(change) = PathChange::between(&node.path, Some(before), None) {
+                place(change, node_lines(&base_starts, node), true);
+            }
+        }
+    }
+    hunks
+}
+
+/// `working` with `hunk`'s lines replaced by the saved ones.
+pub fn revert(saved: &str, working: &str, hunk: &Hunk) -> String {
+    let (old, new) = (lines(saved), lines(working));
+    let mut out = String::with_capacity(working.len());
+    new[..hunk.working.start]
+        .iter()
+        .for_each(|l| out.push_str(l));
+    old[hunk.saved.clone()].iter().for_each(|l| out.push_str(l));
+    new[hunk.working.end..].iter().for_each(|l| out.push_str(l));
+    out
+}
+
+/// Lines with their endings, as the diff counts them.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+fn line_of(starts: &[usize], offset: usize) -> usize {
+    starts.partition_point(|&s| s <= offset).saturating_sub(1)
+}
+
+/// The lines a node's key and value sit on.
+fn node_lines(starts: &[usize], node: &Node) -> Range<usize> {
+    let (start, end) = node.value_span.unwrap_or((node.line_end, node.line_end));
+    let first = line_of(starts, start.min(node.line_end));
+    let last = line_of(starts, end.saturating_sub(1).max(start).max(node.line_end));
+    first..last + 1
+}
+
+pub(super) fn scalar_values(doc: &Document) -> HashMap<Vec<PathSeg>, String> {
+    doc.nodes()
+        .iter()
+        .filter(|n| n.kind == NodeKind::Scalar)
+        .filter_map(|n| Some((n.path.clone(), doc.logical_value(n.id)?)))
+        .collect()
+}
+
+/// The line without its ending, every `![...]` shown as `![••••••]`.
+fn mask(line: &str) -> String {
+    let mut rest = line.trim_end_matches(['\n', '\r']);
+    let mut out = String::new();
+    while let Some(start) = rest.find("![") {
+        let Some(len) = rest[start..].find(']') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push_str("![••••••]");
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub(super) fn quote(value: &str) -> String {
+    const MAX: usize = 24;
+    if value.chars().count() > MAX {
+        let cut: String = value.chars().take(MAX - 1).collect();
+        format!("\"{cut}…\"")
+    } else {
+        format!("\"{value}\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAVED: &str = "\
+db:
+  user: admin
+  password: hunter2
+  port: 5432
+# old comment
+api:
+  key: \"![old]\"
+";
+
+    #[test]
+    fn hunks_summarise_value_changes_without_ciphertext() {
+        let working = Document::parse(
+            "\
+db:
+  user: root
+  password: \"![c2VjcmV0]\"
+  port: 5432
+# new comment
+api:
+  key: \"![new]\"
+  timeout: 30
+",
+        );
+        let hunks = review(SAVED, &working);
+        let summaries: Vec<Vec<String>> = hunks.iter().map(Hunk::summaries).collect();
+        assert_eq!(
+            summaries,
+            vec![
+                vec![
+                    "db.user: \"admin\" → \"root\"".to_string(),
+                    "db.password: plaintext → encrypted".to_string(),
+                ],
+                vec!["comments or layout only".to_string()],
+                vec![
+                    "api.key: encrypted → re-encrypted".to_string(),
+                    "api.timeout: added, \"30\"".to_string(),
+                ],
+            ]
+        );
+        assert_eq!(hunks[0].saved, 1..3);
+        assert_eq!(
+            hunks[0].added,
+            vec!["  user: root", "  password: \"![••••••]\""]
+        );
+        assert!(hunks
+            .iter()
+            .flat_map(|h| h.removed.iter().chain(&h.added))
+            .all(|l| !l.contains("old") || l.contains("comment")));
+    }
+
+    #[test]
+    fn reverting_a_hunk_restores_only_its_lines() {
+        let working = Document::parse(
+            "db:\n  user: admin\n  password: \"![x]\"\n  port: 5432\n# old comment\napi:\n",
+        );
+        let hunks = review(SAVED, &working);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[1].changes[0].kind, ChangeKind::Removed);
+        assert_eq!(
+            hunks[1].summaries(),
+            vec!["api.key: removed, was encrypted"]
+        );
+
+        let text = revert(SAVED, working.raw(), &hunks[1]);
+        assert_eq!(
+            text,
+            "db:\n  user: admin\n  password: \"![x]\"\n  port: 5432\n# old comment\napi:\n  key: \"![old]\"\n"
+        );
+        let text = revert(SAVED, &text, &review(SAVED, &Document::parse(&text))[0]);
+        assert_eq!(text, SAVED);
+    }
+}
@@ -6,14 +6,26 @@
 //! only to the node that started it (never to whatever is selected when the
 //! background job finishes).
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 
+use crate::environment::Environment;
 use crate::hints::YamlSelectionKind;
+use crate::mule::project::{ConfigFile, Project};
+use crate::mule::xref::{self, Report};
 use crate::state::Operation;
 use crate::text_field::TextField;
-use crate::yaml_editor::document::{self, Document, NodeKind, PathSeg, ScalarStyle};
+use crate::yaml_editor::backup::{self, Backup, Store};
+use crate::yaml_editor::bookmarks::Bookmarks;
+use crate::yaml_editor::compare::{self, Row, Side, Status};
+use crate::yaml_editor::document::{self, DocFormat, Document, NodeKind, PathSeg, ScalarStyle};
 use crate::yaml_editor::file_browser::FileBrowser;
+use crate::yaml_editor::history::History;
+use crate::yaml_editor::merge::{self, MergeRow};
+use crate::yaml_editor::query::{self, PathQuery};
+use crate::yaml_editor::rekey::{self, Summary};
+use crate::yaml_editor::review::{self, Hunk};
+use crate::yaml_editor::suspicious::{SecretRules, Signal};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum YamlFocus {
@@ -34,6 +46,9 @@ pub struct OpenModal {
     pub path_input: TextField,
     pub browser: FileBrowser,
     pub error: Option<String>,
+    /// Whether the chosen file is compared with the open one instead of
+    /// replacing it.
+    pub compare: bool,
 }
 
 impl Default for OpenModal {
@@ -43,10 +58,376 @@ impl Default for OpenModal {
             path_input: TextField::default(),
             browser: FileBrowser::default(),
             error: None,
+            compare: false,
         }
     }
 }
 
+/// The project panel listing a Mule project's config files.
+#[derive(Debug, Default)]
+pub struct ProjectPanel {
+    /// Index into [`Project::configs`].
+    pub selected: usize,
+    pub error: Option<String>,
+    /// The placeholder cross-check, shown instead of the file list while set.
+    pub report: Option<Report>,
+    /// Index into the report's findings.
+    pub finding: usize,
+}
+
+/// The compare view: the open file (left) against another file (right),
+/// aligned key by key (see [`compare`]).
+#[derive(Debug)]
+pub struct CompareView {
+    pub path: PathBuf,
+    pub doc: Document,
+    /// Content hash of the right file as read, so a copy never overwrites
+    /// changes made on disk in the meantime.
+    disk_hash: u64,
+    pub rows: Vec<Row>,
+    /// Index into [`CompareView::visible`].
+    pub selected: usize,
+    /// Show only the rows that differ.
+    pub differences_only: bool,
+}
+
+impl CompareView {
+    /// Indices into `rows` of the rows shown.
+    pub fn visible(&self) -> Vec<usize> {
+        (0..self.rows.len())
+            .filter(|&i| !self.differences_only || self.rows[i].status != Status::Same)
+            .collect()
+    }
+
+    /// Index into `rows` of the selected row.
+    pub fn selected_row(&self) -> Option<usize> {
+        self.visible().get(self.selected).copied()
+    }
+}
+
+/// The target-environment picker for a re-key. The environment selected
+/// when it opened is the source.
+#[derive(Debug, Clone)]
+pub struct RekeyPicker {
+    pub from: Environment,
+    /// The environments that can be picked as the target.
+    pub choices: Vec<Environment>,
+    pub selected: usize,
+}
+
+/// A re-key in progress: one value at a time, applied by stable path.
+#[derive(Debug)]
+struct RekeyRun {
+    from: Environment,
+    to: Environment,
+    queue: VecDeque<Vec<PathSeg>>,
+    total: usize,
+    /// The value in flight and its source token, for the stale check.
+    current: Option<(Vec<PathSeg>, String)>,
+    summary: Summary,
+}
+
+/// What a finished re-key did, shown until dismissed.
+#[derive(Debug, Clone)]
+pub struct RekeyReport {
+    pub from: String,
+    pub to: String,
+    pub summary: Summary,
+    /// Index into [`RekeyReport::rows`].
+    pub selected: usize,
+}
+
+impl RekeyReport {
+    /// `(outcome, path, reason)` per value: failures first, then skipped and
+    /// rotated values.
+    pub fn rows(&self) -> Vec<(&'static str, &[PathSeg], Option<&str>)> {
+        let s = &self.summary;
+        s.failed
+            .iter()
+            .map(|(p, e)| ("failed", p.as_slice(), Some(e.as_str())))
+            .chain(
+                s.skipped
+                    .iter()
+                    .map(|(p, why)| ("skipped", p.as_slice(), Some(why.as_str()))),
+            )
+            .chain(s.rotated.iter().map(|p| ("rotated", p.as_slice(), None)))
+            .collect()
+    }
+}
+
+/// Whether a value decrypted when it was verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Ok,
+    /// The first line of the tool's error.
+    Fails(String),
+}
+
+/// A verify verdict, valid while the value and the environment are the ones
+/// it was checked with.
+#[derive(Debug, Clone)]
+struct Verified {
+    env: String,
+    source: String,
+    verdict: Verdict,
+}
+
+/// A verify in progress: each value is decrypted and the plaintext dropped.
+#[derive(Debug)]
+struct VerifyRun {
+    env: Environment,
+    queue: VecDeque<Vec<PathSeg>>,
+    total: usize,
+    /// The value in flight and its source token.
+    current: Option<(Vec<PathSeg>, String)>,
+    failures: Vec<rekey::PathNote>,
+}
+
+/// The values a finished verify could not decrypt.
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub env: String,
+    pub checked: usize,
+    pub failures: Vec<rekey::PathNote>,
+    /// Index into `failures`.
+    pub selected: usize,
+}
+
+/// The plaintext values that look like secrets, listed for review.
+#[derive(Debug, Clone)]
+pub struct SuspiciousPanel {
+    pub items: Vec<(Vec<PathSeg>, Signal)>,
+    /// Index into `items`.
+    pub selected: usize,
+}
+
+/// The file changed on disk while the buffer had unsaved changes: our
+/// changes against the new disk content, path by path.
+#[derive(Debug, Clone)]
+pub struct MergeView {
+    disk: String,
+    pub rows: Vec<MergeRow>,
+    /// Index into `rows`.
+    pub selected: usize,
+}
+
+/// The saved versions of the open file, for loading one back.
+#[derive(Debug, Clone)]
+pub struct BackupView {
+    /// Newest first, each with what loading it would change.
+    pub backups: Vec<(Backup, Vec<String>)>,
+    /// Index into `backups`.
+    pub selected: usize,
+}
+
+/// What the copy menu (`y`) can put on the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyItem {
+    /// The selected node's logical path, `db.password`.
+    Path,
+    /// Its value token as written, quotes and `![...]` included.
+    Value,
+    /// The ciphertext inside `![...]`.
+    Ciphertext,
+    /// The plaintext of a peeked value.
+    Plaintext,
+    /// The node and everything under it as YAML.
+    Subtree,
+    /// The marked values as `path: value` lines.
+    Marked,
+}
+
+impl CopyItem {
+    pub const ALL: [CopyItem; 6] = [
+        CopyItem::Path,
+        CopyItem::Value,
+        CopyItem::Ciphertext,
+        CopyItem::Plaintext,
+        CopyItem::Subtree,
+        CopyItem::Marked,
+    ];
+
+    /// The key that copies it from the menu.
+    pub fn key(self) -> char {
+        match self {
+            CopyItem::Path => 'p',
+            CopyItem::Value => 'v',
+            CopyItem::Ciphertext => 'c',
+            CopyItem::Plaintext => 't',
+            CopyItem::Subtree => 'y',
+            CopyItem::Marked => 'm',
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CopyItem::Path => "Path",
+            CopyItem::Value => "Value as written",
+            CopyItem::Ciphertext => "Ciphertext",
+            CopyItem::Plaintext => "Plaintext (peeked)",
+            CopyItem::Subtree => "Subtree as YAML",
+            CopyItem::Marked => "Marked values",
+        }
+    }
+}
+
+/// The copy menu: the items that apply to the selection.
+#[derive(Debug, Clone)]
+pub struct CopyMenu {
+    pub items: Vec<CopyItem>,
+    /// Index into `items`.
+    pub selected: usize,
+}
+
+/// The go-to prompt: a path typed with completion from the document.
+#[derive(Debug, Default)]
+pub struct GotoPrompt {
+    pub field: TextField,
+    /// Index into [`YamlEditorState::goto_candidates`].
+    pub selected: usize,
+}
+
+/// The open file's bookmarks.
+#[derive(Debug, Clone)]
+pub struct BookmarkView {
+    /// Index into the file's bookmarks.
+    pub selected: usize,
+}
+
+/// The undo history popup.
+#[derive(Debug, Clone)]
+pub struct HistoryView {
+    /// Row in [`YamlEditorState::history_rows`].
+    pub selected: usize,
+}
+
+/// The unsaved changes, hunk by hunk, for review before saving.
+#[derive(Debug, Clone)]
+pub struct ReviewView {
+    pub hunks: Vec<Hunk>,
+    /// Index into `hunks`.
+    pub selected: usize,
+}
+
+/// A decrypted preview, valid while the value is the one it was decrypted
+/// from.
+struct Peeked {
+    source: String,
+    plain: String,
+}
+
+impl std::fmt::Debug for Peeked {
+    // Keep plaintext out of debug output and logs.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Peeked").finish_non_exhaustive()
+    }
+}
+
+/// Peek mode: `![...]` values shown decrypted in the tree and the Selected
+/// pane. The plaintexts live only here, never in the document, and are
+/// dropped with the whole struct when peek is toggled off, the environment
+/// changes or another file is loaded.
+#[derive(Debug)]
+struct Peek {
+    env: Environment,
+    cache: HashMap<Vec<PathSeg>, Peeked>,
+    run: Option<PeekRun>,
+}
+
+/// The values still to decrypt for peek.
+#[derive(Debug)]
+struct PeekRun {
+    queue: VecDeque<Vec<PathSeg>>,
+    total: usize,
+    /// The value in flight and its source token.
+    current: Option<(Vec<PathSeg>, String)>,
+    failed: usize,
+}
+
+/// How the tree search query is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// Case-insensitive substring of a node's label or path.
+    #[default]
+    Text,
+    /// A path query such as `db.*.password` or `**.password` (see
+    /// [`crate::yaml_editor::query`]). Bulk encrypt/decrypt then applies to
+    /// every match instead of the selected subtree.
+    Path,
+    /// Case-insensitive substring of a scalar's logical value.
+    Value,
+    /// A case-insensitive regex over a node's path or a scalar's logical
+    /// value.
+    Regex,
+}
+
+impl SearchMode {
+    /// The next mode, for `Tab` while typing.
+    pub fn next(self) -> SearchMode {
+        match self {
+            SearchMode::Text => SearchMode::Path,
+            SearchMode::Path => SearchMode::Value,
+            SearchMode::Value => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Text,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchMode::Text => "text",
+            SearchMode::Path => "path",
+            SearchMode::Value => "value",
+            SearchMode::Regex => "regex",
+        }
+    }
+}
+
+/// Which scalars a search keeps, on top of its query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchFilter {
+    #[default]
+    All,
+    /// `![...]` values only.
+    Encrypted,
+    /// Values that are not `![...]`.
+    Plaintext,
+    /// Values changed since the file was opened or saved.
+    Modified,
+}
+
+impl SearchFilter {
+    /// The next filter, for `Shift-Tab` while typing.
+    pub fn next(self) -> SearchFilter {
+        match self {
+            SearchFilter::All => SearchFilter::Encrypted,
+            SearchFilter::Encrypted => SearchFilter::Plaintext,
+            SearchFilter::Plaintext => SearchFilter::Modified,
+            SearchFilter::Modified => SearchFilter::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchFilter::All => "all",
+            SearchFilter::Encrypted => "encrypted only",
+            SearchFilter::Plaintext => "plaintext only",
+            SearchFilter::Modified => "modified only",
+        }
+    }
+}
+
+/// What the in-progress edit field writes back to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EditTarget {
+    /// The selected scalar's value.
+    #[default]
+    Value,
+    /// The inline comment on the selected node's line.
+    Comment,
+    /// The name of a new bookmark on the selected node.
+    Bookmark,
+}
+
 /// A pending destructive action awaiting confirmation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Confirm {
@@ -60,10 +441,10 @@ pub enum Confirm {
 /// Save / Discard / Cancel choice.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Guard {
-    /// Quit the application.
+    /// Quit the application; covers every open file.
     Quit,
-    /// Open (replace the current document with) the file at this path.
-    Open(String),
+    /// Close the active file.
+    Close,
 }
 
 /// A crypto operation in flight, targeting a specific node by its stable path.
@@ -76,15 +457,47 @@ struct Pending {
     original_source: String,
 }
 
+/// An open file that is not the active one: everything that belongs to the
+/// file and survives switching away from it. Searches, reports and peeked
+/// values do not.
+#[derive(Debug)]
+struct Buffer {
+    file_path: Option<PathBuf>,
+    initial_content: String,
+    doc: Document,
+    expanded: HashSet<Vec<PathSeg>>,
+    marks: HashSet<Vec<PathSeg>>,
+    selected_path: Option<Vec<PathSeg>>,
+    disk_hash: Option<u64>,
+    ignored_disk: Option<u64>,
+    baseline_values: HashMap<Vec<PathSeg>, String>,
+    history: History,
+    verified: HashMap<Vec<PathSeg>, Verified>,
+}
+
+impl Buffer {
+    fn dirty(&self) -> bool {
+        self.doc.raw() != self.initial_content
+    }
+}
+
 #[derive(Debug)]
 pub struct YamlEditorState {
+    /// The other open files, in tab order with the active one left out: tab
+    /// `i` is `parked[i]` before `active` and `parked[i - 1]` after it.
+    parked: Vec<Buffer>,
+    active: usize,
     pub file_path: Option<PathBuf>,
     initial_content: String,
     doc: Document,
     expanded: HashSet<Vec<PathSeg>>,
+    /// Marked nodes (`Space`), by path so marks survive re-parses. A marked
+    /// container stands for every value under it.
+    marks: HashSet<Vec<PathSeg>>,
     selected_path: Option<Vec<PathSeg>>,
     pub focus: YamlFocus,
     pub editing: Option<TextField>,
+    pub edit_target: EditTarget,
     pub reveal: bool,
     pub crypto_in_progress: bool,
     message: Option<(String, bool)>,
@@ -92,7 +505,46 @@ pub struct YamlEditorState {
     /// Content hash of the file on disk at open / last save, for detecting
     /// external modification before overwriting.
     disk_hash: Option<u64>,
+    /// A disk version the user chose not to merge; not offered again.
+    ignored_disk: Option<u64>,
+    last_poll: Option<std::time::Instant>,
+    pub merge: Option<MergeView>,
+    /// Where each save is backed up; `None` keeps no backups.
+    pub backups: Option<Store>,
+    /// The selected environment's name, recorded with each backup.
+    pub save_env: Option<String>,
+    pub backup_view: Option<BackupView>,
+    /// Named paths per file (`'`), saved across sessions.
+    pub bookmarks: Bookmarks,
+    pub bookmark_view: Option<BookmarkView>,
+    pub goto: Option<GotoPrompt>,
+    pub copy_menu: Option<CopyMenu>,
     pub open_modal: Option<OpenModal>,
+    /// The Mule project loaded with `--project`, if any.
+    pub project: Option<Project>,
+    /// Where project config → environment links are saved; `None` keeps
+    /// them for the session only.
+    pub project_links: Option<PathBuf>,
+    pub project_panel: Option<ProjectPanel>,
+    /// The environment linked to the file just opened, for the app to select
+    /// (see [`Project::linked_env`]).
+    pub pending_env: Option<String>,
+    /// The config file an environment is being created for from the project
+    /// panel; linked to it once the form is saved.
+    pub pending_link: Option<PathBuf>,
+    pub compare: Option<CompareView>,
+    pub review: Option<ReviewView>,
+    /// What counts as a plaintext secret (the config file's `secrets`).
+    pub secret_rules: SecretRules,
+    pub suspicious_panel: Option<SuspiciousPanel>,
+    pub rekey_picker: Option<RekeyPicker>,
+    rekey: Option<RekeyRun>,
+    pub rekey_report: Option<RekeyReport>,
+    /// Verify verdicts by value path.
+    verified: HashMap<Vec<PathSeg>, Verified>,
+    verify: Option<VerifyRun>,
+    pub verify_report: Option<VerifyReport>,
+    peek: Option<Peek>,
     pub confirm: Option<Confirm>,
     /// A quit/open deferred until the user resolves unsaved changes.
     guard: Option<Guard>,
@@ -100,41 +552,85 @@ pub struct YamlEditorState {
     /// used to highlight individually modified properties. Cleared/rebuilt when
     /// the saved baseline changes.
     baseline_values: HashMap<Vec<PathSeg>, String>,
-    undo_stack: Vec<String>,
-    redo_stack: Vec<String>,
+    history: History,
+    pub history_view: Option<HistoryView>,
     /// Search filter over the tree (node label / path).
     pub search: Option<TextField>,
     /// Whether keystrokes are currently editing the search query.
     pub search_editing: bool,
+    /// The compiled regex query, or why it does not compile; kept in step
+    /// with the query and mode by [`Self::refresh_search`].
+    search_regex: Option<Result<regex::Regex, String>>,
+    /// How the search query is matched (`Tab` while typing switches).
+    pub search_mode: SearchMode,
+    /// Which values the search keeps (`Shift-Tab` while typing switches).
+    pub search_filter: SearchFilter,
+    /// Leave the tree unfiltered and only step through the matches with
+    /// `n`/`N` (`Ctrl-f` while typing switches).
+    pub search_jump: bool,
     /// Remaining node paths for an in-progress bulk encrypt/decrypt.
-    bulk_queue: Vec<Vec<PathSeg>>,
+    bulk_queue: VecDeque<Vec<PathSeg>>,
     bulk_op: Option<Operation>,
 }
 
 impl Default for YamlEditorState {
     fn default() -> Self {
         Self {
+            parked: Vec::new(),
+            active: 0,
             file_path: None,
             initial_content: String::new(),
             doc: Document::parse(""),
             expanded: HashSet::new(),
+            marks: HashSet::new(),
             selected_path: None,
             focus: YamlFocus::Tree,
             editing: None,
+            edit_target: EditTarget::Value,
             reveal: false,
             crypto_in_progress: false,
             message: None,
             pending: None,
             disk_hash: None,
+            ignored_disk: None,
+            last_poll: None,
+            merge: None,
+            backups: None,
+            save_env: None,
+            backup_view: None,
+            bookmarks: Bookmarks::default(),
+            bookmark_view: None,
+            goto: None,
+            copy_menu: None,
             open_modal: None,
+            project: None,
+            project_links: None,
+            project_panel: None,
+            pending_env: None,
+            pending_link: None,
+            compare: None,
+            review: None,
+            secret_rules: SecretRules::default(),
+            suspicious_panel: None,
+            rekey_picker: None,
+            rekey: None,
+            rekey_report: None,
+            verified: HashMap::new(),
+            verify: None,
+            verify_report: None,
+            peek: None,
             confirm: None,
             guard: None,
             baseline_values: HashMap::new(),
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            history: History::default(),
+            history_view: None,
             search: None,
             search_editing: false,
-            bulk_queue: Vec::new(),
+            search_regex: None,
+            search_mode: SearchMode::Text,
+            search_filter: SearchFilter::All,
+            search_jump: false,
+            bulk_queue: VecDeque::new(),
             bulk_op: None,
         }
     }
@@ -156,7 +652,7 @@ impl YamlEditorState {
     /// Recompute the per-property baseline from the current saved snapshot
     /// (`initial_content`). Call whenever that baseline changes (open / save).
     fn rebuild_baseline(&mut self) {
-        let baseline = Document::parse(&self.initial_content);
+        let baseline = Document::parse_as(&self.initial_content, self.doc.format());
         let mut map = HashMap::new();
         for (id, node) in baseline.nodes().iter().enumerate() {
             if node.kind == NodeKind::Scalar {
@@ -203,19 +699,30 @@ impl YamlEditorState {
 
     // --- undo / redo -------------------------------------------------------
 
-    /// Record the current content so a mutation can be undone.
-    fn snapshot(&mut self) {
-        if self.undo_stack.last().map(String::as_str) != Some(self.doc.raw()) {
-            self.undo_stack.push(self.doc.raw().to_string());
-            if self.undo_stack.len() > 100 {
-                self.undo_stack.remove(0);
-            }
-            self.redo_stack.clear();
-        }
+    /// Replace the document with `text`, recording the change as `label`.
+    fn change_doc(&mut self, label: impl Into<String>, text: String) {
+        self.history.record(label, self.doc.raw(), &text);
+        self.set_doc(text);
+    }
+
+    /// Replace one scalar in place (see [`Document::replace_scalar`]),
+    /// recording the change as `label` only if it succeeds.
+    fn replace_value(&mut self, id: usize, new_source: &str, label: &str) -> Result<(), String> {
+        let splice = self.doc.replace_scalar(id, new_source)?;
+        self.history.record_splice(label, splice);
+        Ok(())
+    }
+
+    /// `verb path`, the history label for a change to one node.
+    fn node_label(&self, verb: &str, id: usize) -> String {
+        format!(
+            "{verb} {}",
+            document::path_to_string(&self.doc.nodes()[id].path)
+        )
     }
 
     fn set_doc(&mut self, text: String) {
-        self.doc = Document::parse(&text);
+        self.doc = Document::parse_as(&text, self.doc.format());
         self.editing = None;
         if self.selected_id().is_none() {
             self.selected_path = self
@@ -225,29 +732,103 @@ impl YamlEditorState {
         }
     }
 
+    /// Undo and redo wait for a running bulk or re-key, whose changes are
+    /// still being collected into one step.
+    fn history_locked(&mut self) -> bool {
+        if self.history.grouping() {
+            self.set_msg("Wait for the running operation to finish.", true);
+        }
+        self.history.grouping()
+    }
+
     pub fn undo(&mut self) {
-        if let Some(prev) = self.undo_stack.pop() {
-            self.redo_stack.push(self.doc.raw().to_string());
-            self.set_doc(prev);
-            self.set_msg("Undo.", false);
-        } else {
-            self.set_msg("Nothing to undo.", false);
+        if self.history_locked() {
+            return;
+        }
+        match self.history.undo(self.doc.raw()) {
+            Some((text, label)) => {
+                let msg = format!("Undid: {label}.");
+                self.set_doc(text);
+                self.set_msg(msg, false);
+            }
+            None => self.set_msg("Nothing to undo.", false),
         }
     }
 
     pub fn redo(&mut self) {
-        if let Some(next) = self.redo_stack.pop() {
-            self.undo_stack.push(self.doc.raw().to_string());
-            self.set_doc(next);
-            self.set_msg("Redo.", false);
-        } else {
-            self.set_msg("Nothing to redo.", false);
+        if self.history_locked() {
+            return;
+        }
+        match self.history.redo(self.doc.raw()) {
+            Some((text, label)) => {
+                let msg = format!("Redid: {label}.");
+                self.set_doc(text);
+                self.set_msg(msg, false);
+            }
+            None => self.set_msg("Nothing to redo.", false),
+        }
+    }
+
+    /// The history as `(label, applied)` rows, newest first, ending with the
+    /// file as opened. Row `i` is history position `len - i`.
+    pub fn history_rows(&self) -> Vec<(String, bool)> {
+        let entries = self.history.entries();
+        let position = self.history.position();
+        let opened = format!(
+            "Opened {}",
+            self.file_path.as_deref().map(file_name).unwrap_or_default()
+        );
+        entries
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, e)| (e.label.clone(), i < position))
+            .chain(std::iter::once((opened, true)))
+            .collect()
+    }
+
+    pub fn open_history(&mut self) {
+        if !self.is_open() {
+            self.set_msg("Open a file first.", true);
+            return;
+        }
+        let selected = self.history.entries().len() - self.history.position();
+        self.history_view = Some(HistoryView { selected });
+    }
+
+    pub fn history_move(&mut self, delta: isize) {
+        let last = self.history.entries().len() as isize;
+        if let Some(view) = self.history_view.as_mut() {
+            view.selected = (view.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Enter in the history: undo or redo until the selected row is the
+    /// latest applied change, and close the popup.
+    pub fn history_jump(&mut self) {
+        let Some(view) = self.history_view.take() else {
+            return;
+        };
+        if self.history_locked() {
+            return;
+        }
+        let target = self.history.entries().len() - view.selected;
+        if let Some(text) = self.history.travel(self.doc.raw(), target) {
+            self.set_doc(text);
+            self.set_msg(
+                match self.history.entries().get(target.wrapping_sub(1)) {
+                    Some(e) => format!("History: back to \"{}\".", e.label),
+                    None => "History: back to the file as opened.".to_string(),
+                },
+                false,
+            );
         }
     }
 
     // --- opening -----------------------------------------------------------
 
-    /// Validate and load a YAML file from a user-supplied path. On any failure
+    /// Validate and load a YAML, `.properties` or JSON file from a
+    /// user-supplied path. On any failure
     /// the currently open document is left untouched.
     pub fn open_path(&mut self, input: &str) -> Result<(), String> {
         let path = resolve_path(input)?;
@@ -257,29 +838,51 @@ impl YamlEditorState {
         if !path.is_file() {
             return Err("Path is not a regular file".to_string());
         }
-        match path.extension().and_then(|e| e.to_str()) {
-            Some("yaml") | Some("yml") => {}
-            _ => return Err("Not a .yaml/.yml file".to_string()),
-        }
+        let format =
+            DocFormat::from_path(&path).ok_or("Not a .yaml/.yml/.properties/.json file")?;
         let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
-        Document::validate(&content).map_err(|e| format!("Invalid YAML: {e}"))?;
-        self.load_content(path, content);
+        Document::validate_as(&content, format)
+            .map_err(|e| format!("Invalid {}: {e}", format.label()))?;
+        if self.is_open() && self.job_running() {
+            return Err("Wait for the running operation to finish".to_string());
+        }
+        // A file that is already open is switched to, not loaded twice.
+        if self.file_path.as_ref() == Some(&path) {
+            self.pending_env = self.project.as_ref().and_then(|p| p.linked_env(&path));
+            return Ok(());
+        }
+        if let Some(i) = self
+            .parked
+            .iter()
+            .position(|b| b.file_path.as_ref() == Some(&path))
+        {
+            self.switch_to(if i < self.active { i } else { i + 1 });
+            self.set_msg(format!("Switched to {}.", file_name(&path)), false);
+            return Ok(());
+        }
+        // Otherwise it opens in a new tab after the others.
+        if self.is_open() {
+            let current = self.take_buffer();
+            self.parked.insert(self.active, current);
+            self.active = self.parked.len();
+        }
+        self.load_content(path, content, format);
         Ok(())
     }
 
-    fn load_content(&mut self, path: PathBuf, content: String) {
+    fn load_content(&mut self, path: PathBuf, content: String, format: DocFormat) {
         self.disk_hash = Some(hash(&content));
+        self.ignored_disk = None;
         self.initial_content = content.clone();
-        self.doc = Document::parse(&content);
+        self.doc = Document::parse_as(&content, format);
         self.rebuild_baseline();
+        self.pending_env = self.project.as_ref().and_then(|p| p.linked_env(&path));
         self.file_path = Some(path);
-        self.undo_stack.clear();
-        self.redo_stack.clear();
-        self.search = None;
-        self.search_editing = false;
-        self.bulk_op = None;
-        self.bulk_queue.clear();
+        self.history.clear();
+        self.verified.clear();
+        self.reset_views();
         self.expanded.clear();
+        self.marks.clear();
         // Expand top-level containers by default.
         for &root in self.doc.roots() {
             if let Some(n) = self.doc.node(root) {
@@ -292,14 +895,197 @@ impl YamlEditorState {
             .visible()
             .first()
             .map(|&id| self.doc.nodes()[id].path.clone());
-        self.editing = None;
-        self.pending = None;
         self.set_msg(
             format!("Opened {}", self.file_path.as_ref().unwrap().display()),
             false,
         );
     }
 
+    /// Drop what is shown over the active file but not kept with it.
+    fn reset_views(&mut self) {
+        self.compare = None;
+        self.merge = None;
+        self.history_view = None;
+        self.backup_view = None;
+        self.bookmark_view = None;
+        self.goto = None;
+        self.copy_menu = None;
+        self.search = None;
+        self.search_editing = false;
+        self.search_regex = None;
+        self.bulk_op = None;
+        self.bulk_queue.clear();
+        self.rekey = None;
+        self.rekey_report = None;
+        self.verify = None;
+        self.verify_report = None;
+        self.peek = None;
+        self.review = None;
+        self.suspicious_panel = None;
+        self.editing = None;
+        self.pending = None;
+    }
+
+    // --- buffers -----------------------------------------------------------
+
+    /// Whether a crypto job, bulk run, re-key or verify is running on the
+    /// active file; switching files waits for it.
+    fn job_running(&self) -> bool {
+        self.crypto_in_progress
+            || self.bulk_op.is_some()
+            || self.rekey.is_some()
+            || self.verify.is_some()
+    }
+
+    fn take_buffer(&mut self) -> Buffer {
+        // A run cut short still undoes as one step.
+        self.history
+            .end_group(|n| format!("Interrupted run, {n} value(s)"));
+        Buffer {
+            file_path: self.file_path.take(),
+            initial_content: std::mem::take(&mut self.initial_content),
+            doc: std::mem::replace(&mut self.doc, Document::parse("")),
+            expanded: std::mem::take(&mut self.expanded),
+            marks: std::mem::take(&mut self.marks),
+            selected_path: self.selected_path.take(),
+            disk_hash: self.disk_hash.take(),
+            ignored_disk: self.ignored_disk.take(),
+            baseline_values: std::mem::take(&mut self.baseline_values),
+            history: std::mem::take(&mut self.history),
+            verified: std::mem::take(&mut self.verified),
+        }
+    }
+
+    fn put_buffer(&mut self, buffer: Buffer) {
+        self.file_path = buffer.file_path;
+        self.initial_content = buffer.initial_content;
+        self.doc = buffer.doc;
+        self.expanded = buffer.expanded;
+        self.marks = buffer.marks;
+        self.selected_path = buffer.selected_path;
+        self.disk_hash = buffer.disk_hash;
+        self.ignored_disk = buffer.ignored_disk;
+        self.baseline_values = buffer.baseline_values;
+        self.history = buffer.history;
+        self.verified = buffer.verified;
+        self.reset_views();
+        self.pending_env = self
+            .file_path
+            .as_ref()
+            .and_then(|path| self.project.as_ref().and_then(|p| p.linked_env(path)));
+    }
+
+    /// How many files are open.
+    pub fn buffer_count(&self) -> usize {
+        self.parked.len() + usize::from(self.is_open())
+    }
+
+    /// The open files' names and dirty flags in tab order, and the active
+    /// tab.
+    pub fn buffers(&self) -> (Vec<(String, bool)>, usize) {
+        let mut tabs: Vec<(String, bool)> = self
+            .parked
+            .iter()
+            .map(|b| {
+                let name = b.file_path.as_deref().map(file_name).unwrap_or_default();
+                (name, b.dirty())
+            })
+            .collect();
+        if let Some(path) = &self.file_path {
+            tabs.insert(self.active, (file_name(path), self.dirty()));
+        }
+        (tabs, self.active)
+    }
+
+    /// Make tab `index` the active file.
+    fn switch_to(&mut self, index: usize) {
+        if index == self.active || index > self.parked.len() {
+            return;
+        }
+        let current = self.take_buffer();
+        self.parked.insert(self.active, current);
+        let target = self.parked.remove(index);
+        self.put_buffer(target);
+        self.active = index;
+    }
+
+    /// `[` / `]`: switch to the previous or next open file.
+    pub fn switch_buffer(&mut self, delta: isize) {
+        let count = self.buffer_count() as isize;
+        if count < 2 {
+            self.set_msg("No other file open (Ctrl-o opens one).", false);
+            return;
+        }
+        if self.job_running() {
+            self.set_msg("Wait for the running operation to finish.", true);
+            return;
+        }
+        let index = (self.active as isize + delta).rem_euclid(count) as usize;
+        self.switch_to(index);
+        let name = self.file_path.as_deref().map(file_name).unwrap_or_default();
+        self.set_msg(format!("Switched to {name}."), false);
+    }
+
+    /// `Ctrl-w`: close the active file, asking first if it has unsaved
+    /// changes.
+    pub fn request_close(&mut self) {
+        if !self.is_open() {
+            self.set_msg("No file open.", true);
+        } else if self.job_running() {
+            self.set_msg("Wait for the running operation to finish.", true);
+        } else if self.dirty() {
+            self.guard = Some(Guard::Close);
+        } else {
+            self.close_buffer();
+        }
+    }
+
+    /// Close the active file, discarding unsaved changes, and show the next
+    /// one (or none).
+    pub fn close_buffer(&mut self) {
+        let closed = self.take_buffer();
+        self.reset_views();
+        if self.parked.is_empty() {
+            self.active = 0;
+        } else {
+            let index = self.active.min(self.parked.len() - 1);
+            let next = self.parked.remove(index);
+            self.put_buffer(next);
+            self.active = index;
+        }
+        let name = closed
+            .file_path
+            .as_deref()
+            .map(file_name)
+            .unwrap_or_default();
+        self.set_msg(format!("Closed {name}."), false);
+    }
+
+    /// The names of the open files with unsaved changes.
+    pub fn unsaved_files(&self) -> Vec<String> {
+        let (tabs, _) = self.buffers();
+        tabs.into_iter()
+            .filter(|(_, dirty)| *dirty)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Save every open file with unsaved changes. Stops at the first failure
+    /// with that file active. Only files that need saving are switched to, so
+    /// the active file keeps its views when the others are clean.
+    fn save_all(&mut self) -> Result<(), String> {
+        let start = self.active;
+        let (tabs, _) = self.buffers();
+        let dirty: Vec<usize> = (0..tabs.len()).filter(|&i| tabs[i].1).collect();
+        for &index in &dirty {
+            self.switch_to(index);
+            let name = self.file_path.as_deref().map(file_name).unwrap_or_default();
+            self.save().map_err(|e| format!("{name}: {e}"))?;
+        }
+        self.switch_to(start);
+        Ok(())
+    }
+
     // --- navigation --------------------------------------------------------
 
     /// The active, non-empty search query, if any.
@@ -310,18 +1096,118 @@ impl YamlEditorState {
             .filter(|s| !s.is_empty())
     }
 
+    /// The active search parsed as a path query, when in [`SearchMode::Path`]
+    /// and the query is valid.
+    pub fn active_path_query(&self) -> Option<PathQuery> {
+        if self.search_mode != SearchMode::Path {
+            return None;
+        }
+        PathQuery::parse(&self.search_query()?).ok()
+    }
+
+    /// Why the active path query or regex does not parse, if it doesn't. Such
+    /// a query narrows nothing until it is fixed.
+    pub fn search_error(&self) -> Option<String> {
+        match self.search_mode {
+            SearchMode::Path => PathQuery::parse(&self.search_query()?).err(),
+            SearchMode::Regex => self.search_regex.as_ref()?.as_ref().err().cloned(),
+            SearchMode::Text | SearchMode::Value => None,
+        }
+    }
+
+    /// Whether a valid query or a filter is narrowing the tree or the
+    /// matches.
+    pub fn search_active(&self) -> bool {
+        (self.search_query().is_some() && self.search_error().is_none())
+            || self.search_filter != SearchFilter::All
+    }
+
+    /// Node ids matching the active query and filter (no ancestors), in
+    /// document order.
+    pub fn search_matches(&self) -> Vec<usize> {
+        let query = self
+            .search_query()
+            .filter(|_| self.search_error().is_none());
+        let by_query: Box<dyn Fn(&document::Node) -> bool + '_> = match (&query, self.search_mode) {
+            (None, _) => Box::new(|_| true),
+            (Some(query), SearchMode::Text) => {
+                let q = query.to_lowercase();
+                Box::new(move |node| {
+                    let mut hay =
+                        format!("{} {}", node.label, document::path_to_string(&node.path));
+                    for comment in node.leading_comments.iter().chain(&node.inline_comment) {
+                        hay.push('\n');
+                        hay.push_str(comment);
+                    }
+                    hay.to_lowercase().contains(&q)
+                })
+            }
+            (Some(_), SearchMode::Path) => {
+                let hits: HashSet<usize> = self
+                    .active_path_query()
+                    .map(|q| self.doc.query(&q))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect();
+                Box::new(move |node| hits.contains(&node.id))
+            }
+            (Some(query), SearchMode::Value) => {
+                let q = query.to_lowercase();
+                Box::new(move |node| {
+                    node.kind == NodeKind::Scalar
+                        && self
+                            .doc
+                            .logical_value(node.id)
+                            .is_some_and(|v| v.to_lowercase().contains(&q))
+                })
+            }
+            (Some(_), SearchMode::Regex) => match &self.search_regex {
+                Some(Ok(re)) => Box::new(move |node| {
+                    re.is_match(&document::path_to_string(&node.path))
+                        || (node.kind == NodeKind::Scalar
+                            && self
+                                .doc
+                                .logical_value(node.id)
+                                .is_some_and(|v| re.is_match(&v)))
+                }),
+                _ => Box::new(|_| true),
+            },
+        };
+        self.doc
+            .nodes()
+            .iter()
+            .filter(|node| self.passes_filter(node) && by_query(node))
+            .map(|node| node.id)
+            .collect()
+    }
+
+    fn passes_filter(&self, node: &document::Node) -> bool {
+        if self.search_filter == SearchFilter::All {
+            return true;
+        }
+        if node.kind != NodeKind::Scalar {
+            return false;
+        }
+        let encrypted = self
+            .doc
+            .logical_value(node.id)
+            .is_some_and(|v| document::is_wrapped(&v));
+        match self.search_filter {
+            SearchFilter::All => true,
+            SearchFilter::Encrypted => encrypted,
+            SearchFilter::Plaintext => !encrypted,
+            SearchFilter::Modified => self.is_modified(node.id),
+        }
+    }
+
     /// Node ids to keep when a search is active: matches plus their ancestors.
-    fn include_set(&self, query: &str) -> HashSet<usize> {
-        let q = query.to_lowercase();
+    fn include_set(&self) -> HashSet<usize> {
         let mut include = HashSet::new();
-        for node in self.doc.nodes() {
-            let hay = format!("{} {}", node.label, document::path_to_string(&node.path));
-            if hay.to_lowercase().contains(&q) {
-                let mut cur = Some(node.id);
-                while let Some(id) = cur {
-                    include.insert(id);
-                    cur = self.doc.nodes()[id].parent;
-                }
+        for id in self.search_matches() {
+            let mut cur = Some(id);
+            while let Some(id) = cur {
+                include.insert(id);
+                cur = self.doc.nodes()[id].parent;
             }
         }
         include
@@ -330,7 +1216,7 @@ impl YamlEditorState {
     /// Node ids in display order, honouring collapsed containers (and the
     /// active search filter, which reveals matches with their ancestors).
     pub fn visible(&self) -> Vec<usize> {
-        let filter = self.search_query().map(|q| self.include_set(&q));
+        let filter = (self.search_active() && !self.search_jump).then(|| self.include_set());
         let mut out = Vec::new();
         for &root in self.doc.roots() {
             self.push_visible(root, filter.as_ref(), &mut out);
@@ -438,6 +1324,19 @@ impl YamlEditorState {
             .map(|p| document::path_to_string(p))
     }
 
+    /// The path and logical value of the selected scalar.
+    pub fn selected_value(&self) -> Result<(Vec<PathSeg>, String), String> {
+        let id = self.selected_id().ok_or("Nothing selected")?;
+        let node = &self.doc.nodes()[id];
+        if node.kind != NodeKind::Scalar {
+            return Err("Select a scalar value, not a mapping or sequence".to_string());
+        }
+        Ok((
+            node.path.clone(),
+            self.doc.logical_value(id).unwrap_or_default(),
+        ))
+    }
+
     /// Classify the selected node for contextual hints (scalar/container,
     /// encrypted/plain, expanded/collapsed).
     pub fn selection_kind(&self) -> Option<YamlSelectionKind> {
@@ -487,24 +1386,96 @@ impl YamlEditorState {
         }
         let logical = self.doc.logical_value(id).unwrap_or_default();
         self.editing = Some(TextField::from_text(&logical));
+        self.edit_target = EditTarget::Value;
         Ok(())
     }
 
-    pub fn cancel_edit(&mut self) {
-        self.editing = None;
+    /// Enter edit mode for the inline comment on the selected node's line.
+    pub fn begin_comment_edit(&mut self) -> Result<(), String> {
+        let id = self.selected_id().ok_or("Nothing selected")?;
+        if self.doc.format() != DocFormat::Yaml {
+            return Err(format!(
+                "Inline comments are not supported in {} files",
+                self.doc.format().label()
+            ));
+        }
+        let comment = self.doc.nodes()[id].inline_comment.clone();
+        self.editing = Some(TextField::from_text(&comment.unwrap_or_default()));
+        self.edit_target = EditTarget::Comment;
+        Ok(())
     }
 
-    /// Apply the in-progress manual edit to the selected scalar. The value is
-    /// written as a quoted string, matching how encrypt/decrypt results are
-    /// stored.
-    pub fn apply_edit(&mut self) -> Result<(), String> {
+    /// Start naming a bookmark on the selected node; the name defaults to
+    /// its key.
+    pub fn begin_bookmark(&mut self) -> Result<(), String> {
+        let id = self.selected_id().ok_or("Nothing selected")?;
+        let name = match self.doc.nodes()[id].path.last() {
+            Some(PathSeg::Key(k)) => k.clone(),
+            _ => document::path_to_string(&self.doc.nodes()[id].path),
+        };
+        self.editing = Some(TextField::from_text(&name));
+        self.edit_target = EditTarget::Bookmark;
+        Ok(())
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing = None;
+        self.edit_target = EditTarget::Value;
+    }
+
+    /// Apply the in-progress manual edit to the selected node. A value is
+    /// written as a quoted string (an escaped value in `.properties`),
+    /// matching how encrypt/decrypt results are stored; an empty comment
+    /// removes the inline comment.
+    pub fn apply_edit(&mut self) -> Result<(), String> {
         let field = self.editing.take().ok_or("Not editing")?;
+        let target = std::mem::take(&mut self.edit_target);
         let id = self.selected_id().ok_or("Nothing selected")?;
-        let new_source = document::serialize_scalar_quoted(&field.value());
-        let text = self.doc.replace_scalar_source(id, &new_source)?;
-        self.snapshot();
-        self.doc = Document::parse(&text);
-        self.set_msg("Value updated.", false);
+        match target {
+            EditTarget::Value => {
+                let new_source = self.doc.encode_value(&field.value());
+                if self.doc.value_source(id) == Some(new_source.as_str()) {
+                    return Ok(());
+                }
+                self.replace_value(id, &new_source, &self.node_label("Edit", id))?;
+                self.set_msg("Value updated.", false);
+            }
+            EditTarget::Comment => {
+                let comment = field.value();
+                let text = self.doc.replace_inline_comment(id, &comment)?;
+                if text == self.doc.raw() {
+                    return Ok(());
+                }
+                let verb = if comment.trim().is_empty() {
+                    "Remove comment on"
+                } else {
+                    "Comment"
+                };
+                self.history
+                    .record(self.node_label(verb, id), self.doc.raw(), &text);
+                self.doc = Document::parse_as(&text, self.doc.format());
+                self.set_msg(
+                    if comment.trim().is_empty() {
+                        "Comment removed."
+                    } else {
+                        "Comment updated."
+                    },
+                    false,
+                );
+            }
+            EditTarget::Bookmark => {
+                let name = field.value().trim().to_string();
+                if name.is_empty() {
+                    return Err("A bookmark needs a name".to_string());
+                }
+                let file = self.file_path.clone().ok_or("No file open")?;
+                let path = document::path_to_string(&self.doc.nodes()[id].path);
+                match self.bookmarks.add(&file, &name, path) {
+                    Ok(()) => self.set_msg(format!("Bookmarked as \"{name}\"."), false),
+                    Err(e) => self.set_msg(format!("Bookmarked for this session only: {e}"), true),
+                }
+            }
+        }
         Ok(())
     }
 
@@ -578,18 +1549,19 @@ impl YamlEditorState {
             self.set_msg("Result ignored: the value changed while running.", true);
             return;
         }
-        // Always write the result as a quoted string: the cipher wrapper needs
-        // quoting anyway, and a decrypted value is kept quoted for consistency.
+        // Always write the result as a quoted YAML string: the cipher wrapper
+        // needs quoting anyway, and a decrypted value is kept quoted for
+        // consistency. `.properties` values need only escaping.
         let new_source = match pending.op {
-            Operation::Encrypt => {
-                document::serialize_scalar_quoted(&document::wrap_cipher(&output))
-            }
-            Operation::Decrypt => document::serialize_scalar_quoted(&output),
+            Operation::Encrypt => self.doc.encode_value(&document::wrap_cipher(&output)),
+            Operation::Decrypt => self.doc.encode_value(&output),
+        };
+        let verb = match pending.op {
+            Operation::Encrypt => "Encrypt",
+            Operation::Decrypt => "Decrypt",
         };
-        match self.doc.replace_scalar_source(id, &new_source) {
-            Ok(text) => {
-                self.snapshot();
-                self.doc = Document::parse(&text);
+        match self.replace_value(id, &new_source, &self.node_label(verb, id)) {
+            Ok(()) => {
                 self.set_msg(
                     match pending.op {
                         Operation::Encrypt => "Encrypted.",
@@ -611,13 +1583,41 @@ impl YamlEditorState {
         self.search_editing = true;
     }
 
-    pub fn search_field(&mut self) -> Option<&mut TextField> {
-        self.search.as_mut()
+    pub fn search_insert(&mut self, c: char) {
+        if let Some(field) = self.search.as_mut() {
+            field.insert(c);
+        }
+        self.refresh_search();
+    }
+
+    pub fn search_backspace(&mut self) {
+        if let Some(field) = self.search.as_mut() {
+            field.backspace();
+        }
+        self.refresh_search();
+    }
+
+    /// Compile the query once for regex mode, rather than on every render.
+    fn refresh_search(&mut self) {
+        self.search_regex = match (self.search_mode, self.search_query()) {
+            (SearchMode::Regex, Some(query)) => Some(search_regex(&query)),
+            _ => None,
+        };
     }
 
-    /// Stop editing but keep the filter applied.
+    /// Stop editing but keep the filter applied. Without filtering, the
+    /// first match after the selection is selected instead.
     pub fn confirm_search(&mut self) {
         self.search_editing = false;
+        if self.search_jump {
+            let on_match = self
+                .selected_id()
+                .is_some_and(|id| self.search_matches().contains(&id));
+            if self.search_active() && !on_match {
+                self.search_step(1);
+            }
+            return;
+        }
         // Keep the selection inside the filtered set.
         if self
             .selected_id()
@@ -631,10 +1631,63 @@ impl YamlEditorState {
         }
     }
 
-    /// Clear the filter entirely.
+    /// Switch to the next search mode, keeping the typed query.
+    pub fn toggle_search_mode(&mut self) {
+        self.search_mode = self.search_mode.next();
+        self.refresh_search();
+    }
+
+    /// Switch to the next value filter.
+    pub fn cycle_search_filter(&mut self) {
+        self.search_filter = self.search_filter.next();
+    }
+
+    /// Switch between filtering the tree and only stepping through matches.
+    pub fn toggle_search_jump(&mut self) {
+        self.search_jump = !self.search_jump;
+    }
+
+    /// Clear the query and the value filter entirely.
     pub fn clear_search(&mut self) {
         self.search = None;
         self.search_editing = false;
+        self.search_regex = None;
+        self.search_filter = SearchFilter::All;
+    }
+
+    /// `n` / `N`: select the next or previous match after the selection,
+    /// wrapping around, and unfold its parents.
+    pub fn search_step(&mut self, delta: isize) {
+        if let Some(e) = self.search_error() {
+            self.set_msg(format!("Invalid query: {e}"), true);
+            return;
+        }
+        if !self.search_active() {
+            self.set_msg("No search; / starts one.", true);
+            return;
+        }
+        let matches = self.search_matches();
+        if matches.is_empty() {
+            self.set_msg("No matches.", true);
+            return;
+        }
+        let current = self.selected_id();
+        let i = match current.and_then(|c| matches.iter().position(|&m| m == c)) {
+            Some(i) => (i as isize + delta).rem_euclid(matches.len() as isize) as usize,
+            None => {
+                // From outside the matches: the first one after (or before)
+                // the selection in document order.
+                let after = current.map_or(0, |c| matches.partition_point(|&m| m < c));
+                if delta > 0 {
+                    after % matches.len()
+                } else {
+                    (after + matches.len() - 1) % matches.len()
+                }
+            }
+        };
+        let path = self.doc.nodes()[matches[i]].path.clone();
+        self.reveal_path(path);
+        self.set_msg(format!("Match {}/{}.", i + 1, matches.len()), false);
     }
 
     // --- bulk encrypt / decrypt --------------------------------------------
@@ -664,23 +1717,44 @@ impl YamlEditorState {
         }
     }
 
-    /// Queue a bulk encrypt/decrypt of every applicable scalar under the
+    /// Queue a bulk encrypt/decrypt of every applicable scalar in the marked
+    /// nodes, under every match of an active path query, or else under the
     /// selected node. Call `next_bulk_value` to drive it.
     pub fn start_bulk(&mut self, op: Operation) -> Result<(), String> {
         if self.crypto_in_progress || self.bulk_op.is_some() {
             return Err("A crypto operation is already running".to_string());
         }
-        let sel = self.selected_id().ok_or("Nothing selected")?;
         let mut targets = Vec::new();
-        self.collect_bulk_targets(sel, op, &mut targets);
+        let marked = self.marked_ids();
+        let scope = if !marked.is_empty() {
+            for id in marked {
+                self.collect_bulk_targets(id, op, &mut targets);
+            }
+            let mut seen = HashSet::new();
+            targets.retain(|p| seen.insert(p.clone()));
+            "in the marked nodes"
+        } else if let Some(query) = self.active_path_query() {
+            for id in self.doc.query(&query) {
+                self.collect_bulk_targets(id, op, &mut targets);
+            }
+            // A match and its matching descendant would queue a value twice.
+            let mut seen = HashSet::new();
+            targets.retain(|p| seen.insert(p.clone()));
+            "matching the query"
+        } else {
+            let sel = self.selected_id().ok_or("Nothing selected")?;
+            self.collect_bulk_targets(sel, op, &mut targets);
+            "under the selection"
+        };
         if targets.is_empty() {
             return Err(match op {
-                Operation::Encrypt => "No plaintext values to encrypt under the selection".into(),
-                Operation::Decrypt => "No encrypted values to decrypt under the selection".into(),
+                Operation::Encrypt => format!("No plaintext values to encrypt {scope}"),
+                Operation::Decrypt => format!("No encrypted values to decrypt {scope}"),
             });
         }
         self.bulk_op = Some(op);
-        self.bulk_queue = targets;
+        self.bulk_queue = targets.into();
+        self.history.begin_group();
         Ok(())
     }
 
@@ -688,8 +1762,7 @@ impl YamlEditorState {
     /// `None` when the queue is drained.
     pub fn next_bulk_value(&mut self) -> Option<(Operation, String)> {
         let op = self.bulk_op?;
-        while !self.bulk_queue.is_empty() {
-            let path = self.bulk_queue.remove(0);
+        while let Some(path) = self.bulk_queue.pop_front() {
             if let Some(id) = self.doc.find_by_path(&path) {
                 if let Ok(value) = self.begin_crypto_for(id, op) {
                     return Some((op, value));
@@ -697,193 +1770,1710 @@ impl YamlEditorState {
             }
         }
         self.bulk_op = None;
+        let verb = match op {
+            Operation::Encrypt => "encrypt",
+            Operation::Decrypt => "decrypt",
+        };
+        self.history
+            .end_group(|n| format!("Bulk {verb} {n} value(s)"));
         self.set_msg("Bulk operation complete.", false);
         None
     }
 
-    // --- save / restore ----------------------------------------------------
+    // --- marks -------------------------------------------------------------
 
-    /// Whether the file changed on disk since it was opened or last saved.
-    pub fn externally_modified(&self) -> bool {
-        match (&self.file_path, self.disk_hash) {
-            (Some(p), Some(h)) => std::fs::read_to_string(p)
-                .map(|c| hash(&c) != h)
-                .unwrap_or(false),
-            _ => false,
+    /// Mark or unmark the selected node.
+    pub fn toggle_mark(&mut self) {
+        let Some(path) = self
+            .selected_id()
+            .map(|id| self.doc.nodes()[id].path.clone())
+        else {
+            return;
+        };
+        if !self.marks.remove(&path) {
+            self.marks.insert(path);
         }
     }
 
-    /// Save the working document atomically to the original file.
-    pub fn save(&mut self) -> Result<(), String> {
-        let path = self.file_path.clone().ok_or("No file open")?;
-        Document::validate(self.doc.raw()).map_err(|e| format!("Invalid YAML: {e}"))?;
-        atomic_write(&path, self.doc.raw())?;
-        self.initial_content = self.doc.raw().to_string();
-        self.disk_hash = Some(hash(self.doc.raw()));
-        // The saved content is the new baseline: clear per-property highlights.
-        self.rebuild_baseline();
-        self.set_msg("Saved.", false);
-        Ok(())
+    pub fn clear_marks(&mut self) {
+        if self.marks.is_empty() {
+            self.set_msg("Nothing marked.", false);
+        } else {
+            self.marks.clear();
+            self.set_msg("Marks cleared.", false);
+        }
     }
 
-    /// Restore the working document to the exact initial snapshot. Nothing is
-    /// written to disk.
-    pub fn restore(&mut self) {
-        self.doc = Document::parse(&self.initial_content);
-        self.editing = None;
-        self.pending = None;
-        // Keep the selection valid.
-        if self.selected_id().is_none() {
-            self.selected_path = self
-                .visible()
-                .first()
-                .map(|&id| self.doc.nodes()[id].path.clone());
-        }
-        self.set_msg("Restored to the initially opened content.", false);
+    pub fn is_marked(&self, id: usize) -> bool {
+        self.doc
+            .node(id)
+            .is_some_and(|n| self.marks.contains(&n.path))
     }
 
-    // --- open modal & confirmations ---------------------------------------
+    /// Marked nodes that exist in the current document, in document order.
+    /// Marks on paths an edit removed stay dormant until the path is back.
+    pub fn marked_ids(&self) -> Vec<usize> {
+        self.doc
+            .nodes()
+            .iter()
+            .filter(|n| self.marks.contains(&n.path))
+            .map(|n| n.id)
+            .collect()
+    }
 
-    pub fn open_dialog(&mut self) {
-        self.open_modal = Some(OpenModal::default());
+    /// The marked values as `path: value` lines, ciphertext left wrapped,
+    /// for the clipboard.
+    pub fn marked_text(&self) -> Result<String, String> {
+        let marked = self.marked_ids();
+        if marked.is_empty() {
+            return Err("Nothing marked (Space marks a node)".to_string());
+        }
+        let mut seen = HashSet::new();
+        let mut lines = Vec::new();
+        for id in marked {
+            let mut scalars = Vec::new();
+            self.collect_scalars(id, &mut scalars);
+            for id in scalars {
+                if seen.insert(id) {
+                    let node = &self.doc.nodes()[id];
+                    let value = self.doc.logical_value(id).unwrap_or_default();
+                    lines.push(format!("{}: {value}", document::path_to_string(&node.path)));
+                }
+            }
+        }
+        Ok(lines.join("\n"))
     }
 
-    pub fn close_dialog(&mut self) {
-        self.open_modal = None;
+    // --- copying ------------------------------------------------------------
+
+    /// Open the copy menu with what can be copied from the selection and the
+    /// marks.
+    pub fn open_copy_menu(&mut self) {
+        let items: Vec<CopyItem> = CopyItem::ALL
+            .into_iter()
+            .filter(|&item| self.copy_offered(item))
+            .collect();
+        if items.is_empty() {
+            self.set_msg("Nothing selected to copy.", true);
+            return;
+        }
+        self.copy_menu = Some(CopyMenu { items, selected: 0 });
     }
 
-    /// Handle activation inside the open modal (Enter). Returns true when a file
-    /// was opened (so the modal can close).
-    pub fn modal_activate(&mut self) -> bool {
-        let Some(modal) = self.open_modal.as_mut() else {
-            return false;
-        };
-        let candidate = match modal.mode {
-            OpenMode::Browse => modal.browser.activate(),
-            OpenMode::Path => Some(std::path::PathBuf::from(modal.path_input.value())),
-        };
-        let Some(path) = candidate else {
-            return false; // navigated into a directory
+    /// Whether the menu lists `item`. Plaintext is listed for every
+    /// encrypted value, and explains itself when it is not peeked yet.
+    fn copy_offered(&self, item: CopyItem) -> bool {
+        let Some(id) = self.selected_id() else {
+            return item == CopyItem::Marked && !self.marks.is_empty();
         };
-        let input = path.to_string_lossy().to_string();
-        // Replacing an unsaved document: defer until the user chooses
-        // Save / Discard / Cancel.
-        if self.dirty() {
-            self.guard = Some(Guard::Open(input));
-            self.open_modal = None;
-            return true;
+        let encrypted = self
+            .doc
+            .logical_value(id)
+            .is_some_and(|v| document::is_wrapped(&v));
+        match item {
+            CopyItem::Path | CopyItem::Subtree => true,
+            CopyItem::Value => self.doc.value_source(id).is_some(),
+            CopyItem::Ciphertext | CopyItem::Plaintext => encrypted,
+            CopyItem::Marked => !self.marks.is_empty(),
         }
-        match self.open_path(&input) {
-            Ok(()) => {
-                self.open_modal = None;
-                true
-            }
-            Err(e) => {
-                if let Some(m) = self.open_modal.as_mut() {
-                    m.error = Some(e);
-                }
-                false
-            }
+    }
+
+    pub fn copy_move(&mut self, delta: isize) {
+        if let Some(menu) = self.copy_menu.as_mut() {
+            let last = menu.items.len().saturating_sub(1) as isize;
+            menu.selected = (menu.selected as isize + delta).clamp(0, last) as usize;
         }
     }
 
-    // --- unsaved-changes guard --------------------------------------------
+    /// Enter in the menu: copy the selected item.
+    pub fn copy_selected(&mut self) {
+        let item = self
+            .copy_menu
+            .as_ref()
+            .and_then(|m| m.items.get(m.selected).copied());
+        if let Some(item) = item {
+            self.copy(item);
+        }
+    }
 
-    /// The pending guarded action, if the UI is waiting on a Save/Discard/Cancel
-    /// choice.
-    pub fn guard(&self) -> Option<&Guard> {
-        self.guard.as_ref()
+    /// Copy `item` to the system clipboard and close the menu. Does nothing
+    /// for an item the menu does not list.
+    pub fn copy(&mut self, item: CopyItem) {
+        if !self
+            .copy_menu
+            .as_ref()
+            .is_some_and(|m| m.items.contains(&item))
+        {
+            return;
+        }
+        self.copy_menu = None;
+        let copied = self
+            .copy_text(item)
+            .and_then(|(text, what)| crate::clipboard::copy(&text).map(|()| what));
+        match copied {
+            Ok(what) => self.set_msg(format!("Copied {what}."), false),
+            Err(e) => self.set_msg(e, true),
+        }
     }
 
-    /// Guard a quit: returns `true` if there are unsaved changes (a prompt was
-    /// raised) or `false` if it is safe to quit immediately.
-    pub fn guard_quit(&mut self) -> bool {
-        if self.dirty() {
-            self.guard = Some(Guard::Quit);
-            true
+    /// The text `item` copies, and what it is for the status message.
+    pub fn copy_text(&self, item: CopyItem) -> Result<(String, String), String> {
+        let selected = || {
+            let id = self.selected_id().ok_or("Nothing selected")?;
+            Ok::<_, String>((id, document::path_to_string(&self.doc.nodes()[id].path)))
+        };
+        match item {
+            CopyItem::Marked => {
+                let text = self.marked_text()?;
+                let n = text.lines().count();
+                Ok((text, format!("{n} marked value(s)")))
+            }
+            CopyItem::Path => Ok((selected()?.1, "the path".to_string())),
+            CopyItem::Value => {
+                let (id, path) = selected()?;
+                let token = self.doc.value_source(id).ok_or("Not a scalar value")?;
+                Ok((token.to_string(), format!("the value of {path}")))
+            }
+            CopyItem::Ciphertext => {
+                let (id, path) = selected()?;
+                let value = self.doc.logical_value(id).unwrap_or_default();
+                if !document::is_wrapped(&value) {
+                    return Err(format!("{path} is not encrypted"));
+                }
+                Ok((
+                    document::unwrap_cipher(&value),
+                    format!("the ciphertext of {path}"),
+                ))
+            }
+            CopyItem::Plaintext => {
+                let (id, path) = selected()?;
+                let plain = self
+                    .peeked(id)
+                    .ok_or("Peek it first (o) to decrypt it for copying")?;
+                Ok((plain.to_string(), format!("the plaintext of {path}")))
+            }
+            CopyItem::Subtree => {
+                let (id, path) = selected()?;
+                let text = self.doc.subtree_yaml(id).ok_or("Cannot render as YAML")?;
+                Ok((text, format!("{path} as YAML")))
+            }
+        }
+    }
+
+    fn collect_scalars(&self, id: usize, out: &mut Vec<usize>) {
+        let node = &self.doc.nodes()[id];
+        if node.kind == NodeKind::Scalar {
+            out.push(id);
         } else {
-            false
+            for &c in &node.children {
+                self.collect_scalars(c, out);
+            }
         }
     }
 
-    /// Resolve the guard by saving first. On success the guard is cleared and
-    /// the pending action returned; on failure the guard is kept and `None`
-    /// returned (with an error message set).
-    pub fn guard_save(&mut self) -> Option<Guard> {
-        if let Err(e) = self.save() {
-            self.set_msg(format!("Save failed: {e}"), true);
+    // --- suspicious values -------------------------------------------------
+
+    /// Why scalar `id` looks like a plaintext secret, if it does.
+    pub fn suspicion(&self, id: usize) -> Option<Signal> {
+        let node = self.doc.node(id)?;
+        if node.kind != NodeKind::Scalar {
             return None;
         }
-        self.guard.take()
+        let value = self.doc.logical_value(id)?;
+        self.secret_rules.check(&node.path, &value)
     }
 
-    /// Resolve the guard by discarding unsaved changes; returns the pending
-    /// action.
-    pub fn guard_discard(&mut self) -> Option<Guard> {
-        self.guard.take()
+    fn collect_suspicious(&self, id: usize, out: &mut Vec<(Vec<PathSeg>, Signal)>) {
+        let node = &self.doc.nodes()[id];
+        if node.kind == NodeKind::Scalar {
+            if let Some(signal) = self.suspicion(id) {
+                out.push((node.path.clone(), signal));
+            }
+        } else {
+            for &c in &node.children {
+                self.collect_suspicious(c, out);
+            }
+        }
     }
 
-    /// Dismiss the guard, keeping the document as-is.
-    pub fn guard_cancel(&mut self) {
-        self.guard = None;
+    /// Every suspicious value in the file, in document order.
+    pub fn suspicious(&self) -> Vec<(Vec<PathSeg>, Signal)> {
+        let mut out = Vec::new();
+        for &root in self.doc.roots() {
+            self.collect_suspicious(root, &mut out);
+        }
+        out
     }
 
-    /// Restore, asking for confirmation first if there are unsaved changes.
-    pub fn request_restore(&mut self) {
-        if self.dirty() {
-            self.confirm = Some(Confirm::Restore);
-        } else {
-            self.restore();
+    /// Whether any value under container `id` is suspicious.
+    pub fn subtree_suspicious(&self, id: usize) -> bool {
+        let node = &self.doc.nodes()[id];
+        match node.kind {
+            NodeKind::Scalar => self.suspicion(id).is_some(),
+            _ => node.children.iter().any(|&c| self.subtree_suspicious(c)),
         }
     }
 
-    /// Save, asking to confirm if the file changed on disk since it was opened.
-    pub fn request_save(&mut self) {
+    pub fn open_suspicious(&mut self) {
         if !self.is_open() {
-            self.set_msg("No file open.", true);
+            self.set_msg("Open a file first.", true);
             return;
         }
-        if self.externally_modified() {
-            self.confirm = Some(Confirm::OverwriteExternal);
-            self.set_msg("File changed on disk. Confirm to overwrite.", true);
+        let items = self.suspicious();
+        if items.is_empty() {
+            self.set_msg("No plaintext values look like secrets.", false);
             return;
         }
-        if let Err(e) = self.save() {
-            self.set_msg(format!("Save failed: {e}"), true);
-        }
+        self.suspicious_panel = Some(SuspiciousPanel { items, selected: 0 });
     }
 
-    pub fn confirm_yes(&mut self) {
-        match self.confirm.take() {
-            Some(Confirm::Restore) => self.restore(),
-            Some(Confirm::OverwriteExternal) => {
-                if let Err(e) = self.save() {
-                    self.set_msg(format!("Save failed: {e}"), true);
-                }
-            }
-            None => {}
+    pub fn suspicious_move(&mut self, delta: isize) {
+        if let Some(panel) = self.suspicious_panel.as_mut() {
+            let last = panel.items.len().saturating_sub(1) as isize;
+            panel.selected = (panel.selected as isize + delta).clamp(0, last) as usize;
         }
     }
 
-    pub fn confirm_no(&mut self) {
-        self.confirm = None;
+    /// Enter in the panel: close it and select the value.
+    pub fn suspicious_activate(&mut self) {
+        let Some(panel) = self.suspicious_panel.take() else {
+            return;
+        };
+        if let Some((path, _)) = panel.items.get(panel.selected) {
+            self.reveal_path(path.clone());
+        }
     }
-}
 
-pub enum ToggleResult {
-    Toggled,
-    EditScalar,
-}
+    /// Queue a bulk encrypt of exactly the values listed in the panel, and
+    /// close it. Driven by `next_bulk_value` like any bulk encrypt.
+    pub fn start_encrypt_suspicious(&mut self) -> Result<(), String> {
+        if self.crypto_in_progress || self.bulk_op.is_some() {
+            return Err("A crypto operation is already running".to_string());
+        }
+        let panel = self.suspicious_panel.take().ok_or("No suspicious values")?;
+        self.bulk_op = Some(Operation::Encrypt);
+        self.bulk_queue = panel.items.into_iter().map(|(path, _)| path).collect();
+        self.history.begin_group();
+        Ok(())
+    }
 
-fn first_line(s: &str) -> String {
-    s.lines().next().unwrap_or("").to_string()
-}
+    // --- re-key ------------------------------------------------------------
 
-/// Expand `~` and normalize a user-supplied path.
-fn resolve_path(input: &str) -> Result<PathBuf, String> {
-    let trimmed = input.trim();
-    if trimmed.is_empty() {
-        return Err("Enter a path".to_string());
+    /// Open the target picker for re-keying the file from `from`. `envs` are
+    /// all configured environments.
+    pub fn open_rekey_picker(&mut self, from: Environment, envs: &[Environment]) {
+        if !self.is_open() {
+            self.set_msg("Open a file first.", true);
+            return;
+        }
+        if self.crypto_in_progress || self.bulk_op.is_some() || self.verify.is_some() {
+            self.set_msg("A crypto operation is already running", true);
+            return;
+        }
+        let choices: Vec<Environment> = envs
+            .iter()
+            .filter(|e| e.name != from.name)
+            .cloned()
+            .collect();
+        if choices.is_empty() {
+            self.set_msg("Add the environment to re-key to first (a).", true);
+            return;
+        }
+        self.rekey_picker = Some(RekeyPicker {
+            from,
+            choices,
+            selected: 0,
+        });
+    }
+
+    pub fn rekey_picker_move(&mut self, delta: isize) {
+        if let Some(picker) = self.rekey_picker.as_mut() {
+            let last = picker.choices.len().saturating_sub(1) as isize;
+            picker.selected = (picker.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Enter in the picker: queue every encrypted value in the file. Call
+    /// `next_rekey_value` to drive it.
+    pub fn start_rekey(&mut self) -> Result<(), String> {
+        let Some(picker) = self.rekey_picker.take() else {
+            return Ok(());
+        };
+        let to = picker.choices[picker.selected].clone();
+        rekey::check_envs(&picker.from, &to)?;
+        let (targets, skipped) = rekey::plan(&self.doc);
+        if targets.is_empty() {
+            return Err("No encrypted values to re-key".to_string());
+        }
+        // The whole run is undone in one step.
+        self.history.begin_group();
+        self.rekey = Some(RekeyRun {
+            from: picker.from,
+            to,
+            total: targets.len(),
+            queue: targets.into(),
+            current: None,
+            summary: Summary {
+                skipped,
+                ..Summary::default()
+            },
+        });
+        self.rekey_report = None;
+        Ok(())
+    }
+
+    /// Prepare the next queued value, returning `(from, to, value)` to spawn,
+    /// or `None` once the run is finished (and its report shown).
+    pub fn next_rekey_value(&mut self) -> Option<(Environment, Environment, String)> {
+        let run = self.rekey.as_mut()?;
+        while let Some(path) = run.queue.pop_front() {
+            let Some(id) = self.doc.find_by_path(&path) else {
+                run.summary
+                    .skipped
+                    .push((path, "no longer exists".to_string()));
+                continue;
+            };
+            let value = self.doc.logical_value(id).unwrap_or_default();
+            let source = self.doc.value_source(id).unwrap_or("").to_string();
+            let done = run.total - run.queue.len();
+            run.current = Some((path, source));
+            self.crypto_in_progress = true;
+            let (from, to) = (run.from.clone(), run.to.clone());
+            let total = run.total;
+            self.set_msg(format!("Re-keying {done}/{total}…"), false);
+            return Some((from, to, value));
+        }
+        let run = self.rekey.take()?;
+        self.crypto_in_progress = false;
+        let to = run.to.name.clone();
+        self.history
+            .end_group(|n| format!("Re-key {n} value(s) to {to}"));
+        self.set_msg(
+            format!("Re-key complete: {}.", run.summary.counts()),
+            !run.summary.failed.is_empty(),
+        );
+        self.rekey_report = Some(RekeyReport {
+            from: run.from.name,
+            to: run.to.name,
+            summary: run.summary,
+            selected: 0,
+        });
+        None
+    }
+
+    /// Apply (or record as failed or stale) the re-keyed value in flight. The
+    /// new ciphertext replaces the old one directly; the plaintext never
+    /// reaches this side.
+    pub fn finish_rekey(&mut self, outcome: Result<String, String>) {
+        self.crypto_in_progress = false;
+        let Some(run) = self.rekey.as_mut() else {
+            return;
+        };
+        let Some((path, source)) = run.current.take() else {
+            return;
+        };
+        let id = self
+            .doc
+            .find_by_path(&path)
+            .filter(|&id| self.doc.value_source(id) == Some(source.as_str()));
+        let applied = match (outcome, id) {
+            (_, None) => {
+                run.summary
+                    .skipped
+                    .push((path, "changed while running".to_string()));
+                return;
+            }
+            (Err(e), _) => Err(e),
+            (Ok(wrapped), Some(id)) => {
+                let token = self.doc.encode_value(&wrapped);
+                self.doc
+                    .replace_scalar(id, &token)
+                    .map(|splice| self.history.record_splice("", splice))
+            }
+        };
+        match applied {
+            Ok(()) => run.summary.rotated.push(path),
+            Err(e) => run.summary.failed.push((path, e)),
+        }
+    }
+
+    pub fn rekey_in_progress(&self) -> bool {
+        self.rekey.is_some()
+    }
+
+    pub fn rekey_report_move(&mut self, delta: isize) {
+        if let Some(report) = self.rekey_report.as_mut() {
+            let last = report.rows().len().saturating_sub(1) as isize;
+            report.selected = (report.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Enter in the re-key report: close it and select the value.
+    pub fn rekey_report_activate(&mut self) {
+        let Some(report) = self.rekey_report.take() else {
+            return;
+        };
+        if let Some((_, path, _)) = report.rows().get(report.selected) {
+            self.reveal_path(path.to_vec());
+        }
+    }
+
+    // --- verify ------------------------------------------------------------
+
+    /// Paths of the `![...]` scalars in the subtrees of `roots`, in document
+    /// order and without duplicates.
+    fn encrypted_under(&self, roots: Vec<usize>) -> Vec<Vec<PathSeg>> {
+        let mut seen = HashSet::new();
+        let mut targets = Vec::new();
+        let mut stack: Vec<usize> = roots.into_iter().rev().collect();
+        while let Some(id) = stack.pop() {
+            let node = &self.doc.nodes()[id];
+            if !seen.insert(id) {
+                continue;
+            }
+            if node.kind == NodeKind::Scalar {
+                if self
+                    .doc
+                    .logical_value(id)
+                    .is_some_and(|v| document::is_wrapped(&v))
+                {
+                    targets.push(node.path.clone());
+                }
+            } else {
+                stack.extend(node.children.iter().rev());
+            }
+        }
+        targets
+    }
+
+    /// Queue every encrypted value under the selection (or every match of an
+    /// active path query), or in the whole file, to be decrypted with `env`.
+    /// Call `next_verify_value` to drive it.
+    pub fn start_verify(&mut self, env: Environment, whole_file: bool) -> Result<(), String> {
+        if self.crypto_in_progress || self.bulk_op.is_some() || self.rekey.is_some() {
+            return Err("A crypto operation is already running".to_string());
+        }
+        let (roots, scope) = if whole_file {
+            (self.doc.roots().to_vec(), "in the file")
+        } else if let Some(query) = self.active_path_query() {
+            (self.doc.query(&query), "matching the query")
+        } else {
+            let sel = self.selected_id().ok_or("Nothing selected")?;
+            (vec![sel], "under the selection")
+        };
+        let targets = self.encrypted_under(roots);
+        if targets.is_empty() {
+            return Err(format!("No encrypted values to verify {scope}"));
+        }
+        self.verify = Some(VerifyRun {
+            env,
+            total: targets.len(),
+            queue: targets.into(),
+            current: None,
+            failures: Vec::new(),
+        });
+        self.verify_report = None;
+        Ok(())
+    }
+
+    /// Prepare the next queued value, returning `(env, ciphertext)` to spawn,
+    /// or `None` once the run is finished (and its failures reported).
+    pub fn next_verify_value(&mut self) -> Option<(Environment, String)> {
+        let run = self.verify.as_mut()?;
+        while let Some(path) = run.queue.pop_front() {
+            let Some(id) = self.doc.find_by_path(&path) else {
+                continue;
+            };
+            let value = self.doc.logical_value(id).unwrap_or_default();
+            let source = self.doc.value_source(id).unwrap_or("").to_string();
+            let done = run.total - run.queue.len();
+            let total = run.total;
+            run.current = Some((path, source));
+            self.crypto_in_progress = true;
+            let env = run.env.clone();
+            self.set_msg(format!("Verifying {done}/{total}…"), false);
+            return Some((env, document::unwrap_cipher(&value)));
+        }
+        let run = self.verify.take()?;
+        self.crypto_in_progress = false;
+        let failures = run.failures;
+        if failures.is_empty() {
+            self.set_msg(
+                format!("All {} value(s) decrypt with {}.", run.total, run.env.name),
+                false,
+            );
+        } else {
+            self.set_msg(
+                format!(
+                    "{} of {} value(s) do not decrypt with {}.",
+                    failures.len(),
+                    run.total,
+                    run.env.name
+                ),
+                true,
+            );
+            self.verify_report = Some(VerifyReport {
+                env: run.env.name,
+                checked: run.total,
+                failures,
+                selected: 0,
+            });
+        }
+        None
+    }
+
+    /// Record whether the value in flight decrypted. The document is never
+    /// touched.
+    pub fn finish_verify(&mut self, outcome: Result<(), String>) {
+        self.crypto_in_progress = false;
+        let Some(run) = self.verify.as_mut() else {
+            return;
+        };
+        let Some((path, source)) = run.current.take() else {
+            return;
+        };
+        let verdict = match outcome {
+            Ok(()) => Verdict::Ok,
+            Err(e) => {
+                let reason = first_line(&e);
+                run.failures.push((path.clone(), reason.clone()));
+                Verdict::Fails(reason)
+            }
+        };
+        self.verified.insert(
+            path,
+            Verified {
+                env: run.env.name.clone(),
+                source,
+                verdict,
+            },
+        );
+    }
+
+    /// The verdict for scalar `id` under environment `env`, unless the value
+    /// changed since it was verified.
+    pub fn verdict(&self, id: usize, env: &str) -> Option<&Verdict> {
+        let node = self.doc.node(id)?;
+        let v = self.verified.get(&node.path)?;
+        (v.env == env && self.doc.value_source(id) == Some(v.source.as_str())).then_some(&v.verdict)
+    }
+
+    /// Whether any value under container `id` failed to decrypt with `env`.
+    pub fn subtree_fails(&self, id: usize, env: &str) -> bool {
+        let node = &self.doc.nodes()[id];
+        match node.kind {
+            NodeKind::Scalar => matches!(self.verdict(id, env), Some(Verdict::Fails(_))),
+            _ => node.children.iter().any(|&c| self.subtree_fails(c, env)),
+        }
+    }
+
+    pub fn verify_report_move(&mut self, delta: isize) {
+        if let Some(report) = self.verify_report.as_mut() {
+            let last = report.failures.len().saturating_sub(1) as isize;
+            report.selected = (report.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Enter in the verify report: close it and select the value.
+    pub fn verify_report_activate(&mut self) {
+        let Some(report) = self.verify_report.take() else {
+            return;
+        };
+        if let Some((path, _)) = report.failures.get(report.selected) {
+            self.reveal_path(path.clone());
+        }
+    }
+
+    // --- peek --------------------------------------------------------------
+
+    /// Turn peek on: queue the encrypted values under the selection (or every
+    /// match of an active path query), or every encrypted value visible in
+    /// the tree, to be decrypted with `env`. Call `next_peek_value` to drive
+    /// it.
+    pub fn start_peek(&mut self, env: Environment, all_visible: bool) -> Result<(), String> {
+        if self.crypto_in_progress
+            || self.bulk_op.is_some()
+            || self.rekey.is_some()
+            || self.verify.is_some()
+        {
+            return Err("A crypto operation is already running".to_string());
+        }
+        let (roots, scope) = if all_visible {
+            let scalars = self
+                .visible()
+                .into_iter()
+                .filter(|&id| self.doc.nodes()[id].kind == NodeKind::Scalar)
+                .collect();
+            (scalars, "in view")
+        } else if let Some(query) = self.active_path_query() {
+            (self.doc.query(&query), "matching the query")
+        } else {
+            let sel = self.selected_id().ok_or("Nothing selected")?;
+            (vec![sel], "under the selection")
+        };
+        let targets = self.encrypted_under(roots);
+        if targets.is_empty() {
+            return Err(format!("No encrypted values to peek {scope}"));
+        }
+        self.peek = Some(Peek {
+            env,
+            cache: HashMap::new(),
+            run: Some(PeekRun {
+                total: targets.len(),
+                queue: targets.into(),
+                current: None,
+                failed: 0,
+            }),
+        });
+        Ok(())
+    }
+
+    /// Turn peek off, wiping every decrypted preview.
+    pub fn stop_peek(&mut self) {
+        self.peek = None;
+    }
+
+    pub fn peek_active(&self) -> bool {
+        self.peek.is_some()
+    }
+
+    /// The environment the previews were decrypted with, while peek is on.
+    pub fn peek_env(&self) -> Option<&Environment> {
+        self.peek.as_ref().map(|p| &p.env)
+    }
+
+    /// Prepare the next queued value, returning `(env, ciphertext)` to spawn,
+    /// or `None` once every value has been tried.
+    pub fn next_peek_value(&mut self) -> Option<(Environment, String)> {
+        let peek = self.peek.as_mut()?;
+        let run = peek.run.as_mut()?;
+        while let Some(path) = run.queue.pop_front() {
+            let Some(id) = self.doc.find_by_path(&path) else {
+                continue;
+            };
+            let value = self.doc.logical_value(id).unwrap_or_default();
+            let source = self.doc.value_source(id).unwrap_or("").to_string();
+            let done = run.total - run.queue.len();
+            let total = run.total;
+            run.current = Some((path, source));
+            self.crypto_in_progress = true;
+            let env = peek.env.clone();
+            self.set_msg(format!("Peeking {done}/{total}…"), false);
+            return Some((env, document::unwrap_cipher(&value)));
+        }
+        let run = peek.run.take()?;
+        let env = peek.env.name.clone();
+        self.crypto_in_progress = false;
+        if run.failed == 0 {
+            self.set_msg(format!("Peeking {} value(s) with {env}.", run.total), false);
+        } else {
+            self.set_msg(
+                format!(
+                    "Peeking with {env}: {} of {} value(s) do not decrypt.",
+                    run.failed, run.total
+                ),
+                true,
+            );
+        }
+        None
+    }
+
+    /// Cache the plaintext of the value in flight. Ignored if peek was
+    /// turned off meanwhile.
+    pub fn finish_peek(&mut self, outcome: Result<String, String>) {
+        self.crypto_in_progress = false;
+        let Some(peek) = self.peek.as_mut() else {
+            return;
+        };
+        let Some(run) = peek.run.as_mut() else {
+            return;
+        };
+        let Some((path, source)) = run.current.take() else {
+            return;
+        };
+        match outcome {
+            Ok(plain) => {
+                peek.cache.insert(path, Peeked { source, plain });
+            }
+            Err(_) => run.failed += 1,
+        }
+    }
+
+    /// The decrypted preview of scalar `id`, unless the value changed since.
+    pub fn peeked(&self, id: usize) -> Option<&str> {
+        let node = self.doc.node(id)?;
+        let p = self.peek.as_ref()?.cache.get(&node.path)?;
+        (self.doc.value_source(id) == Some(p.source.as_str())).then_some(p.plain.as_str())
+    }
+
+    // --- review ------------------------------------------------------------
+
+    /// Show the unsaved changes against the file as opened or last saved.
+    pub fn open_review(&mut self) {
+        if !self.dirty() {
+            self.set_msg("No unsaved changes to review.", false);
+            return;
+        }
+        self.review = Some(ReviewView {
+            hunks: review::review(&self.initial_content, &self.doc),
+            selected: 0,
+        });
+    }
+
+    pub fn close_review(&mut self) {
+        self.review = None;
+    }
+
+    pub fn review_move(&mut self, delta: isize) {
+        if let Some(view) = self.review.as_mut() {
+            let last = view.hunks.len().saturating_sub(1) as isize;
+            view.selected = (view.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Put the selected hunk's saved lines back, as one undo step. Refused if
+    /// the file would no longer parse without the neighbouring hunks.
+    pub fn review_revert(&mut self) {
+        let Some(hunk) = self
+            .review
+            .as_ref()
+            .and_then(|view| view.hunks.get(view.selected))
+        else {
+            return;
+        };
+        let text = review::revert(&self.initial_content, self.doc.raw(), hunk);
+        let label = match hunk.changes.as_slice() {
+            [] => "Revert a comment or layout change".to_string(),
+            [one] => format!("Revert {}", document::path_to_string(&one.path)),
+            [first, rest @ ..] => format!(
+                "Revert {} and {} more",
+                document::path_to_string(&first.path),
+                rest.len()
+            ),
+        };
+        if let Err(e) = Document::validate_as(&text, self.doc.format()) {
+            self.set_msg(format!("Cannot revert this change on its own: {e}"), true);
+            return;
+        }
+        self.change_doc(label, text);
+        let hunks = review::review(&self.initial_content, &self.doc);
+        if hunks.is_empty() {
+            self.review = None;
+            self.set_msg("All changes reverted.", false);
+        } else if let Some(view) = self.review.as_mut() {
+            view.selected = view.selected.min(hunks.len() - 1);
+            view.hunks = hunks;
+            self.set_msg("Change reverted.", false);
+        }
+    }
+
+    /// Enter in the review: close it and select the first changed value of
+    /// the hunk, if it is still in the document.
+    pub fn review_activate(&mut self) {
+        let Some(view) = self.review.take() else {
+            return;
+        };
+        let path = view
+            .hunks
+            .get(view.selected)
+            .and_then(|h| h.changes.iter().find(|c| c.after.is_some()))
+            .map(|c| c.path.clone());
+        if let Some(path) = path {
+            self.reveal_path(path);
+        }
+    }
+
+    // --- save / restore ----------------------------------------------------
+
+    /// Whether the file changed on disk since it was opened or last saved.
+    pub fn externally_modified(&self) -> bool {
+        match (&self.file_path, self.disk_hash) {
+            (Some(p), Some(h)) => std::fs::read_to_string(p)
+                .map(|c| hash(&c) != h)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Save the working document atomically to the original file.
+    pub fn save(&mut self) -> Result<(), String> {
+        let path = self.file_path.clone().ok_or("No file open")?;
+        let format = self.doc.format();
+        Document::validate_as(self.doc.raw(), format)
+            .map_err(|e| format!("Invalid {}: {e}", format.label()))?;
+        let previous = std::fs::read_to_string(&path).ok();
+        atomic_write(&path, self.doc.raw())?;
+        self.initial_content = self.doc.raw().to_string();
+        self.disk_hash = Some(hash(self.doc.raw()));
+        self.ignored_disk = None;
+        // The saved content is the new baseline: clear per-property highlights.
+        self.rebuild_baseline();
+        let backed_up = self.backups.as_ref().map_or(Ok(()), |store| {
+            store.record(
+                &path,
+                previous.as_deref(),
+                self.doc.raw(),
+                self.save_env.as_deref(),
+            )
+        });
+        match backed_up {
+            Ok(()) => self.set_msg("Saved.", false),
+            Err(e) => self.set_msg(format!("Saved, but the backup failed: {e}"), true),
+        }
+        Ok(())
+    }
+
+    /// Restore the working document to the exact initial snapshot. Nothing is
+    /// written to disk.
+    pub fn restore(&mut self) {
+        let text = self.initial_content.clone();
+        self.history
+            .record("Restore the opened file", self.doc.raw(), &text);
+        self.doc = Document::parse_as(&text, self.doc.format());
+        self.editing = None;
+        self.pending = None;
+        // Keep the selection valid.
+        if self.selected_id().is_none() {
+            self.selected_path = self
+                .visible()
+                .first()
+                .map(|&id| self.doc.nodes()[id].path.clone());
+        }
+        self.set_msg("Restored to the initially opened content.", false);
+    }
+
+    // --- backups ------------------------------------------------------------
+
+    /// List the open file's backups with what loading each would change.
+    pub fn open_backups(&mut self) {
+        let (Some(path), Some(store)) = (self.file_path.as_ref(), self.backups.as_ref()) else {
+            self.set_msg(
+                if self.is_open() {
+                    "Backups are turned off."
+                } else {
+                    "Open a file first."
+                },
+                true,
+            );
+            return;
+        };
+        let backups: Vec<(Backup, Vec<String>)> = store
+            .list(path)
+            .into_iter()
+            .map(|b| {
+                let changes = match b.read() {
+                    Ok(text) => backup::summaries(&backup::diff(&self.doc, &text)),
+                    Err(e) => vec![format!("unreadable: {e}")],
+                };
+                (b, changes)
+            })
+            .collect();
+        if backups.is_empty() {
+            self.set_msg("No backups of this file yet; they are kept on save.", false);
+            return;
+        }
+        self.backup_view = Some(BackupView {
+            backups,
+            selected: 0,
+        });
+    }
+
+    pub fn backup_move(&mut self, delta: isize) {
+        if let Some(view) = self.backup_view.as_mut() {
+            let last = view.backups.len().saturating_sub(1) as isize;
+            view.selected = (view.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Enter in the backups: the selected version becomes the working
+    /// document, as one undo step. Nothing is written until saved.
+    pub fn backup_load(&mut self) {
+        let Some(view) = self.backup_view.take() else {
+            return;
+        };
+        if self.history_locked() {
+            return;
+        }
+        let Some((backup, _)) = view.backups.get(view.selected) else {
+            return;
+        };
+        let text = match backup.read() {
+            Ok(text) => text,
+            Err(e) => {
+                self.set_msg(format!("Cannot read the backup: {e}"), true);
+                return;
+            }
+        };
+        if let Err(e) = Document::validate_as(&text, self.doc.format()) {
+            self.set_msg(
+                format!("The backup does not parse: {}", first_line(&e)),
+                true,
+            );
+            return;
+        }
+        let when = backup.when();
+        self.change_doc(format!("Load the backup from {when}"), text);
+        self.set_msg(
+            format!("Loaded the backup from {when}; save to keep it."),
+            false,
+        );
+    }
+
+    // --- go to & bookmarks ---------------------------------------------------
+
+    pub fn open_goto(&mut self) {
+        if !self.is_open() {
+            self.set_msg("Open a file first.", true);
+            return;
+        }
+        self.goto = Some(GotoPrompt::default());
+    }
+
+    /// The paths matching the go-to input, ignoring case: an exact match
+    /// first, then the paths starting with it, then the ones containing it,
+    /// each in document order.
+    pub fn goto_candidates(&self) -> Vec<String> {
+        let Some(prompt) = &self.goto else {
+            return Vec::new();
+        };
+        let typed = prompt.field.value().trim().to_lowercase();
+        let mut ranked: Vec<(u8, String)> = self
+            .doc
+            .nodes()
+            .iter()
+            .filter_map(|n| {
+                let path = document::path_to_string(&n.path);
+                let lower = path.to_lowercase();
+                let rank = if lower == typed {
+                    0
+                } else if lower.starts_with(&typed) {
+                    1
+                } else if lower.contains(&typed) {
+                    2
+                } else {
+                    return None;
+                };
+                Some((rank, path))
+            })
+            .collect();
+        ranked.sort_by_key(|(rank, _)| *rank);
+        ranked.into_iter().map(|(_, path)| path).collect()
+    }
+
+    pub fn goto_field(&mut self) -> Option<&mut TextField> {
+        let prompt = self.goto.as_mut()?;
+        prompt.selected = 0;
+        Some(&mut prompt.field)
+    }
+
+    pub fn goto_move(&mut self, delta: isize) {
+        let last = self.goto_candidates().len().saturating_sub(1) as isize;
+        if let Some(prompt) = self.goto.as_mut() {
+            prompt.selected = (prompt.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Tab in the prompt: complete the input to the selected candidate.
+    pub fn goto_complete(&mut self) {
+        let candidates = self.goto_candidates();
+        if let Some(prompt) = self.goto.as_mut() {
+            if let Some(path) = candidates.get(prompt.selected) {
+                prompt.field = TextField::from_text(path);
+                prompt.selected = 0;
+            }
+        }
+    }
+
+    /// Enter in the prompt: go to the selected candidate, or to the path as
+    /// typed, expanding its ancestors.
+    pub fn goto_accept(&mut self) {
+        let candidates = self.goto_candidates();
+        let Some(prompt) = self.goto.take() else {
+            return;
+        };
+        let typed = prompt.field.value();
+        let target = candidates
+            .get(prompt.selected)
+            .cloned()
+            .unwrap_or_else(|| typed.trim().to_string());
+        if !self.go_to(&target) {
+            self.set_msg(format!("No such path: {}", typed.trim()), true);
+            self.goto = Some(prompt);
+        }
+    }
+
+    /// Select the node at `path` (as text), expanding its ancestors.
+    fn go_to(&mut self, path: &str) -> bool {
+        let Ok(path) = query::parse_path(path) else {
+            return false;
+        };
+        self.reveal_path(path)
+    }
+
+    pub fn open_bookmarks(&mut self) {
+        if !self.is_open() {
+            self.set_msg("Open a file first.", true);
+            return;
+        }
+        self.bookmark_view = Some(BookmarkView { selected: 0 });
+    }
+
+    /// The open file's bookmarks as `(name, path, in the document)`.
+    pub fn bookmark_rows(&self) -> Vec<(String, String, bool)> {
+        let Some(file) = &self.file_path else {
+            return Vec::new();
+        };
+        self.bookmarks
+            .for_file(file)
+            .iter()
+            .map(|b| {
+                let found =
+                    query::parse_path(&b.path).is_ok_and(|p| self.doc.find_by_path(&p).is_some());
+                (b.name.clone(), b.path.clone(), found)
+            })
+            .collect()
+    }
+
+    pub fn bookmark_move(&mut self, delta: isize) {
+        let last = self.bookmark_rows().len().saturating_sub(1) as isize;
+        if let Some(view) = self.bookmark_view.as_mut() {
+            view.selected = (view.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Enter in the bookmarks: close them and go to the selected one.
+    pub fn bookmark_jump(&mut self) {
+        let Some(view) = self.bookmark_view.take() else {
+            return;
+        };
+        let Some((name, path, _)) = self.bookmark_rows().into_iter().nth(view.selected) else {
+            return;
+        };
+        if !self.go_to(&path) {
+            self.set_msg(format!("\"{name}\": {path} is not in this file."), true);
+        }
+    }
+
+    /// `x` in the bookmarks: delete the selected one.
+    pub fn bookmark_delete(&mut self) {
+        let (Some(view), Some(file)) = (self.bookmark_view.as_mut(), self.file_path.as_ref())
+        else {
+            return;
+        };
+        let index = view.selected;
+        if let Err(e) = self.bookmarks.remove(file, index) {
+            self.set_msg(format!("Could not save the bookmarks: {e}"), true);
+        }
+        let last = self.bookmark_rows().len().saturating_sub(1);
+        if let Some(view) = self.bookmark_view.as_mut() {
+            view.selected = view.selected.min(last);
+        }
+    }
+
+    // --- changes on disk -----------------------------------------------------
+
+    /// Called on every tick: at most once a second, look for a change to the
+    /// open file on disk (see [`check_disk`](Self::check_disk)).
+    pub fn poll_disk(&mut self) {
+        let now = std::time::Instant::now();
+        if self
+            .last_poll
+            .is_some_and(|t| now.duration_since(t) < std::time::Duration::from_secs(1))
+        {
+            return;
+        }
+        self.last_poll = Some(now);
+        self.check_disk();
+    }
+
+    /// If the file changed on disk, reload it when there are no unsaved
+    /// changes, or offer a merge when there are. Waits while a run, an edit
+    /// or a dialog is in progress.
+    pub fn check_disk(&mut self) {
+        let busy = self.job_running()
+            || self.editing.is_some()
+            || self.review.is_some()
+            || self.merge.is_some()
+            || self.backup_view.is_some()
+            || self.confirm.is_some()
+            || self.guard.is_some();
+        let Some(path) = self.file_path.clone().filter(|_| !busy) else {
+            return;
+        };
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return;
+        };
+        let h = hash(&content);
+        if Some(h) == self.disk_hash || Some(h) == self.ignored_disk {
+            return;
+        }
+        let name = file_name(&path);
+        if let Err(e) = Document::validate_as(&content, self.doc.format()) {
+            self.ignored_disk = Some(h);
+            self.set_msg(
+                format!(
+                    "{name} changed on disk but does not parse: {}",
+                    first_line(&e)
+                ),
+                true,
+            );
+            return;
+        }
+        if self.dirty() {
+            let rows = merge::plan(&self.initial_content, &self.doc, &content);
+            self.merge = Some(MergeView {
+                disk: content,
+                rows,
+                selected: 0,
+            });
+            self.set_msg(format!("{name} changed on disk. Merge your changes?"), true);
+        } else {
+            self.adopt_disk(content.clone(), content, format!("Reload {name} from disk"));
+            self.set_msg(format!("Reloaded {name}: it changed on disk."), false);
+        }
+    }
+
+    /// Make `disk` the saved baseline and `text` the working document, as
+    /// one undo step. Selection, expansion and marks stay on their paths.
+    fn adopt_disk(&mut self, disk: String, text: String, label: String) {
+        self.history.record(label, self.doc.raw(), &text);
+        self.set_doc(text);
+        self.disk_hash = Some(hash(&disk));
+        self.initial_content = disk;
+        self.ignored_disk = None;
+        self.rebuild_baseline();
+    }
+
+    pub fn merge_move(&mut self, delta: isize) {
+        if let Some(view) = self.merge.as_mut() {
+            let last = view.rows.len().saturating_sub(1) as isize;
+            view.selected = (view.selected as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Space in the merge: keep our value or the disk's at the selected path.
+    pub fn merge_toggle(&mut self) {
+        let Some(row) = self
+            .merge
+            .as_mut()
+            .and_then(|view| view.rows.get_mut(view.selected))
+        else {
+            return;
+        };
+        if row.choosable() {
+            row.keep_mine = !row.keep_mine;
+        } else {
+            self.set_msg("Only the disk version is possible here.", true);
+        }
+    }
+
+    /// Enter in the merge: the disk content with the kept values of ours
+    /// becomes the working document; the disk content is the new baseline.
+    pub fn merge_accept(&mut self) {
+        let Some(view) = self.merge.take() else {
+            return;
+        };
+        let merged = match merge::apply(&self.doc, &view.disk, &view.rows) {
+            Ok(text) => text,
+            Err(e) => {
+                self.set_msg(format!("Merge failed: {e}"), true);
+                self.merge = Some(view);
+                return;
+            }
+        };
+        let kept = view.rows.iter().filter(|r| r.keep_mine).count();
+        let dropped = view.rows.len() - kept;
+        self.adopt_disk(view.disk, merged, "Merge with the file on disk".to_string());
+        self.set_msg(
+            format!("Merged: kept {kept} of your change(s), took the disk version for {dropped}."),
+            false,
+        );
+    }
+
+    /// Esc in the merge: keep the working document as it is. Saving will
+    /// ask before overwriting the file.
+    pub fn merge_dismiss(&mut self) {
+        if let Some(view) = self.merge.take() {
+            self.ignored_disk = Some(hash(&view.disk));
+            self.set_msg(
+                "Kept your version; saving asks before overwriting the file on disk.",
+                false,
+            );
+        }
+    }
+
+    // --- open modal & confirmations ---------------------------------------
+
+    pub fn open_dialog(&mut self) {
+        self.open_modal = Some(OpenModal::default());
+    }
+
+    pub fn close_dialog(&mut self) {
+        self.open_modal = None;
+    }
+
+    /// Handle activation inside the open modal (Enter). Returns true when a file
+    /// was opened (so the modal can close).
+    pub fn modal_activate(&mut self) -> bool {
+        let Some(modal) = self.open_modal.as_mut() else {
+            return false;
+        };
+        let candidate = match modal.mode {
+            OpenMode::Browse => modal.browser.activate(),
+            OpenMode::Path => Some(std::path::PathBuf::from(modal.path_input.value())),
+        };
+        let Some(path) = candidate else {
+            return false; // navigated into a directory
+        };
+        let input = path.to_string_lossy().to_string();
+        if modal.compare {
+            return match self.start_compare(&input) {
+                Ok(()) => {
+                    self.open_modal = None;
+                    true
+                }
+                Err(e) => {
+                    if let Some(m) = self.open_modal.as_mut() {
+                        m.error = Some(e);
+                    }
+                    false
+                }
+            };
+        }
+        match self.open_path(&input) {
+            Ok(()) => {
+                self.open_modal = None;
+                true
+            }
+            Err(e) => {
+                if let Some(m) = self.open_modal.as_mut() {
+                    m.error = Some(e);
+                }
+                false
+            }
+        }
+    }
+
+    // --- compare view -------------------------------------------------------
+
+    /// `c`: choose a file to compare the open one with.
+    pub fn open_compare_dialog(&mut self) {
+        if !self.is_open() {
+            self.set_msg("Open a file to compare first.", true);
+            return;
+        }
+        self.open_modal = Some(OpenModal {
+            compare: true,
+            ..OpenModal::default()
+        });
+    }
+
+    /// Compare the open document with the file at a user-supplied path.
+    pub fn start_compare(&mut self, input: &str) -> Result<(), String> {
+        if !self.is_open() {
+            return Err("Open a file to compare first.".to_string());
+        }
+        let path = resolve_path(input)?;
+        let format =
+            DocFormat::from_path(&path).ok_or("Not a .yaml/.yml/.properties/.json file")?;
+        let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        Document::validate_as(&content, format)
+            .map_err(|e| format!("Invalid {}: {e}", format.label()))?;
+        let same = |a: &Path, b: &Path| match (a.canonicalize(), b.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => a == b,
+        };
+        if self
+            .file_path
+            .as_deref()
+            .is_some_and(|open| same(open, &path))
+        {
+            return Err("That is the open file.".to_string());
+        }
+        self.compare = Some(CompareView {
+            path,
+            doc: Document::parse_as(&content, format),
+            disk_hash: hash(&content),
+            rows: Vec::new(),
+            selected: 0,
+            differences_only: false,
+        });
+        self.refresh_compare();
+        Ok(())
+    }
+
+    /// Re-align the rows after either side changed.
+    fn refresh_compare(&mut self) {
+        let Some(view) = self.compare.as_mut() else {
+            return;
+        };
+        view.rows = compare::compare(&self.doc, &view.doc);
+        view.selected = view.selected.min(view.visible().len().saturating_sub(1));
+    }
+
+    pub fn close_compare(&mut self) {
+        self.compare = None;
+    }
+
+    pub fn compare_move(&mut self, delta: isize) {
+        if let Some(view) = self.compare.as_mut() {
+            let len = view.visible().len();
+            if len > 0 {
+                view.selected =
+                    (view.selected as isize + delta).clamp(0, len as isize - 1) as usize;
+            }
+        }
+    }
+
+    /// Show only the rows that differ, or every row, keeping the selection
+    /// on the same key where possible.
+    pub fn compare_toggle_filter(&mut self) {
+        if let Some(view) = self.compare.as_mut() {
+            let row = view.selected_row();
+            view.differences_only = !view.differences_only;
+            view.selected = row
+                .and_then(|r| view.visible().iter().position(|&i| i >= r))
+                .unwrap_or(0);
+        }
+    }
+
+    /// Copy the selected key, and any values under it, into the file that
+    /// lacks it with a placeholder value. The open file is changed in the
+    /// editor (undoable, saved as usual); the other file is written at once.
+    pub fn compare_copy(&mut self) {
+        let Some(view) = self.compare.as_ref() else {
+            return;
+        };
+        let Some(index) = view.selected_row() else {
+            return;
+        };
+        let Status::Missing(side) = view.rows[index].status else {
+            self.set_msg("Only a key missing on one side can be copied.", true);
+            return;
+        };
+        let result = match side {
+            Side::Left => compare::copy_missing(&view.rows, index, &view.doc, &self.doc, side).map(
+                |(text, added)| {
+                    self.change_doc(format!("Copy {added} key(s) from the compared file"), text);
+                    let name = self.file_path.as_deref().map(file_name).unwrap_or_default();
+                    format!("{added} key(s) added to {name} (unsaved).")
+                },
+            ),
+            Side::Right => self.copy_to_compared(index),
+        };
+        match result {
+            Ok(msg) => {
+                self.refresh_compare();
+                self.set_msg(
+                    format!("{msg} Placeholder value: {}", compare::PLACEHOLDER),
+                    false,
+                );
+            }
+            Err(e) => self.set_msg(e, true),
+        }
+    }
+
+    fn copy_to_compared(&mut self, index: usize) -> Result<String, String> {
+        let view = self.compare.as_mut().ok_or("Nothing to compare.")?;
+        let (text, added) =
+            compare::copy_missing(&view.rows, index, &self.doc, &view.doc, Side::Right)?;
+        let on_disk = std::fs::read_to_string(&view.path).map_err(|e| e.to_string())?;
+        if hash(&on_disk) != view.disk_hash {
+            return Err(format!(
+                "{} changed on disk; compare again before copying.",
+                file_name(&view.path)
+            ));
+        }
+        atomic_write(&view.path, &text)?;
+        view.disk_hash = hash(&text);
+        view.doc = Document::parse_as(&text, view.doc.format());
+        Ok(format!(
+            "{added} key(s) written to {}.",
+            file_name(&view.path)
+        ))
+    }
+
+    /// Enter in the compare view: close it and select the key in the open
+    /// file.
+    pub fn compare_activate(&mut self) {
+        let Some(view) = self.compare.as_ref() else {
+            return;
+        };
+        let path = view
+            .selected_row()
+            .and_then(|i| view.rows[i].left.as_ref())
+            .map(|e| e.path.clone());
+        match path {
+            Some(path) => {
+                self.compare = None;
+                self.reveal_path(path);
+            }
+            None => self.set_msg("Not in the open file; press c to copy it.", true),
+        }
+    }
+
+    // --- project workspace -----------------------------------------------
+
+    /// Load the Mule project at a user-supplied directory and show its config
+    /// files. On failure the current project (if any) is kept.
+    pub fn open_project(&mut self, input: &str) -> Result<(), String> {
+        let root = resolve_path(input)?;
+        let project = Project::load(&root, self.secret_rules.clone(), self.project_links.clone())?;
+        if let Some(e) = project.links.error() {
+            self.set_msg(e.to_string(), true);
+        }
+        self.project = Some(project);
+        self.project_panel = Some(ProjectPanel::default());
+        Ok(())
+    }
+
+    /// Show the project panel with freshly counted values.
+    pub fn show_project(&mut self) {
+        match self.project.as_mut() {
+            Some(project) => {
+                project.rescan();
+                let selected = self
+                    .file_path
+                    .as_ref()
+                    .and_then(|open| project.configs.iter().position(|c| &c.path == open))
+                    .unwrap_or(0);
+                self.project_panel = Some(ProjectPanel {
+                    selected,
+                    ..ProjectPanel::default()
+                });
+            }
+            None => self.set_msg("No project loaded (start with --project <dir>).", true),
+        }
+    }
+
+    /// Esc in the project panel: leave the report, or close the panel.
+    pub fn close_project(&mut self) {
+        match self.project_panel.as_mut() {
+            Some(panel) if panel.report.is_some() => panel.report = None,
+            _ => self.project_panel = None,
+        }
+    }
+
+    pub fn project_move(&mut self, delta: isize) {
+        let configs = self.project.as_ref().map_or(0, |p| p.configs.len());
+        let Some(panel) = self.project_panel.as_mut() else {
+            return;
+        };
+        let (len, cursor) = match &panel.report {
+            Some(report) => (report.findings.len(), &mut panel.finding),
+            None => (configs, &mut panel.selected),
+        };
+        if len > 0 {
+            *cursor = (*cursor as isize + delta).clamp(0, len as isize - 1) as usize;
+        }
+    }
+
+    /// Cross-check the project's XML placeholders against its config files
+    /// (see [`xref`]) and show the findings in the panel.
+    pub fn project_cross_check(&mut self) {
+        let (Some(project), Some(panel)) = (self.project.as_mut(), self.project_panel.as_mut())
+        else {
+            return;
+        };
+        project.rescan();
+        panel.report = Some(xref::cross_check(project));
+        panel.finding = 0;
+    }
+
+    /// The config file selected in the project panel's file list.
+    pub fn selected_config(&self) -> Option<&ConfigFile> {
+        let panel = self.project_panel.as_ref().filter(|p| p.report.is_none())?;
+        self.project.as_ref()?.configs.get(panel.selected)
+    }
+
+    /// `c` in the project panel: compare the open file with the selected one.
+    pub fn project_compare(&mut self) {
+        let Some(path) = self.selected_config().map(|c| c.path.clone()) else {
+            return;
+        };
+        match self.start_compare(&path.to_string_lossy()) {
+            Ok(()) => self.project_panel = None,
+            Err(e) => {
+                if let Some(panel) = self.project_panel.as_mut() {
+                    panel.error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Select the node at `path`, expanding its ancestors so it is visible.
+    pub fn reveal_path(&mut self, path: Vec<PathSeg>) -> bool {
+        if self.doc.find_by_path(&path).is_none() {
+            return false;
+        }
+        for len in 1..path.len() {
+            self.expanded.insert(path[..len].to_vec());
+        }
+        self.selected_path = Some(path);
+        true
+    }
+
+    /// Enter in the project panel: open the selected config file, or jump to
+    /// the value behind the selected finding. Unsaved changes are guarded the
+    /// same way the open modal does.
+    pub fn project_activate(&mut self) {
+        let (Some(project), Some(panel)) = (&self.project, &self.project_panel) else {
+            return;
+        };
+        let (file, jump) = match &panel.report {
+            Some(report) => match report.findings.get(panel.finding) {
+                Some(finding) => match finding.location() {
+                    Some(loc) => (loc.config.clone(), Some(loc.path.clone())),
+                    None => {
+                        self.set_msg("Not defined in any config file.", true);
+                        return;
+                    }
+                },
+                None => return,
+            },
+            None => match project.configs.get(panel.selected) {
+                Some(config) => (config.path.clone(), None),
+                None => return,
+            },
+        };
+        if self.file_path.as_ref() == Some(&file) {
+            self.project_panel = None;
+            if let Some(path) = jump {
+                self.reveal_path(path);
+            }
+            return;
+        }
+        let input = file.to_string_lossy().to_string();
+        match self.open_path(&input) {
+            Ok(()) => {
+                self.project_panel = None;
+                if let Some(path) = jump {
+                    self.reveal_path(path);
+                }
+            }
+            Err(e) => {
+                if let Some(panel) = self.project_panel.as_mut() {
+                    panel.error = Some(e);
+                }
+            }
+        }
+    }
+
+    // --- unsaved-changes guard --------------------------------------------
+
+    /// The pending guarded action, if the UI is waiting on a Save/Discard/Cancel
+    /// choice.
+    pub fn guard(&self) -> Option<&Guard> {
+        self.guard.as_ref()
+    }
+
+    /// Guard a quit: returns `true` if any open file has unsaved changes (a
+    /// prompt was raised) or `false` if it is safe to quit immediately.
+    pub fn guard_quit(&mut self) -> bool {
+        if !self.unsaved_files().is_empty() {
+            self.guard = Some(Guard::Quit);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Resolve the guard by saving first. On success the guard is cleared and
+    /// the pending action returned; on failure the guard is kept and `None`
+    /// returned (with an error message set).
+    pub fn guard_save(&mut self) -> Option<Guard> {
+        let saved = match self.guard {
+            Some(Guard::Quit) => self.save_all(),
+            _ => self.save(),
+        };
+        if let Err(e) = saved {
+            self.set_msg(format!("Save failed: {e}"), true);
+            return None;
+        }
+        self.guard.take()
+    }
+
+    /// Resolve the guard by discarding unsaved changes; returns the pending
+    /// action.
+    pub fn guard_discard(&mut self) -> Option<Guard> {
+        self.guard.take()
+    }
+
+    /// Dismiss the guard, keeping the document as-is.
+    pub fn guard_cancel(&mut self) {
+        self.guard = None;
+    }
+
+    /// Restore, asking for confirmation first if there are unsaved changes.
+    pub fn request_restore(&mut self) {
+        if self.dirty() {
+            self.confirm = Some(Confirm::Restore);
+        } else {
+            self.restore();
+        }
+    }
+
+    /// Save, asking to confirm if the file changed on disk since it was opened.
+    pub fn request_save(&mut self) {
+        if !self.is_open() {
+            self.set_msg("No file open.", true);
+            return;
+        }
+        if self.externally_modified() {
+            self.confirm = Some(Confirm::OverwriteExternal);
+            self.set_msg("File changed on disk. Confirm to overwrite.", true);
+            return;
+        }
+        if let Err(e) = self.save() {
+            self.set_msg(format!("Save failed: {e}"), true);
+        }
+    }
+
+    pub fn confirm_yes(&mut self) {
+        match self.confirm.take() {
+            Some(Confirm::Restore) => self.restore(),
+            Some(Confirm::OverwriteExternal) => {
+                if let Err(e) = self.save() {
+                    self.set_msg(format!("Save failed: {e}"), true);
+                }
+            }
+            None => {}
+        }
+    }
+
+    pub fn confirm_no(&mut self) {
+        self.confirm = None;
+    }
+}
+
+pub enum ToggleResult {
+    Toggled,
+    EditScalar,
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// A tree search regex; matching ignores case.
+fn search_regex(pattern: &str) -> Result<regex::Regex, String> {
+    regex::RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| first_line(&e.to_string()))
+}
+
+fn first_line(s: &str) -> String {
+    s.lines().next().unwrap_or("").to_string()
+}
+
+/// Expand `~` and normalize a user-supplied path.
+fn resolve_path(input: &str) -> Result<PathBuf, String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err("Enter a path".to_string());
     }
     if let Some(rest) = trimmed.strip_prefix('~') {
         let home = directories::BaseDirs::new()
@@ -904,7 +3494,7 @@ fn hash(s: &str) -> u64 {
 
 /// Write `content` to `path` via a temp file in the same directory, then rename
 /// over the original (atomic where the platform supports it).
-fn atomic_write(path: &Path, content: &str) -> Result<(), String> {
+pub(crate) fn atomic_write(path: &Path, content: &str) -> Result<(), String> {
     let dir = path.parent().unwrap_or_else(|| Path::new("."));
     let tmp = dir.join(format!(
         ".{}.lazyprop.tmp",
@@ -1034,6 +3624,47 @@ mod tests {
         assert!(on_disk.contains("password: \"newpass\""));
     }
 
+    #[test]
+    fn save_keeps_crlf_and_bom() {
+        let src = "\u{feff}database:\r\n  password: secret\r\n  host: db\r\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        st.selected_path = Some(vec![
+            PathSeg::Key("database".into()),
+            PathSeg::Key("password".into()),
+        ]);
+        st.editing = Some(TextField::from_text("newpass"));
+        st.apply_edit().unwrap();
+        st.save().unwrap();
+        let on_disk = std::fs::read_to_string(&tmp.path).unwrap();
+        assert_eq!(on_disk, src.replace("secret", "\"newpass\""));
+    }
+
+    #[test]
+    fn open_rejects_tab_indentation() {
+        let tmp = tempfile_path::Temp::new("a:\n\tb: 1\n");
+        let mut st = YamlEditorState::default();
+        let err = st.open_path(tmp.path.to_str().unwrap()).unwrap_err();
+        assert!(
+            err.contains("line 2: tab character in indentation"),
+            "{err}"
+        );
+        assert!(!st.is_open());
+    }
+
+    #[test]
+    fn open_accepts_tabs_inside_block_scalars() {
+        let tmp = tempfile_path::Temp::new("script: |\n  set -e\n  \techo hi\nport: 1\n");
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        assert!(st.is_open());
+        assert!(st
+            .doc()
+            .find_by_path(&[PathSeg::Key("port".into())])
+            .is_some());
+    }
+
     #[test]
     fn undo_redo_reverts_and_replays_edit() {
         let (mut st, _t) = open_sample();
@@ -1056,10 +3687,10 @@ mod tests {
         let (mut st, _t) = open_sample();
         let all = st.visible().len();
         st.start_search();
-        st.search_field().unwrap().insert('p');
-        st.search_field().unwrap().insert('o');
-        st.search_field().unwrap().insert('r');
-        st.search_field().unwrap().insert('t');
+        st.search_insert('p');
+        st.search_insert('o');
+        st.search_insert('r');
+        st.search_insert('t');
         st.confirm_search();
         let filtered = st.visible().len();
         assert!(filtered < all);
@@ -1075,6 +3706,159 @@ mod tests {
         assert_eq!(st.visible().len(), all);
     }
 
+    #[test]
+    fn path_search_filters_by_query() {
+        let (mut st, _t) = open_sample();
+        let all = st.visible().len();
+        st.start_search();
+        st.toggle_search_mode();
+        for c in "**.host".chars() {
+            st.search_insert(c);
+        }
+        st.confirm_search();
+        let labels: Vec<String> = st
+            .visible()
+            .iter()
+            .map(|&id| st.doc().nodes()[id].label.clone())
+            .collect();
+        assert_eq!(labels, vec!["servers", "[0]", "host"]);
+        assert!(st.search_error().is_none());
+
+        // An invalid query reports why and leaves the tree unfiltered.
+        st.start_search();
+        st.search_insert('[');
+        assert_eq!(st.visible().len(), all);
+        assert!(st.search_error().is_some());
+    }
+
+    #[test]
+    fn text_search_matches_comments() {
+        let src =
+            "db:\n  # rotated 2024-05\n  password: a\n  user: b # owner: payments\n  host: c\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        let labels = |st: &YamlEditorState| -> Vec<String> {
+            st.visible()
+                .iter()
+                .map(|&id| st.doc().nodes()[id].label.clone())
+                .collect()
+        };
+        st.search = Some(TextField::from_text("rotated"));
+        assert_eq!(labels(&st), vec!["db", "password"]);
+        st.search = Some(TextField::from_text("PAYMENTS"));
+        assert_eq!(labels(&st), vec!["db", "user"]);
+    }
+
+    #[test]
+    fn comment_edit_leaves_value_untouched() {
+        let (mut st, _t) = open_sample();
+        st.selected_path = Some(vec![
+            PathSeg::Key("database".into()),
+            PathSeg::Key("password".into()),
+        ]);
+        st.begin_comment_edit().unwrap();
+        assert_eq!(st.edit_target, EditTarget::Comment);
+        st.editing = Some(TextField::from_text("rotated 2024-05"));
+        st.apply_edit().unwrap();
+        assert!(st
+            .doc()
+            .raw()
+            .contains("password: secret # rotated 2024-05\n"));
+        assert_eq!(st.edit_target, EditTarget::Value);
+        assert!(st.dirty());
+        // The value itself is not flagged as modified.
+        assert!(!modified_at(
+            &st,
+            &[
+                PathSeg::Key("database".into()),
+                PathSeg::Key("password".into())
+            ]
+        ));
+        st.undo();
+        assert!(!st.dirty());
+    }
+
+    #[test]
+    fn bulk_uses_active_path_query() {
+        let src = "a:\n  password: p1\n  user: u1\nb:\n  password: p2\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        st.search = Some(TextField::from_text("*.password"));
+        st.search_mode = SearchMode::Path;
+        st.start_bulk(Operation::Encrypt).unwrap();
+        while st.next_bulk_value().is_some() {
+            st.finish_crypto(Ok("C".to_string()));
+        }
+        assert!(st.doc().raw().contains("password: \"![C]\"\n  user: u1"));
+        assert!(st.doc().raw().ends_with("password: \"![C]\"\n"));
+        // Text mode ignores the query and falls back to the selection.
+        st.search_mode = SearchMode::Text;
+        st.selected_path = Some(vec![PathSeg::Key("a".into())]);
+        st.start_bulk(Operation::Encrypt).unwrap();
+        assert!(st.next_bulk_value().is_some());
+    }
+
+    #[test]
+    fn value_regex_and_filter_searches_step_through_matches() {
+        let src = "db:\n  user: admin\n  password: \"![c2VjcmV0]\"\n  url: jdbc:pg://db:5432\napi:\n  key: \"![a2V5]\"\n  port: 8080\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        let paths = |st: &YamlEditorState| -> Vec<String> {
+            st.search_matches()
+                .iter()
+                .map(|&id| document::path_to_string(&st.doc().nodes()[id].path))
+                .collect()
+        };
+
+        st.search = Some(TextField::from_text("PG://"));
+        st.search_mode = SearchMode::Value;
+        assert_eq!(paths(&st), vec!["db.url"]);
+        st.search = Some(TextField::from_text(r"^(db|api)\.(user|port)$"));
+        st.search_mode = SearchMode::Regex;
+        st.refresh_search();
+        assert_eq!(paths(&st), vec!["db.user", "api.port"]);
+        // A broken regex is reported and filters nothing out.
+        st.search = Some(TextField::from_text("(unclosed"));
+        st.refresh_search();
+        assert!(st.search_error().is_some());
+        assert!(!st.search_active());
+        assert_eq!(st.visible().len(), st.doc().nodes().len());
+
+        // A filter alone is a search; with a query both must hold.
+        st.search = None;
+        st.search_filter = SearchFilter::Encrypted;
+        assert_eq!(paths(&st), vec!["db.password", "api.key"]);
+        st.search = Some(TextField::from_text("api"));
+        st.search_mode = SearchMode::Text;
+        assert_eq!(paths(&st), vec!["api.key"]);
+        st.search = None;
+        st.search_filter = SearchFilter::Plaintext;
+        assert_eq!(paths(&st), vec!["db.user", "db.url", "api.port"]);
+
+        // Jumping leaves the tree whole and wraps around both ways.
+        st.search_jump = true;
+        let all = st.visible().len();
+        assert_eq!(all, st.doc().nodes().len());
+        st.selected_path = Some(vec![PathSeg::Key("db".into())]);
+        st.search_step(1);
+        assert_eq!(st.selected_path_string().as_deref(), Some("db.user"));
+        assert_eq!(st.message(), Some(("Match 1/3.", false)));
+        st.search_step(-1);
+        assert_eq!(st.selected_path_string().as_deref(), Some("api.port"));
+        st.search_step(1);
+        assert_eq!(st.selected_path_string().as_deref(), Some("db.user"));
+
+        st.search_filter = SearchFilter::Modified;
+        st.search_step(1);
+        assert_eq!(st.message(), Some(("No matches.", true)));
+        st.clear_search();
+        assert!(!st.search_active());
+        assert_eq!(st.visible().len(), all);
+    }
+
     #[test]
     fn bulk_queues_all_scalars_under_subtree() {
         let (mut st, _t) = open_sample();
@@ -1109,6 +3893,122 @@ mod tests {
         assert!(st.guard().is_none());
     }
 
+    #[test]
+    fn buffers_keep_their_own_changes_and_quit_saves_them_all() {
+        let (mut st, dev) = open_sample();
+        let prod = tempfile_path::Temp::new("a: 1\nb: 2\n");
+        let key = |k: &str| vec![PathSeg::Key(k.into())];
+        edit_value(
+            &mut st,
+            vec![
+                PathSeg::Key("database".into()),
+                PathSeg::Key("username".into()),
+            ],
+            "root",
+        );
+        st.open_path(prod.path.to_str().unwrap()).unwrap();
+        assert_eq!(st.buffer_count(), 2);
+        assert!(!st.dirty());
+        st.select_path(key("b"));
+        st.undo();
+        assert_eq!(st.message().unwrap().0, "Nothing to undo.");
+
+        // Back to the first file: its edit, history and selection are intact.
+        st.switch_buffer(-1);
+        assert_eq!(st.file_path.as_ref(), Some(&dev.path));
+        assert!(st.dirty());
+        assert_eq!(
+            st.selected_path_string().as_deref(),
+            Some("database.username")
+        );
+        st.open_path(prod.path.to_str().unwrap()).unwrap();
+        assert_eq!(st.buffer_count(), 2, "an open file is switched to");
+        assert_eq!(st.selected_path_string().as_deref(), Some("b"));
+        edit_value(&mut st, key("a"), "9");
+
+        let (tabs, active) = st.buffers();
+        assert_eq!(active, 1);
+        assert!(tabs.iter().all(|(_, dirty)| *dirty));
+        assert!(st.guard_quit());
+        assert_eq!(st.guard_save(), Some(Guard::Quit));
+        assert!(st.unsaved_files().is_empty());
+        assert!(std::fs::read_to_string(&dev.path)
+            .unwrap()
+            .contains("username: \"root\""));
+        assert_eq!(
+            std::fs::read_to_string(&prod.path).unwrap(),
+            "a: \"9\"\nb: 2\n"
+        );
+        assert_eq!(st.file_path.as_ref(), Some(&prod.path));
+
+        // With the other file clean, saving all leaves the active view alone.
+        edit_value(&mut st, key("a"), "10");
+        st.search = Some(TextField::from_text("b"));
+        assert!(st.guard_quit());
+        assert_eq!(st.guard_save(), Some(Guard::Quit));
+        assert_eq!(st.search_query().as_deref(), Some("b"));
+
+        st.close_buffer();
+        assert_eq!(st.file_path.as_ref(), Some(&dev.path));
+        st.close_buffer();
+        assert!(!st.is_open());
+        assert_eq!(st.buffer_count(), 0);
+    }
+
+    #[test]
+    fn disk_changes_reload_clean_files_and_merge_dirty_ones() {
+        let (mut st, t) = open_sample();
+        let path = |keys: &[&str]| -> Vec<PathSeg> {
+            keys.iter().map(|k| PathSeg::Key(k.to_string())).collect()
+        };
+        st.select_path(path(&["database", "password"]));
+        st.toggle_mark();
+        st.check_disk();
+        assert_eq!(st.doc().raw(), SAMPLE, "unchanged on disk");
+
+        // Clean: reloaded in place, selection and marks kept by path.
+        let v2 = SAMPLE.replace("admin", "dba");
+        std::fs::write(&t.path, &v2).unwrap();
+        st.check_disk();
+        assert_eq!(st.doc().raw(), v2);
+        assert!(!st.dirty());
+        assert_eq!(
+            st.selected_path_string().as_deref(),
+            Some("database.password")
+        );
+        assert_eq!(st.marked_ids().len(), 1);
+
+        // Dirty: both sides edit; ours is merged onto the disk version.
+        edit_value(&mut st, path(&["database", "password"]), "s3cret");
+        let v3 = v2.replace("8081", "9090");
+        std::fs::write(&t.path, &v3).unwrap();
+        st.check_disk();
+        let view = st.merge.as_ref().expect("merge offered");
+        assert_eq!(view.rows.len(), 1);
+        assert!(!view.rows[0].is_conflict());
+        st.merge_accept();
+        assert_eq!(st.doc().raw(), v3.replace("secret", "\"s3cret\""));
+        assert!(st.dirty());
+        st.undo();
+        assert_eq!(st.doc().raw(), v2.replace("secret", "\"s3cret\""));
+
+        // Dismissed: not offered again until the file changes once more.
+        std::fs::write(&t.path, SAMPLE).unwrap();
+        st.check_disk();
+        st.merge_dismiss();
+        st.check_disk();
+        assert!(st.merge.is_none());
+        // Nor after a visit to another tab.
+        let other = tempfile_path::Temp::new("b: 1\n");
+        st.open_path(other.path.to_str().unwrap()).unwrap();
+        st.switch_buffer(-1);
+        assert_eq!(st.file_path.as_deref(), Some(t.path.as_path()));
+        st.check_disk();
+        assert!(st.merge.is_none());
+        st.request_save();
+        assert_eq!(st.confirm, Some(Confirm::OverwriteExternal));
+    }
+
     fn modified_at(st: &YamlEditorState, path: &[PathSeg]) -> bool {
         let id = st.doc().find_by_path(path).expect("node exists");
         st.is_modified(id)
@@ -1245,7 +4145,552 @@ services:
         assert!(!modified_at(&st, &user));
     }
 
+    #[test]
+    fn properties_values_are_escaped_not_quoted() {
+        let src = "# creds\r\ndb.password = secret\r\ndb.user=admin\r\n";
+        let tmp = tempfile_path::Temp::with_ext(src, "properties");
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        st.selected_path = Some(vec![PathSeg::Key("db.password".into())]);
+        assert_eq!(st.begin_crypto(Operation::Encrypt).unwrap(), "secret");
+        st.finish_crypto(Ok("CIPHER".to_string()));
+        assert!(st.begin_comment_edit().is_err());
+        st.save().unwrap();
+        let on_disk = std::fs::read_to_string(&tmp.path).unwrap();
+        assert_eq!(
+            on_disk,
+            "# creds\r\ndb.password = ![CIPHER]\r\ndb.user=admin\r\n"
+        );
+    }
+
+    #[test]
+    fn json_bulk_encrypt_keeps_formatting() {
+        let src = "{\n  \"db\": { \"user\": \"admin\",\n          \"port\": 5432 },\n  \"debug\": true\n}\n";
+        let tmp = tempfile_path::Temp::with_ext(src, "json");
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        st.selected_path = Some(vec![PathSeg::Key("db".into())]);
+        st.start_bulk(Operation::Encrypt).unwrap();
+        assert_eq!(st.next_bulk_value().unwrap().1, "admin");
+        st.finish_crypto(Ok("C1".to_string()));
+        assert_eq!(st.next_bulk_value().unwrap().1, "5432");
+        st.finish_crypto(Ok("C2".to_string()));
+        assert!(st.next_bulk_value().is_none());
+        let debug = [PathSeg::Key("debug".into())];
+        let user = [PathSeg::Key("db".into()), PathSeg::Key("user".into())];
+        assert!(modified_at(&st, &user));
+        assert!(!modified_at(&st, &debug));
+        st.save().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&tmp.path).unwrap(),
+            "{\n  \"db\": { \"user\": \"![C1]\",\n          \"port\": \"![C2]\" },\n  \"debug\": true\n}\n"
+        );
+    }
+
+    #[test]
+    fn project_panel_opens_config_files() {
+        let root = std::env::temp_dir().join(format!("lazyprop_ws_{}", std::process::id()));
+        let resources = root.join("src/main/resources");
+        std::fs::create_dir_all(&resources).unwrap();
+        std::fs::write(root.join("mule-artifact.json"), "{}").unwrap();
+        std::fs::write(resources.join("config-dev.yaml"), "a: 1\n").unwrap();
+        std::fs::write(resources.join("config-prod.yaml"), "b: 2\n").unwrap();
+        let mut st = YamlEditorState::default();
+        st.open_project(root.to_str().unwrap()).unwrap();
+        assert_eq!(st.project.as_ref().unwrap().configs.len(), 2);
+        st.project_move(1);
+        st.project_activate();
+        assert!(st.project_panel.is_none());
+        assert_eq!(st.file_path, Some(resources.join("config-prod.yaml")));
+        // Reopening the panel preselects the open file.
+        st.show_project();
+        assert_eq!(st.project_panel.as_ref().unwrap().selected, 1);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn cross_check_findings_jump_to_the_value() {
+        let dir = crate::mule::test_support::TempDir::new();
+        dir.write("mule-artifact.json", "{}");
+        dir.write(
+            "src/main/mule/api.xml",
+            "<db password=\"${secure::db.password}\"/>\n",
+        );
+        dir.write(
+            "src/main/resources/config.yaml",
+            "db:\n  nested:\n    x: 1\n  password: hunter2\n",
+        );
+        let mut st = YamlEditorState::default();
+        st.open_project(dir.0.to_str().unwrap()).unwrap();
+        st.project_cross_check();
+        let report = st.project_panel.as_ref().unwrap().report.as_ref().unwrap();
+        assert_eq!(report.findings.len(), 1);
+        st.project_activate();
+        assert!(st.project_panel.is_none());
+        assert_eq!(st.selected_path_string().as_deref(), Some("db.password"));
+    }
+
     // Minimal temp-file helper (avoids a new dependency).
+    #[test]
+    fn compare_copies_missing_keys_both_ways() {
+        let (mut st, tmp) = open_sample();
+        let other = tempfile_path::Temp::with_ext(
+            "database.username=admin\ndatabase.password=![x]\napi.key=k\n",
+            "properties",
+        );
+        assert!(st.start_compare(tmp.path.to_str().unwrap()).is_err());
+        st.start_compare(other.path.to_str().unwrap()).unwrap();
+        let status = |st: &YamlEditorState, key: &str| {
+            let view = st.compare.as_ref().unwrap();
+            view.rows.iter().find(|r| r.key == key).unwrap().status
+        };
+        let select = |st: &mut YamlEditorState, key: &str| {
+            let view = st.compare.as_mut().unwrap();
+            view.selected = view
+                .visible()
+                .iter()
+                .position(|&i| view.rows[i].key == key)
+                .unwrap();
+        };
+        assert_eq!(st.compare.as_ref().unwrap().rows.len(), 5);
+        assert_eq!(status(&st, "database.password"), Status::Encryption);
+        assert_eq!(status(&st, "servers[0].host"), Status::Missing(Side::Right));
+
+        // Only keys missing on one side can be copied.
+        select(&mut st, "database.username");
+        st.compare_copy();
+        assert!(st.message().unwrap().1);
+
+        // Into the open document: undoable, saved later.
+        select(&mut st, "api.key");
+        st.compare_copy();
+        assert!(st.dirty());
+        assert_eq!(status(&st, "api.key"), Status::Same);
+        assert!(st.doc().raw().ends_with("api:\n  key: \"CHANGE_ME\"\n"));
+
+        // Into the other file: written at once.
+        st.compare_toggle_filter();
+        select(&mut st, "servers[0].port");
+        st.compare_copy();
+        assert_eq!(status(&st, "servers[0].port"), Status::Same);
+        let written = std::fs::read_to_string(&other.path).unwrap();
+        assert!(
+            written.ends_with("servers[0].port=CHANGE_ME\n"),
+            "{written}"
+        );
+    }
+
+    #[test]
+    fn encrypts_exactly_the_suspicious_values() {
+        let src = "db:\n  user: admin\n  password: hunter2\n  url: jdbc:mysql://app:pw@db/orders\napi:\n  key: \"![done]\"\n  ref: ${api.key}\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        st.open_suspicious();
+        let panel = st.suspicious_panel.as_ref().unwrap();
+        let listed: Vec<(String, Signal)> = panel
+            .items
+            .iter()
+            .map(|(p, s)| (document::path_to_string(p), *s))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("db.password".to_string(), Signal::SecretKey),
+                ("db.url".to_string(), Signal::JdbcCredentials),
+            ]
+        );
+        let db = st.doc().find_by_path(&[PathSeg::Key("db".into())]).unwrap();
+        assert!(st.subtree_suspicious(db));
+
+        st.start_encrypt_suspicious().unwrap();
+        assert!(st.suspicious_panel.is_none());
+        let mut sent = Vec::new();
+        while let Some((op, value)) = st.next_bulk_value() {
+            assert_eq!(op, Operation::Encrypt);
+            sent.push(value);
+            st.finish_crypto(Ok(format!("enc{}", sent.len())));
+        }
+        assert_eq!(sent, vec!["hunter2", "jdbc:mysql://app:pw@db/orders"]);
+        assert!(st.suspicious().is_empty());
+        assert!(st.doc().raw().contains("user: admin"));
+    }
+
+    #[test]
+    fn bulk_and_copy_follow_marks_across_edits() {
+        let src = "db:\n  user: admin\n  password: hunter2\napi:\n  key: abc\n  url: http://x\nsmtp:\n  password: mail\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        let key = |k: &str| {
+            k.split('.')
+                .map(|s| PathSeg::Key(s.into()))
+                .collect::<Vec<_>>()
+        };
+        for path in ["smtp.password", "db.password", "api.key"] {
+            st.select_path(key(path));
+            st.toggle_mark();
+        }
+        st.select_path(key("api.key"));
+        st.toggle_mark();
+
+        // An edit re-parses the document; the marks stay on their paths.
+        st.select_path(key("db.user"));
+        st.editing = Some(TextField::from_text("root"));
+        st.apply_edit().unwrap();
+        assert_eq!(st.marked_ids().len(), 2);
+        assert_eq!(
+            st.marked_text().unwrap(),
+            "db.password: hunter2\nsmtp.password: mail"
+        );
+
+        st.start_bulk(Operation::Encrypt).unwrap();
+        let mut sent = Vec::new();
+        while let Some((_, value)) = st.next_bulk_value() {
+            sent.push(value);
+            st.finish_crypto(Ok("C".to_string()));
+        }
+        assert_eq!(sent, vec!["hunter2", "mail"]);
+        assert!(st.doc().raw().contains("key: abc"));
+        assert!(st.doc().raw().contains("user: \"root\""));
+
+        st.clear_marks();
+        assert!(st.marked_text().is_err());
+    }
+
+    #[test]
+    fn history_undoes_a_bulk_run_in_one_step() {
+        let (mut st, _t) = open_sample();
+        st.select_path(vec![
+            PathSeg::Key("database".into()),
+            PathSeg::Key("username".into()),
+        ]);
+        st.editing = Some(TextField::from_text("root"));
+        st.apply_edit().unwrap();
+        st.select_path(vec![PathSeg::Key("database".into())]);
+        st.start_bulk(Operation::Encrypt).unwrap();
+        st.undo();
+        assert!(st.message().unwrap().1, "undo waits for the bulk run");
+        while st.next_bulk_value().is_some() {
+            st.finish_crypto(Ok("C".to_string()));
+        }
+        let rows: Vec<String> = st.history_rows().into_iter().map(|(l, _)| l).collect();
+        assert_eq!(
+            rows[..2],
+            ["Bulk encrypt 2 value(s)", "Edit database.username"]
+        );
+
+        st.undo();
+        assert_eq!(st.message().unwrap().0, "Undid: Bulk encrypt 2 value(s).");
+        assert!(st
+            .doc()
+            .raw()
+            .contains("username: \"root\"\n  password: secret"));
+
+        // Jump to the file as opened, then forward to the end again.
+        st.open_history();
+        st.history_move(5);
+        st.history_jump();
+        assert_eq!(st.doc().raw(), SAMPLE);
+        assert!(st.history_rows()[..2].iter().all(|(_, applied)| !applied));
+        st.open_history();
+        st.history_move(-5);
+        st.history_jump();
+        assert!(st.doc().raw().contains("password: \"![C]\""));
+    }
+
+    #[test]
+    fn saves_are_backed_up_and_can_be_loaded_back() {
+        let (mut st, tmp) = open_sample();
+        let root = tmp.path.with_extension("backups");
+        st.backups = Some(Store::new(root.clone(), 5));
+        st.save_env = Some("Prod".to_string());
+        st.select_path(vec![PathSeg::Key("database".into())]);
+        st.start_bulk(Operation::Encrypt).unwrap();
+        while st.next_bulk_value().is_some() {
+            st.finish_crypto(Ok("C".to_string()));
+        }
+        st.save().unwrap();
+
+        st.open_backups();
+        let view = st.backup_view.as_ref().unwrap();
+        let envs: Vec<Option<&str>> = view.backups.iter().map(|(b, _)| b.env.as_deref()).collect();
+        assert_eq!(envs, vec![Some("Prod"), None]);
+        assert!(view.backups[0].1.is_empty());
+        assert_eq!(
+            view.backups[1].1,
+            vec![
+                "database.username: encrypted → plaintext",
+                "database.password: encrypted → plaintext",
+            ]
+        );
+
+        st.backup_move(1);
+        st.backup_load();
+        assert_eq!(st.doc().raw(), SAMPLE);
+        assert!(st.dirty());
+        st.undo();
+        assert!(st.doc().raw().contains("password: \"![C]\""));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn goto_completes_paths_and_bookmarks_jump_back() {
+        let (mut st, _t) = open_sample();
+        st.open_goto();
+        for c in "PORT".chars() {
+            st.goto_field().unwrap().insert(c);
+        }
+        assert_eq!(st.goto_candidates(), vec!["servers[0].port"]);
+        st.goto_field().unwrap().clear();
+        for c in "servers[0]".chars() {
+            st.goto_field().unwrap().insert(c);
+        }
+        assert_eq!(
+            st.goto_candidates(),
+            vec!["servers[0]", "servers[0].host", "servers[0].port"]
+        );
+        st.goto_move(2);
+        st.goto_complete();
+        assert_eq!(st.goto.as_ref().unwrap().field.value(), "servers[0].port");
+        st.goto_accept();
+        let port = vec![
+            PathSeg::Key("servers".into()),
+            PathSeg::Index(0),
+            PathSeg::Key("port".into()),
+        ];
+        assert_eq!(st.selected_path.as_ref(), Some(&port));
+        assert!(st.expanded.contains(&port[..2]));
+
+        // A path that does not exist keeps the prompt open.
+        st.open_goto();
+        st.goto_field().unwrap().insert('z');
+        st.goto_accept();
+        assert!(st.goto.is_some() && st.message().unwrap().1);
+        st.goto = None;
+
+        st.begin_bookmark().unwrap();
+        assert_eq!(st.editing.as_ref().unwrap().value(), "port");
+        st.apply_edit().unwrap();
+        st.select_path(vec![PathSeg::Key("database".into())]);
+        st.open_bookmarks();
+        assert_eq!(
+            st.bookmark_rows(),
+            vec![("port".to_string(), "servers[0].port".to_string(), true)]
+        );
+        st.bookmark_jump();
+        assert_eq!(st.selected_path.as_ref(), Some(&port));
+        st.open_bookmarks();
+        st.bookmark_delete();
+        assert!(st.bookmark_rows().is_empty());
+    }
+
+    #[test]
+    fn review_reverts_one_hunk_at_a_time() {
+        let src = "a: 1\nb: 2\nc: 3\nd: 4\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        st.open_review();
+        assert!(st.review.is_none());
+
+        edit_value(&mut st, vec![PathSeg::Key("a".into())], "one");
+        edit_value(&mut st, vec![PathSeg::Key("d".into())], "![four]");
+        st.open_review();
+        let hunks = &st.review.as_ref().unwrap().hunks;
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[1].summaries(), vec!["d: plaintext → encrypted"]);
+
+        st.review_revert();
+        assert_eq!(st.doc().raw(), "a: 1\nb: 2\nc: 3\nd: \"![four]\"\n");
+        assert_eq!(st.review.as_ref().unwrap().hunks.len(), 1);
+        st.review_revert();
+        assert!(st.review.is_none());
+        assert!(!st.dirty());
+        st.undo();
+        assert_eq!(st.doc().raw(), "a: 1\nb: 2\nc: 3\nd: \"![four]\"\n");
+    }
+
+    #[test]
+    fn rekey_applies_new_ciphers_as_one_undo_step() {
+        use crate::environment::{Algorithm, State as CipherMode};
+        let env = |name: &str| Environment::new(name, Algorithm::AES, CipherMode::CBC, false, "k");
+        let src = "a: \"![one]\"\nb: \"![two]\"\nc: plain\nd: \"![three]\"\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        st.open_rekey_picker(env("dev"), &[env("dev")]);
+        assert!(st.rekey_picker.is_none());
+        st.open_rekey_picker(env("dev"), &[env("dev"), env("prod")]);
+        assert_eq!(st.rekey_picker.as_ref().unwrap().choices[0].name, "prod");
+        st.start_rekey().unwrap();
+
+        let (from, to, value) = st.next_rekey_value().unwrap();
+        assert_eq!((from.name.as_str(), to.name.as_str()), ("dev", "prod"));
+        assert_eq!(value, "![one]");
+        st.finish_rekey(Ok("![ONE]".to_string()));
+        st.next_rekey_value().unwrap();
+        st.finish_rekey(Err("decrypt with dev failed".to_string()));
+        // A value edited while its re-key runs is left alone.
+        st.next_rekey_value().unwrap();
+        let d = st.doc.find_by_path(&[PathSeg::Key("d".into())]).unwrap();
+        st.replace_value(d, "edited", "Edit").unwrap();
+        st.finish_rekey(Ok("![THREE]".to_string()));
+        assert!(st.next_rekey_value().is_none());
+
+        assert_eq!(
+            st.doc().raw(),
+            "a: \"![ONE]\"\nb: \"![two]\"\nc: plain\nd: edited\n"
+        );
+        let report = st.rekey_report.as_ref().unwrap();
+        assert_eq!(report.summary.counts(), "1 rotated, 1 failed, 2 skipped");
+        assert_eq!(report.rows()[0].0, "failed");
+        st.undo();
+        st.undo();
+        assert_eq!(st.doc().raw(), src);
+    }
+
+    #[test]
+    fn verify_marks_values_without_touching_them() {
+        use crate::environment::{Algorithm, State as CipherMode};
+        let env = Environment::new("dev", Algorithm::AES, CipherMode::CBC, false, "k");
+        let src = "a:\n  x: \"![good]\"\n  y: \"![bad]\"\nb: \"![other]\"\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        st.selected_path = Some(vec![PathSeg::Key("a".into())]);
+        st.start_verify(env.clone(), false).unwrap();
+        let (_, cipher) = st.next_verify_value().unwrap();
+        assert_eq!(cipher, "good");
+        st.finish_verify(Ok(()));
+        st.next_verify_value().unwrap();
+        st.finish_verify(Err(
+            "Input length must be multiple of 16\n\tat ...".to_string()
+        ));
+        assert!(st.next_verify_value().is_none());
+
+        assert_eq!(st.doc().raw(), src);
+        let id = |st: &YamlEditorState, keys: &[&str]| {
+            let path: Vec<PathSeg> = keys.iter().map(|k| PathSeg::Key(k.to_string())).collect();
+            st.doc().find_by_path(&path).unwrap()
+        };
+        assert_eq!(st.verdict(id(&st, &["a", "x"]), "dev"), Some(&Verdict::Ok));
+        assert_eq!(st.verdict(id(&st, &["a", "x"]), "prod"), None);
+        assert_eq!(st.verdict(id(&st, &["b"]), "dev"), None);
+        assert!(st.subtree_fails(id(&st, &["a"]), "dev"));
+        let report = st.verify_report.as_ref().unwrap();
+        assert_eq!(
+            report.failures,
+            vec![(
+                vec![PathSeg::Key("a".into()), PathSeg::Key("y".into())],
+                "Input length must be multiple of 16".to_string()
+            )]
+        );
+
+        // Changing the value drops its badge; the whole file is checked with V.
+        let y = id(&st, &["a", "y"]);
+        st.replace_value(y, "\"![fixed]\"", "Edit").unwrap();
+        assert_eq!(st.verdict(y, "dev"), None);
+        st.start_verify(env, true).unwrap();
+        let mut count = 0;
+        while st.next_verify_value().is_some() {
+            st.finish_verify(Ok(()));
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert!(!st.subtree_fails(id(&st, &["a"]), "dev"));
+    }
+
+    #[test]
+    fn copy_menu_offers_what_applies_to_the_selection() {
+        use crate::environment::{Algorithm, State as CipherMode};
+        let env = Environment::new("dev", Algorithm::AES, CipherMode::CBC, false, "k");
+        let tmp = tempfile_path::Temp::new("db:\n  password: \"![c2VjcmV0]\"\n  port: 5432\n");
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        let text = |st: &YamlEditorState, item| st.copy_text(item).map(|(t, _)| t);
+
+        st.select_path(vec![PathSeg::Key("db".into())]);
+        st.open_copy_menu();
+        assert_eq!(
+            st.copy_menu.take().unwrap().items,
+            vec![CopyItem::Path, CopyItem::Subtree]
+        );
+        assert_eq!(
+            text(&st, CopyItem::Subtree).unwrap(),
+            "db:\n  password: \"![c2VjcmV0]\"\n  port: 5432\n"
+        );
+
+        st.select_path(vec![
+            PathSeg::Key("db".into()),
+            PathSeg::Key("password".into()),
+        ]);
+        st.toggle_mark();
+        st.open_copy_menu();
+        assert_eq!(st.copy_menu.as_ref().unwrap().items, CopyItem::ALL);
+        assert_eq!(text(&st, CopyItem::Path).unwrap(), "db.password");
+        assert_eq!(text(&st, CopyItem::Value).unwrap(), "\"![c2VjcmV0]\"");
+        assert_eq!(text(&st, CopyItem::Ciphertext).unwrap(), "c2VjcmV0");
+        assert_eq!(
+            text(&st, CopyItem::Marked).unwrap(),
+            "db.password: ![c2VjcmV0]"
+        );
+        assert!(text(&st, CopyItem::Plaintext).is_err());
+        st.copy_menu = None;
+        st.start_peek(env, false).unwrap();
+        st.next_peek_value().unwrap();
+        st.finish_peek(Ok("secret".to_string()));
+        assert_eq!(text(&st, CopyItem::Plaintext).unwrap(), "secret");
+    }
+
+    #[test]
+    fn peek_caches_plaintext_outside_the_document() {
+        use crate::environment::{Algorithm, State as CipherMode};
+        let env = Environment::new("dev", Algorithm::AES, CipherMode::CBC, false, "k");
+        let src = "a:\n  x: \"![one]\"\n  y: \"![two]\"\nb: \"![three]\"\n";
+        let tmp = tempfile_path::Temp::new(src);
+        let mut st = YamlEditorState::default();
+        st.open_path(tmp.path.to_str().unwrap()).unwrap();
+        let id = |st: &YamlEditorState, keys: &[&str]| {
+            let path: Vec<PathSeg> = keys.iter().map(|k| PathSeg::Key(k.to_string())).collect();
+            st.doc().find_by_path(&path).unwrap()
+        };
+
+        // Only values in view are peeked with O: collapse `a`.
+        st.selected_path = Some(vec![PathSeg::Key("a".into())]);
+        st.collapse_or_parent();
+        st.start_peek(env.clone(), true).unwrap();
+        let (_, cipher) = st.next_peek_value().unwrap();
+        assert_eq!(cipher, "three");
+        st.finish_peek(Ok("plain-three".to_string()));
+        assert!(st.next_peek_value().is_none());
+        assert_eq!(st.peeked(id(&st, &["b"])), Some("plain-three"));
+        assert_eq!(st.doc().raw(), src);
+        st.stop_peek();
+        assert_eq!(st.peeked(id(&st, &["b"])), None);
+
+        st.start_peek(env, false).unwrap();
+        st.next_peek_value().unwrap();
+        st.finish_peek(Ok("plain-one".to_string()));
+        st.next_peek_value().unwrap();
+        st.finish_peek(Err("Given final block not properly padded".to_string()));
+        assert!(st.next_peek_value().is_none());
+        assert!(st.message().unwrap().1);
+        assert_eq!(st.peeked(id(&st, &["a", "x"])), Some("plain-one"));
+        assert_eq!(st.peeked(id(&st, &["a", "y"])), None);
+        assert_eq!(st.peek_env().map(|e| e.name.as_str()), Some("dev"));
+
+        // An edited value is shown as stored again; switching files wipes
+        // the cache.
+        let x = id(&st, &["a", "x"]);
+        st.replace_value(x, "\"![changed]\"", "Edit").unwrap();
+        assert_eq!(st.peeked(x), None);
+        let other = tempfile_path::Temp::new("c: 3\n");
+        st.open_path(other.path.to_str().unwrap()).unwrap();
+        st.switch_buffer(-1);
+        assert!(!st.peek_active());
+    }
+
     mod tempfile_path {
         use std::path::PathBuf;
         pub struct Temp {
@@ -1253,8 +4698,11 @@ services:
         }
         impl Temp {
             pub fn new(content: &str) -> Self {
+                Temp::with_ext(content, "yaml")
+            }
+            pub fn with_ext(content: &str, ext: &str) -> Self {
                 let path = std::env::temp_dir().join(format!(
-                    "lazyprop_yaml_{}_{}.yaml",
+                    "lazyprop_yaml_{}_{}.{ext}",
                     std::process::id(),
                     std::time::SystemTime::now()
                         .duration_since(std::time::UNIX_EPOCH)
@@ -0,0 +1,238 @@
+//! Spotting plaintext values that look like secrets.
+//!
+//! A value is suspicious when it is not `![...]`, not a `${...}` placeholder,
+//! and either sits under a key that names a secret, looks like a random token
+//! (high Shannon entropy), or is a JDBC URL carrying its password. The rules
+//! come from the `secrets` section of the config file (see [`SecretRules`]).
+
+use serde::Deserialize;
+
+use super::document::{self, PathSeg};
+
+/// The heuristics, configurable under `secrets` in the config file. Missing
+/// fields keep their defaults.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SecretRules {
+    /// Words that make a key a secret, matched against its last segment with
+    /// case and separators ignored (`clientSecret`, `client_secret`).
+    pub key_words: Vec<String>,
+    /// Also treat keys ending in `key` (`apiKey`, `signing_key`) as secrets,
+    /// but not `key` itself.
+    pub key_suffix: bool,
+    /// Bits per character from which a token-like value counts as random.
+    /// `0` turns the entropy check off.
+    pub min_entropy: f64,
+    /// Values shorter than this are never judged by entropy.
+    pub entropy_min_length: usize,
+    /// Flag JDBC URLs with a password inline.
+    pub jdbc_credentials: bool,
+}
+
+impl Default for SecretRules {
+    fn default() -> Self {
+        Self {
+            key_words: [
+                "password",
+                "passwd",
+                "pwd",
+                "secret",
+                "token",
+                "credential",
+                "passphrase",
+                "apikey",
+                "privatekey",
+            ]
+            .map(String::from)
+            .to_vec(),
+            key_suffix: true,
+            min_entropy: 4.3,
+            entropy_min_length: 20,
+            jdbc_credentials: true,
+        }
+    }
+}
+
+/// Why a value was flagged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    SecretKey,
+    HighEntropy,
+    JdbcCredentials,
+}
+
+impl Signal {
+    pub fn label(self) -> &'static str {
+        match self {
+            Signal::SecretKey => "secret key name",
+            Signal::HighEntropy => "random-looking value",
+            Signal::JdbcCredentials => "JDBC URL with password",
+        }
+    }
+}
+
+impl SecretRules {
+    /// Why the plaintext `value` at `path` is probably a secret left
+    /// unencrypted, if it is.
+    pub fn check(&self, path: &[PathSeg], value: &str) -> Option<Signal> {
+        let trimmed = value.trim();
+        if trimmed.is_empty() || document::is_wrapped(value) || trimmed.starts_with("${") {
+            return None;
+        }
+        let key = path.iter().rev().find_map(|seg| match seg {
+            PathSeg::Key(k) => Some(k.as_str()),
+            PathSeg::Index(_) => None,
+        });
+        if key.is_some_and(|k| self.key_looks_secret(k)) {
+            Some(Signal::SecretKey)
+        } else if self.jdbc_credentials && jdbc_has_password(trimmed) {
+            Some(Signal::JdbcCredentials)
+        } else if self.looks_random(trimmed) {
+            Some(Signal::HighEntropy)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a key names a secret: its last dotted segment contains one of
+    /// [`SecretRules::key_words`], or (with [`SecretRules::key_suffix`]) ends
+    /// in `key`.
+    pub fn key_looks_secret(&self, key: &str) -> bool {
+        let norm = normalize(key.rsplit('.').next().unwrap_or(key));
+        self.key_words.iter().any(|w| norm.contains(&normalize(w)))
+            || (self.key_suffix && norm.ends_with("key") && norm != "key")
+    }
+
+    /// A long, token-like value (no spaces, not a URL or absolute path,
+    /// letters and digits mixed) with high entropy.
+    fn looks_random(&self, value: &str) -> bool {
+        self.min_entropy > 0.0
+            && value.chars().count() >= self.entropy_min_length
+            && !value.contains(char::is_whitespace)
+            && !value.contains("://")
+            && !value.starts_with('/')
+            && value.contains(|c: char| c.is_ascii_digit())
+            && value.contains(|c: char| c.is_alphabetic())
+            && entropy(value) >= self.min_entropy
+    }
+}
+
+/// Shannon entropy of `s`, in bits per character.
+pub fn entropy(s: &str) -> f64 {
+    let mut counts = std::collections::HashMap::new();
+    for c in s.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+    }
+    let len = s.chars().count() as f64;
+    counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// `jdbc:...` with `password=` in its parameters, or a password before the
+/// host (`//user:pass@host`, Oracle's `user/pass@host`).
+fn jdbc_has_password(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    let Some(rest) = lower.strip_prefix("jdbc:") else {
+        return false;
+    };
+    if lower.contains("password=") {
+        return true;
+    }
+    let authority = match rest.find("//") {
+        Some(i) => rest[i + 2..].split('/').next().unwrap_or(""),
+        None => rest
+            .rsplit(':')
+            .find(|part| part.contains('@'))
+            .unwrap_or(""),
+    };
+    authority
+        .split_once('@')
+        .is_some_and(|(userinfo, _)| userinfo.contains(':') || userinfo.contains('/'))
+}
+
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(keys: &[&str]) -> Vec<PathSeg> {
+        keys.iter().map(|k| PathSeg::Key(k.to_string())).collect()
+    }
+
+    #[test]
+    fn secret_keys() {
+        let rules = SecretRules::default();
+        for key in [
+            "password",
+            "db.password",
+            "clientSecret",
+            "api_key",
+            "privateKey",
+            "auth.token",
+        ] {
+            assert!(rules.key_looks_secret(key), "{key}");
+        }
+        for key in ["user", "key", "keystore.path", "host", "db.password.url"] {
+            assert!(!rules.key_looks_secret(key), "{key}");
+        }
+        let custom = SecretRules {
+            key_words: vec!["pin".to_string()],
+            key_suffix: false,
+            ..SecretRules::default()
+        };
+        assert!(custom.key_looks_secret("card.PIN"));
+        assert!(!custom.key_looks_secret("apiKey"));
+    }
+
+    #[test]
+    fn signals() {
+        let rules = SecretRules::default();
+        let check = |keys: &[&str], value: &str| rules.check(&path(keys), value);
+        assert_eq!(
+            check(&["db", "password"], "hunter2"),
+            Some(Signal::SecretKey)
+        );
+        assert_eq!(check(&["db", "password"], "![abc]"), None);
+        assert_eq!(check(&["db", "password"], "${db.pw}"), None);
+        assert_eq!(check(&["db", "password"], ""), None);
+        assert_eq!(
+            check(&["db", "url"], "jdbc:mysql://app:s3cret@db:3306/orders"),
+            Some(Signal::JdbcCredentials)
+        );
+        assert_eq!(
+            check(
+                &["db", "url"],
+                "jdbc:sqlserver://db:1433;user=app;password=s3cret"
+            ),
+            Some(Signal::JdbcCredentials)
+        );
+        assert_eq!(
+            check(&["db", "url"], "jdbc:oracle:thin:scott/tiger@db:1521:orcl"),
+            Some(Signal::JdbcCredentials)
+        );
+        assert_eq!(check(&["db", "url"], "jdbc:mysql://db:3306/orders"), None);
+        assert_eq!(
+            check(&["stripe"], "sk_live_BnstSbUb-ozTeNWCLm3CYaATv_tc1ZT"),
+            Some(Signal::HighEntropy)
+        );
+        for value in [
+            "https://api.example.com/v1/orders",
+            "/opt/mule/apps/config/keystore.jks",
+            "6f9341f7-a250-42c6-84f6-594f4c678572",
+            "The quick brown fox jumps over 2 lazy dogs",
+        ] {
+            assert_eq!(check(&["value"], value), None, "{value}");
+        }
+    }
+}
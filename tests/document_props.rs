@@ -0,0 +1,193 @@
+//! Property tests for the source-preserving YAML document: replacing one
+//! scalar must change exactly that value's bytes, whatever the file's line
+//! endings, BOM, trailing whitespace or comment layout.
+
+use lazyprop::yaml_editor::document::{serialize_scalar_quoted, Document, LineEnding, PathSeg};
+use proptest::prelude::*;
+
+const BOM: char = '\u{feff}';
+
+/// One generated `key: value` line, before layout is applied.
+#[derive(Debug, Clone)]
+struct Entry {
+    key: String,
+    value: String,
+    children: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+struct Layout {
+    crlf: bool,
+    bom: bool,
+    trailing_ws: String,
+    comment: bool,
+}
+
+fn key() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_]{0,7}"
+}
+
+fn value() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z][a-z0-9]{0,10}",
+        "[a-z0-9 ]{0,10}".prop_map(|v| format!("\"{v}\"")),
+        "[a-z0-9 ]{0,10}".prop_map(|v| format!("'{v}'")),
+        Just("\"![CIPHER==]\"".to_string()),
+    ]
+}
+
+fn entries() -> impl Strategy<Value = Vec<Entry>> {
+    prop::collection::vec(
+        (
+            key(),
+            value(),
+            prop::collection::vec((key(), value()), 0..3),
+        )
+            .prop_map(|(key, value, children)| Entry {
+                key,
+                value,
+                children,
+            }),
+        1..5,
+    )
+    .prop_map(|mut es| {
+        // Keys must be unique per level for the YAML to be valid.
+        let mut seen = std::collections::HashSet::new();
+        es.retain(|e| seen.insert(e.key.clone()));
+        for e in &mut es {
+            let mut seen = std::collections::HashSet::new();
+            e.children.retain(|(k, _)| seen.insert(k.clone()));
+        }
+        es
+    })
+}
+
+fn layout() -> impl Strategy<Value = Layout> {
+    (any::<bool>(), any::<bool>(), "[ \t]{0,2}", any::<bool>()).prop_map(
+        |(crlf, bom, trailing_ws, comment)| Layout {
+            crlf,
+            bom,
+            trailing_ws,
+            comment,
+        },
+    )
+}
+
+fn render(entries: &[Entry], layout: &Layout) -> String {
+    let nl = if layout.crlf { "\r\n" } else { "\n" };
+    let tail = |s: &mut String| {
+        s.push_str(&layout.trailing_ws);
+        if layout.comment {
+            s.push_str(" # note");
+        }
+        s.push_str(nl);
+    };
+    let mut out = String::new();
+    if layout.bom {
+        out.push(BOM);
+    }
+    for e in entries {
+        out.push_str(&e.key);
+        if e.children.is_empty() {
+            out.push_str(": ");
+            out.push_str(&e.value);
+            tail(&mut out);
+        } else {
+            out.push(':');
+            out.push_str(nl);
+            for (k, v) in &e.children {
+                out.push_str(&format!("  {k}: {v}"));
+                tail(&mut out);
+            }
+        }
+    }
+    out
+}
+
+proptest! {
+    #[test]
+    fn replacing_a_value_changes_only_its_bytes(
+        entries in entries(),
+        layout in layout(),
+        pick in any::<prop::sample::Index>(),
+        new_value in "[ -~]{0,12}",
+    ) {
+        let src = render(&entries, &layout);
+        prop_assume!(Document::validate(&src).is_ok());
+        let doc = Document::parse(&src);
+        let editable: Vec<usize> = doc
+            .nodes()
+            .iter()
+            .filter(|n| n.is_editable_scalar())
+            .map(|n| n.id)
+            .collect();
+        prop_assert!(!editable.is_empty());
+        let id = editable[pick.index(editable.len())];
+        let (s, e) = doc.node(id).unwrap().value_span.unwrap();
+
+        let token = serialize_scalar_quoted(&new_value);
+        let out = doc.replace_scalar_source(id, &token).unwrap();
+
+        // Everything outside the span is byte-identical.
+        prop_assert_eq!(&out[..s], &src[..s]);
+        prop_assert_eq!(&out[s + token.len()..], &src[e..]);
+        prop_assert_eq!(out.starts_with(BOM), layout.bom);
+
+        // The reparsed tree is the same shape, with only the target changed.
+        let after = Document::parse(&out);
+        let expected_ending = if layout.crlf { LineEnding::CrLf } else { LineEnding::Lf };
+        prop_assert_eq!(after.line_ending(), expected_ending);
+        prop_assert_eq!(after.nodes().len(), doc.nodes().len());
+        for (a, b) in doc.nodes().iter().zip(after.nodes()) {
+            prop_assert_eq!(&a.path, &b.path);
+            if a.id == id {
+                prop_assert_eq!(after.logical_value(b.id), Some(new_value.clone()));
+            } else {
+                prop_assert_eq!(doc.value_source(a.id), after.value_source(b.id));
+            }
+        }
+    }
+
+    #[test]
+    fn in_place_replacement_equals_reparse(
+        entries in entries(),
+        layout in layout(),
+        picks in prop::collection::vec((any::<prop::sample::Index>(), "[ -~]{0,12}"), 1..6),
+    ) {
+        let src = render(&entries, &layout);
+        let mut doc = Document::parse(&src);
+        for (pick, new_value) in picks {
+            let editable: Vec<usize> = doc
+                .nodes()
+                .iter()
+                .filter(|n| n.is_editable_scalar())
+                .map(|n| n.id)
+                .collect();
+            let id = editable[pick.index(editable.len())];
+            doc.replace_scalar(id, &serialize_scalar_quoted(&new_value)).unwrap();
+            let reparsed = Document::parse(doc.raw());
+            prop_assert_eq!(doc.nodes(), reparsed.nodes());
+        }
+        Document::validate(doc.raw()).unwrap();
+    }
+
+    #[test]
+    fn spans_never_cover_line_breaks_or_bom(
+        entries in entries(),
+        layout in layout(),
+    ) {
+        let src = render(&entries, &layout);
+        let doc = Document::parse(&src);
+        for node in doc.nodes() {
+            if let Some(v) = doc.value_source(node.id) {
+                prop_assert!(!v.contains(['\r', '\n', BOM]), "{:?}", v);
+                prop_assert_eq!(v, v.trim_end());
+            }
+        }
+        // Top-level keys never absorb the BOM.
+        for &root in doc.roots() {
+            let node = doc.node(root).unwrap();
+            prop_assert!(matches!(&node.path[0], PathSeg::Key(k) if !k.starts_with(BOM)));
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Property tests checking that the `Document` tree has exactly the structure
+//! serde_yaml reads from the same text, across block and flow layouts, and
+//! that parsing arbitrary input never panics.
+
+use lazyprop::yaml_editor::document::{Document, NodeKind};
+use proptest::prelude::*;
+use serde_yaml::Value;
+
+fn scalar() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        "[ -~]{0,12}".prop_map(Value::String),
+        "[a-z]{1,6}( [a-z]{1,6})?\n[a-z ]{0,6}".prop_map(Value::String),
+        any::<i32>().prop_map(|n| Value::Number(n.into())),
+        any::<bool>().prop_map(Value::Bool),
+        Just(Value::Null),
+    ]
+}
+
+fn tree() -> impl Strategy<Value = Value> {
+    scalar().prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Sequence),
+            prop::collection::btree_map("[a-z_][a-z0-9_.-]{0,6}", inner, 0..4).prop_map(|m| {
+                Value::Mapping(m.into_iter().map(|(k, v)| (Value::String(k), v)).collect())
+            }),
+        ]
+    })
+}
+
+/// A document root: a non-empty mapping or sequence.
+fn root() -> impl Strategy<Value = Value> {
+    tree().prop_filter("root must be a non-empty collection", |v| match v {
+        Value::Mapping(m) => !m.is_empty(),
+        Value::Sequence(s) => !s.is_empty(),
+        _ => false,
+    })
+}
+
+/// Emit `v` as flow YAML (`{a: 1, b: [x]}`), quoting every string.
+fn flow(v: &Value) -> String {
+    match v {
+        Value::Mapping(m) => {
+            let items: Vec<String> = m
+                .iter()
+                .map(|(k, v)| format!("{}: {}", flow(k), flow(v)))
+                .collect();
+            format!("{{{}}}", items.join(", "))
+        }
+        Value::Sequence(s) => {
+            let items: Vec<String> = s.iter().map(flow).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Value::String(s) => format!("{s:?}"),
+        other => serde_yaml::to_string(other).unwrap().trim_end().to_string(),
+    }
+}
+
+/// Emit `v` in block style down to `depth`, then in flow style.
+fn mixed(v: &Value, depth: usize, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match v {
+        Value::Mapping(m) if depth > 0 && !m.is_empty() => {
+            for (k, v) in m {
+                out.push_str(&format!("{pad}{}:", flow(k)));
+                block_value(v, depth, indent, out);
+            }
+        }
+        Value::Sequence(s) if depth > 0 && !s.is_empty() => {
+            for v in s {
+                out.push_str(&format!("{pad}-"));
+                block_value(v, depth, indent, out);
+            }
+        }
+        _ => out.push_str(&format!("{pad}{}\n", flow(v))),
+    }
+}
+
+fn block_value(v: &Value, depth: usize, indent: usize, out: &mut String) {
+    let nested = match v {
+        Value::Mapping(m) => depth > 1 && !m.is_empty(),
+        Value::Sequence(s) => depth > 1 && !s.is_empty(),
+        _ => false,
+    };
+    if nested {
+        out.push('\n');
+        mixed(v, depth - 1, indent + 2, out);
+    } else {
+        out.push_str(&format!(" {}\n", flow(v)));
+    }
+}
+
+/// Check that the subtree of `ids` has the shape of `expected`.
+fn check(doc: &Document, ids: &[usize], expected: &Value) -> Result<(), TestCaseError> {
+    match expected {
+        Value::Mapping(m) => {
+            prop_assert_eq!(ids.len(), m.len());
+            for (&id, (k, v)) in ids.iter().zip(m) {
+                let node = doc.node(id).unwrap();
+                prop_assert_eq!(Some(node.label.as_str()), k.as_str());
+                check_node(doc, id, v)?;
+            }
+        }
+        Value::Sequence(s) => {
+            prop_assert_eq!(ids.len(), s.len());
+            for (i, (&id, v)) in ids.iter().zip(s).enumerate() {
+                prop_assert_eq!(&doc.node(id).unwrap().label, &format!("[{i}]"));
+                check_node(doc, id, v)?;
+            }
+        }
+        _ => prop_assert!(false, "not a collection: {:?}", expected),
+    }
+    Ok(())
+}
+
+fn check_node(doc: &Document, id: usize, expected: &Value) -> Result<(), TestCaseError> {
+    let node = doc.node(id).unwrap();
+    match expected {
+        Value::Mapping(_) => {
+            prop_assert_eq!(node.kind, NodeKind::Mapping);
+            check(doc, &node.children, expected)
+        }
+        Value::Sequence(_) => {
+            prop_assert_eq!(node.kind, NodeKind::Sequence);
+            check(doc, &node.children, expected)
+        }
+        _ => {
+            prop_assert_eq!(node.kind, NodeKind::Scalar);
+            // An editable value's span holds exactly the scalar that is read.
+            if node.is_editable_scalar() {
+                let src = doc.value_source(id).unwrap();
+                let read: Value = serde_yaml::from_str(src).unwrap();
+                prop_assert_eq!(&read, expected, "source {:?}", src);
+            }
+            Ok(())
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn block_tree_matches_serde(value in root()) {
+        let src = serde_yaml::to_string(&value).unwrap();
+        prop_assert_eq!(&serde_yaml::from_str::<Value>(&src).unwrap(), &value);
+        let doc = Document::parse(&src);
+        check(&doc, doc.roots(), &value)?;
+    }
+
+    #[test]
+    fn mixed_block_and_flow_tree_matches_serde(value in root(), depth in 1usize..4) {
+        let mut src = String::new();
+        mixed(&value, depth, 0, &mut src);
+        let read: Value = serde_yaml::from_str(&src).unwrap();
+        prop_assert_eq!(&read, &value, "{}", src);
+        let doc = Document::parse(&src);
+        check(&doc, doc.roots(), &value)?;
+    }
+
+    #[test]
+    fn parse_never_panics(src in "[a-z:#'\"\\-\\[\\]{},|>&*!~ \n\t]{0,80}") {
+        let doc = Document::parse(&src);
+        for node in doc.nodes() {
+            if let Some((s, e)) = node.value_span {
+                prop_assert!(s <= e && src.get(s..e).is_some());
+            }
+            if let Some((s, e)) = node.comment_span {
+                prop_assert!(s <= e && src.get(s..e).is_some());
+            }
+        }
+        // Whatever parses as a mapping of scalars has one node per entry.
+        if let Ok(Value::Mapping(m)) = serde_yaml::from_str::<Value>(&src) {
+            if m.keys().all(Value::is_string) && !src.contains(['&', '*', '!', '\t']) {
+                prop_assert_eq!(doc.roots().len(), m.len(), "{:?}", src);
+            }
+        }
+    }
+}
+
+#[test]
+fn unusual_layouts_stay_in_the_tree() {
+    let src = "\
+f: {
+  a: 1,
+  b: [x, y]
+}
+key:
+  value on the next line
+? complex
+: v
+base: &base
+  host: h
+copy: *base
+text: |
+  line
+multi: one
+  two
+";
+    let doc = Document::parse(src);
+    let paths: Vec<String> = doc
+        .nodes()
+        .iter()
+        .map(|n| lazyprop::yaml_editor::document::path_to_string(&n.path))
+        .collect();
+    for p in [
+        "f",
+        "f.a",
+        "f.b",
+        "f.b[0]",
+        "f.b[1]",
+        "key",
+        "complex",
+        "base",
+        "base.host",
+        "copy",
+        "text",
+        "multi",
+    ] {
+        assert!(paths.iter().any(|q| q == p), "{p} missing from {paths:?}");
+    }
+    let by = |p: &str| {
+        doc.nodes()
+            .iter()
+            .find(|n| lazyprop::yaml_editor::document::path_to_string(&n.path) == p)
+            .unwrap()
+    };
+    // Flow contents are navigable but not edited in place.
+    assert!(!by("f.a").is_editable_scalar());
+    assert_eq!(
+        doc.value_source(by("key").id),
+        Some("value on the next line")
+    );
+    assert!(by("key").is_editable_scalar());
+    assert!(by("base.host").is_editable_scalar());
+    assert!(!by("copy").is_editable_scalar());
+    assert_eq!(doc.value_source(by("text").id), Some("|"));
+    assert!(!by("multi").is_editable_scalar());
+}
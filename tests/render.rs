@@ -54,7 +54,7 @@ fn about_screen_renders_help_and_info() {
         .unwrap();
 
     // Tall enough to fit the logo art plus all sections without scrolling.
-    let mut terminal = Terminal::new(TestBackend::new(100, 60)).unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(100, 64)).unwrap();
     terminal
         .draw(|frame| {
             about